@@ -0,0 +1,34 @@
+use futures::StreamExt;
+use lss_driver::{SharedLSSDriver, TelemetryField, TelemetryPoller};
+use std::time::Duration;
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt()]
+struct Args {
+    #[structopt(about = "Serial port to use")]
+    port: String,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Args = Args::from_args();
+    let driver = SharedLSSDriver::new(lss_driver::LSSDriver::new(&args.port)?);
+    let mut search = driver.clone();
+    let mut poller = TelemetryPoller::new(driver.clone());
+    for i in 0..254 {
+        if search.query_status(i).await.is_ok() {
+            println!("Found servo with ID {}", i);
+            poller = poller
+                .poll(i, TelemetryField::Position, Duration::from_millis(50))
+                .poll(i, TelemetryField::Current, Duration::from_millis(200))
+                .poll(i, TelemetryField::Temperature, Duration::from_secs(2));
+        }
+    }
+    let mut samples = poller.start();
+    while let Some(sample) = samples.next().await {
+        println!("{} {:?}", sample.id, sample.value);
+    }
+    println!("{:?}", samples.stats());
+    Ok(())
+}
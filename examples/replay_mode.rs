@@ -1,11 +1,15 @@
 use async_std::io;
 use async_std::task::sleep;
+use lss_driver::GroupTiming;
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use structopt::StructOpt;
 
+/// Time between recorded samples
+const STEP: Duration = Duration::from_millis(50);
+
 #[derive(StructOpt, Debug)]
 #[structopt()]
 struct Args {
@@ -76,7 +80,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             history.get_mut(id).unwrap().push(position);
         }
         step_counter += 1;
-        sleep(Duration::from_millis(50)).await;
+        sleep(STEP).await;
     }
     running.store(true, Ordering::SeqCst);
     println!("Finished recording!");
@@ -93,10 +97,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             .set_color(lss_driver::BROADCAST_ID, lss_driver::LedColor::Cyan)
             .await?;
         for step in (0..step_counter).rev() {
-            for (id, record) in &history {
-                driver.move_to_position(**id, record[step]).await?;
-            }
-            sleep(Duration::from_millis(50)).await;
+            let targets = history
+                .iter()
+                .map(|(id, record)| (**id, record[step]))
+                .collect();
+            driver
+                .start_group_move(&targets, GroupTiming::Timed(STEP))
+                .await?;
+            sleep(STEP).await;
             if !running.load(Ordering::SeqCst) {
                 break;
             }
@@ -113,10 +121,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             .set_color(lss_driver::BROADCAST_ID, lss_driver::LedColor::Cyan)
             .await?;
         for step in 0..step_counter {
-            for (id, record) in &history {
-                driver.move_to_position(**id, record[step]).await?;
-            }
-            sleep(Duration::from_millis(50)).await;
+            let targets = history
+                .iter()
+                .map(|(id, record)| (**id, record[step]))
+                .collect();
+            driver
+                .start_group_move(&targets, GroupTiming::Timed(STEP))
+                .await?;
+            sleep(STEP).await;
             if !running.load(Ordering::SeqCst) {
                 break;
             }
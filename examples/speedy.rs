@@ -1,4 +1,5 @@
 use async_std::task::sleep;
+use lss_driver::WaitOptions;
 // use std::thread::sleep;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
@@ -34,18 +35,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         driver.move_to_position(5, 120.0).await?;
         driver.set_color(5, lss_driver::LedColor::Blue).await?;
         sleep(Duration::from_millis(50)).await;
-        while driver.query_status(5).await? != lss_driver::MotorStatus::Holding {
-            sleep(Duration::from_millis(20)).await;
-        }
+        driver
+            .wait_for_status(5, lss_driver::MotorStatus::Holding, WaitOptions::default())
+            .await?;
         if !running.load(Ordering::Acquire) {
             break;
         }
         driver.move_to_position(5, -120.0).await?;
         driver.set_color(5, lss_driver::LedColor::Red).await?;
         sleep(Duration::from_millis(50)).await;
-        while driver.query_status(5).await? != lss_driver::MotorStatus::Holding {
-            sleep(Duration::from_millis(20)).await;
-        }
+        driver
+            .wait_for_status(5, lss_driver::MotorStatus::Holding, WaitOptions::default())
+            .await?;
     }
     driver.limp(5).await?;
     driver.set_color(5, lss_driver::LedColor::Cyan).await?;
@@ -0,0 +1,170 @@
+//! Virtual LSS bus exposed on a pseudo-terminal
+//!
+//! Point any program using `LSSDriver::new` at the printed `/dev/pts/N` path
+//! to talk to simulated servos instead of hardware.
+
+use lss_driver::simulator::{Faults, VirtualBus, VirtualServo};
+use lss_driver::{Model, SafeModeStatus};
+#[cfg(unix)]
+use nix::{
+    pty::openpty,
+    sys::termios::{cfmakeraw, tcgetattr, tcsetattr, SetArg},
+    unistd::ttyname,
+};
+#[cfg(unix)]
+use std::fs::File;
+#[cfg(unix)]
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+use structopt::StructOpt;
+
+/// Servo given as `ID` or `ID:MODEL`. e.g. `5:HS1`
+#[derive(Debug)]
+struct ServoArg {
+    id: u8,
+    model: Model,
+}
+
+impl FromStr for ServoArg {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut split = text.split(':');
+        let id = split
+            .next()
+            .unwrap_or_default()
+            .parse()
+            .map_err(|_| format!("Invalid servo ID in {:?}", text))?;
+        let model = match split.next() {
+            None | Some("ST1") => Model::ST1,
+            Some("HS1") => Model::HS1,
+            Some("HT1") => Model::HT1,
+            Some(other) => return Err(format!("Unknown model {:?}. Use ST1, HS1 or HT1", other)),
+        };
+        Ok(ServoArg { id, model })
+    }
+}
+
+fn parse_safe_mode_reason(text: &str) -> Result<SafeModeStatus, String> {
+    match text {
+        "current" => Ok(SafeModeStatus::CurrentLimit),
+        "voltage" => Ok(SafeModeStatus::InputVoltageOutOfRange),
+        "temperature" => Ok(SafeModeStatus::TemperatureLimit),
+        other => Err(format!(
+            "Unknown reason {:?}. Use current, voltage or temperature",
+            other
+        )),
+    }
+}
+
+#[derive(StructOpt, Debug)]
+#[structopt(about = "Simulated LSS servo bus on a pseudo-terminal")]
+struct Args {
+    /// Servos on the bus as ID or ID:MODEL (ST1, HS1, HT1)
+    #[structopt(long = "servo", default_value = "1:ST1")]
+    servos: Vec<ServoArg>,
+    /// Physical speed limit of servos in °/s
+    #[structopt(long)]
+    speed_limit: Option<f32>,
+    /// Drop every n-th response to simulate timeouts
+    #[structopt(long)]
+    drop_every: Option<u32>,
+    /// Corrupt every n-th response
+    #[structopt(long)]
+    garble_every: Option<u32>,
+    /// Trip safe mode on each servo after it receives this many commands
+    #[structopt(long)]
+    safe_mode_after: Option<u32>,
+    /// Safe mode reason (current, voltage, temperature)
+    #[structopt(long, default_value = "current", parse(try_from_str = parse_safe_mode_reason))]
+    safe_mode_reason: SafeModeStatus,
+    /// Create a symlink to the pseudo-terminal at this path
+    #[structopt(long)]
+    link: Option<PathBuf>,
+}
+
+/// Point `link` at `target`
+///
+/// Only an existing symlink is replaced so that a typo can't delete a regular file
+#[cfg(unix)]
+fn replace_link(
+    target: &std::path::Path,
+    link: &std::path::Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match std::fs::symlink_metadata(link) {
+        Ok(metadata) if metadata.file_type().is_symlink() => std::fs::remove_file(link)?,
+        Ok(_) => {
+            return Err(format!(
+                "{} already exists and is not a symlink. Refusing to replace it",
+                link.display()
+            )
+            .into())
+        }
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => (),
+        Err(err) => return Err(err.into()),
+    }
+    std::os::unix::fs::symlink(target, link)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn main() {
+    eprintln!("lss-virtual-bus needs a pseudo-terminal and only runs on unix");
+}
+
+#[cfg(unix)]
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Args = Args::from_args();
+
+    let servos = args
+        .servos
+        .into_iter()
+        .map(|servo| {
+            let virtual_servo = VirtualServo::new(servo.id, servo.model);
+            match args.speed_limit {
+                Some(speed_limit) => virtual_servo.with_speed_limit(speed_limit),
+                None => virtual_servo,
+            }
+        })
+        .collect();
+    let bus = VirtualBus::new(servos).with_faults(Faults {
+        drop_every: args.drop_every,
+        garble_every: args.garble_every,
+        safe_mode_after: args.safe_mode_after,
+        safe_mode_reason: args.safe_mode_reason,
+    });
+
+    let pty = openpty(None, None)?;
+    let mut termios = tcgetattr(&pty.slave)?;
+    cfmakeraw(&mut termios);
+    tcsetattr(&pty.slave, SetArg::TCSANOW, &termios)?;
+    let path = ttyname(&pty.slave)?;
+    if let Some(link) = &args.link {
+        replace_link(&path, link)?;
+        println!(
+            "Virtual LSS bus listening on {} ({})",
+            path.display(),
+            link.display()
+        );
+    } else {
+        println!("Virtual LSS bus listening on {}", path.display());
+    }
+
+    // slave side is kept open so the terminal doesn't hang up when clients disconnect
+    let _slave = pty.slave;
+    let mut master = File::from(pty.master);
+    let mut buffer = Vec::new();
+    let mut chunk = [0; 256];
+    loop {
+        let read = master.read(&mut chunk)?;
+        buffer.extend_from_slice(&chunk[..read]);
+        while let Some(end) = buffer.iter().position(|byte| *byte == b'\r') {
+            let frame: Vec<u8> = buffer.drain(..=end).collect();
+            let frame = String::from_utf8_lossy(&frame);
+            for response in bus.process(&frame) {
+                master.write_all(response.as_bytes())?;
+            }
+        }
+    }
+}
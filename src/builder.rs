@@ -0,0 +1,465 @@
+use crate::message_types::{LssDriverError, ResponseValidation, RetryPolicy};
+use crate::serial_driver::{FramedSerialDriver, DEFAULT_BAUD_RATE, DEFAULT_TIMEOUT};
+use crate::transport::{FramedDriver, LssCommand, LssResponse};
+use crate::{LSSDriver, BROADCAST_ID, SUPPORTED_BAUD_RATES};
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+use std::time::Duration;
+
+type DriverResult<T> = Result<T, LssDriverError>;
+
+type CommandHook = Arc<dyn Fn(&LssCommand) + Send + Sync>;
+type ResponseHook = Arc<dyn Fn(&LssResponse) + Send + Sync>;
+
+/// Shortest time to wait for an answer while detecting the baud rate
+///
+/// Slow baud rates need more than the default timeout
+const PROBE_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// Settings of [LSSDriver] that don't depend on the transport
+#[derive(Clone, Default)]
+pub(crate) struct DriverSettings {
+    pub(crate) response_validation: ResponseValidation,
+    /// `None` leaves it up to the transport
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) retry_policy: RetryPolicy,
+    on_command: Option<CommandHook>,
+    on_response: Option<ResponseHook>,
+}
+
+impl DriverSettings {
+    pub(crate) fn log_command(&self, command: &LssCommand) {
+        if let Some(hook) = &self.on_command {
+            hook(command);
+        }
+    }
+
+    pub(crate) fn log_response(&self, response: &LssResponse) {
+        if let Some(hook) = &self.on_response {
+            hook(response);
+        }
+    }
+}
+
+enum Transport {
+    Serial { port: String, baud_rate: u32 },
+    Custom(Box<dyn FramedDriver + Send + Sync>),
+}
+
+/// Builder for [LSSDriver]
+///
+/// All connection options are set here.
+/// Created with [LSSDriver::builder] for serial ports
+/// or [LSSDriverBuilder::with_transport] for custom transports.
+///
+/// # Example
+///
+/// ```no_run
+/// use lss_driver::{LSSDriver, ResponseValidation, RetryPolicy};
+/// use std::time::Duration;
+/// let mut driver = LSSDriver::builder("/dev/ttyUSB0")
+///     .baud_rate(9600)
+///     .timeout(Duration::from_millis(50))
+///     .retry_policy(RetryPolicy::new(2, Duration::from_millis(10)))
+///     .response_validation(ResponseValidation::DiscardStale)
+///     .on_command(|command| println!("-> {}", command.as_str()))
+///     .on_response(|response| println!("<- {}", response.as_str()))
+///     .build()
+///     .unwrap();
+/// ```
+pub struct LSSDriverBuilder {
+    transport: Transport,
+    settings: DriverSettings,
+}
+
+impl LSSDriverBuilder {
+    /// Start building a driver on a serial port
+    ///
+    /// # Arguments
+    ///
+    /// * `port` - Port to use. e.g. COM1 or /dev/ttyACM0
+    pub fn new(port: &str) -> LSSDriverBuilder {
+        LSSDriverBuilder {
+            transport: Transport::Serial {
+                port: port.to_owned(),
+                baud_rate: DEFAULT_BAUD_RATE,
+            },
+            settings: DriverSettings::default(),
+        }
+    }
+
+    /// Start building a driver on top of a custom transport
+    ///
+    /// Look at [FramedDriver] for the contract the transport has to uphold
+    ///
+    /// # Arguments
+    ///
+    /// * `transport` - Transport to use
+    pub fn with_transport(transport: Box<dyn FramedDriver + Send + Sync>) -> LSSDriverBuilder {
+        LSSDriverBuilder {
+            transport: Transport::Custom(transport),
+            settings: DriverSettings::default(),
+        }
+    }
+
+    /// Set baud rate
+    ///
+    /// Default is 115200. Ignored for custom transports
+    ///
+    /// # Arguments
+    ///
+    /// * `baud_rate` - Baudrate. e.g. 115200
+    pub fn baud_rate(mut self, baud_rate: u32) -> LSSDriverBuilder {
+        if let Transport::Serial {
+            baud_rate: rate, ..
+        } = &mut self.transport
+        {
+            *rate = baud_rate;
+        }
+        self
+    }
+
+    /// Set how long to wait for responses
+    ///
+    /// Default is 10ms for serial ports. Slow baud rates and long cables need more.
+    /// Can be changed later with [LSSDriver::set_timeout] or for a single call with [LSSDriver::call_timeout]
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - How long to wait for each response
+    pub fn timeout(mut self, timeout: Duration) -> LSSDriverBuilder {
+        self.settings.timeout = Some(timeout);
+        self
+    }
+
+    /// Set when failed commands are retried
+    ///
+    /// Default is no retries
+    ///
+    /// # Arguments
+    ///
+    /// * `retry_policy` - Retry policy
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> LSSDriverBuilder {
+        self.settings.retry_policy = retry_policy;
+        self
+    }
+
+    /// Set how responses are matched against queries
+    ///
+    /// Default is [ResponseValidation::Strict]
+    ///
+    /// # Arguments
+    ///
+    /// * `response_validation` - Validation mode
+    pub fn response_validation(
+        mut self,
+        response_validation: ResponseValidation,
+    ) -> LSSDriverBuilder {
+        self.settings.response_validation = response_validation;
+        self
+    }
+
+    /// Call `hook` with every command before it is sent
+    ///
+    /// Retried commands are passed to the hook on every attempt
+    ///
+    /// # Arguments
+    ///
+    /// * `hook` - Function to call
+    pub fn on_command(
+        mut self,
+        hook: impl Fn(&LssCommand) + Send + Sync + 'static,
+    ) -> LSSDriverBuilder {
+        self.settings.on_command = Some(Arc::new(hook));
+        self
+    }
+
+    /// Call `hook` with every response that was received
+    ///
+    /// # Arguments
+    ///
+    /// * `hook` - Function to call
+    pub fn on_response(
+        mut self,
+        hook: impl Fn(&LssResponse) + Send + Sync + 'static,
+    ) -> LSSDriverBuilder {
+        self.settings.on_response = Some(Arc::new(hook));
+        self
+    }
+
+    /// Open the port and create the driver
+    ///
+    /// Fails with [LssDriverError::FailedOpeningSerialPort] if the port can't be opened
+    pub fn build(self) -> DriverResult<LSSDriver> {
+        let transport: Box<dyn FramedDriver + Send + Sync> = match self.transport {
+            Transport::Serial { port, baud_rate } => {
+                let timeout = self.settings.timeout.unwrap_or(DEFAULT_TIMEOUT);
+                Box::new(FramedSerialDriver::with_timeout(&port, baud_rate, timeout)?)
+            }
+            Transport::Custom(transport) => transport,
+        };
+        Ok(LSSDriver::with_settings(transport, self.settings))
+    }
+
+    /// Open the port at the baud rate servos answer on and create the driver
+    ///
+    /// Tries 115200 first and then the rest of [SUPPORTED_BAUD_RATES].
+    /// Each one is probed with a `QID` broadcast. Returns the driver and the detected baud rate.
+    /// The configured baud rate is ignored.
+    ///
+    /// Baud rates the port can't be opened at are skipped.
+    ///
+    /// Fails with [LssDriverError::BaudRateNotDetected] if no servo answers
+    /// and for custom transports, which can't be reopened.
+    /// Fails with the last error opening the port if it couldn't be opened at any baud rate.
+    pub async fn detect_baud_rate(self) -> DriverResult<(LSSDriver, u32)> {
+        let port = match self.transport {
+            Transport::Serial { port, .. } => port,
+            Transport::Custom(_) => {
+                return Err(LssDriverError::BaudRateNotDetected {
+                    port: "custom transport".to_owned(),
+                })
+            }
+        };
+        let timeout = self.settings.timeout.unwrap_or(DEFAULT_TIMEOUT);
+        detect_baud_rate(&port, self.settings, |baud_rate| {
+            let transport = FramedSerialDriver::with_timeout(&port, baud_rate, timeout)?;
+            Ok(Box::new(transport))
+        })
+        .await
+    }
+}
+
+/// Open a transport at each supported baud rate until a servo answers
+pub(crate) async fn detect_baud_rate(
+    port: &str,
+    settings: DriverSettings,
+    mut open: impl FnMut(u32) -> DriverResult<Box<dyn FramedDriver + Send + Sync>>,
+) -> DriverResult<(LSSDriver, u32)> {
+    let probe_timeout = settings.timeout.unwrap_or_default().max(PROBE_TIMEOUT);
+    let baud_rates = std::iter::once(DEFAULT_BAUD_RATE).chain(
+        SUPPORTED_BAUD_RATES
+            .into_iter()
+            .filter(|baud_rate| *baud_rate != DEFAULT_BAUD_RATE),
+    );
+    let mut open_error = None;
+    let mut opened_any = false;
+    for baud_rate in baud_rates {
+        // some adapters refuse non-standard rates. Skip them and keep probing
+        let transport = match open(baud_rate) {
+            Ok(transport) => transport,
+            Err(err) => {
+                open_error = Some(err);
+                continue;
+            }
+        };
+        opened_any = true;
+        let mut driver = LSSDriver::with_settings(transport, settings.clone());
+        let probe = driver
+            .call_timeout(probe_timeout)
+            .query_id(BROADCAST_ID)
+            .await;
+        if probe.is_ok() {
+            return Ok((driver, baud_rate));
+        }
+    }
+    match open_error {
+        Some(err) if !opened_any => Err(err),
+        _ => Err(LssDriverError::BaudRateNotDetected {
+            port: port.to_owned(),
+        }),
+    }
+}
+
+/// Handle to [LSSDriver] that uses a different timeout
+///
+/// Created with [LSSDriver::call_timeout].
+/// Restores the previous timeout when dropped.
+pub struct CallTimeout<'a> {
+    driver: &'a mut LSSDriver,
+    previous: Option<Duration>,
+}
+
+impl<'a> CallTimeout<'a> {
+    pub(crate) fn new(driver: &'a mut LSSDriver, timeout: Duration) -> CallTimeout<'a> {
+        let previous = driver.settings.timeout.replace(timeout);
+        CallTimeout { driver, previous }
+    }
+}
+
+impl Deref for CallTimeout<'_> {
+    type Target = LSSDriver;
+
+    fn deref(&self) -> &Self::Target {
+        self.driver
+    }
+}
+
+impl DerefMut for CallTimeout<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.driver
+    }
+}
+
+impl Drop for CallTimeout<'_> {
+    fn drop(&mut self) {
+        self.driver.settings.timeout = self.previous;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulator::{VirtualBus, VirtualServo};
+    use crate::Model;
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    /// Transport that only reaches the bus when opened at the right baud rate
+    struct BaudGate {
+        bus: VirtualBus,
+        matches: bool,
+    }
+
+    #[async_trait]
+    impl FramedDriver for BaudGate {
+        async fn send(&mut self, command: LssCommand) -> DriverResult<()> {
+            if self.matches {
+                self.bus.send(command).await?;
+            }
+            Ok(())
+        }
+
+        async fn receive(&mut self) -> DriverResult<LssResponse> {
+            self.bus.receive().await
+        }
+    }
+
+    #[tokio::test]
+    async fn baud_rate_is_detected() {
+        let bus = VirtualBus::new(vec![VirtualServo::new(5, Model::ST1)]);
+        let opened = Arc::new(Mutex::new(Vec::new()));
+        let log = opened.clone();
+        let (mut driver, baud_rate) =
+            detect_baud_rate("virtual", DriverSettings::default(), |baud_rate| {
+                log.lock().unwrap().push(baud_rate);
+                Ok(Box::new(BaudGate {
+                    bus: bus.clone(),
+                    matches: baud_rate == 38400,
+                }))
+            })
+            .await
+            .unwrap();
+        assert_eq!(baud_rate, 38400);
+        assert_eq!(*opened.lock().unwrap(), vec![115200, 9600, 19200, 38400]);
+        assert_eq!(driver.query_id(BROADCAST_ID).await.unwrap(), 5);
+        assert_eq!(driver.settings.timeout, None);
+    }
+
+    #[tokio::test]
+    async fn baud_rates_that_fail_to_open_are_skipped() {
+        let bus = VirtualBus::new(vec![VirtualServo::new(5, Model::ST1)]);
+        let (_, baud_rate) = detect_baud_rate("virtual", DriverSettings::default(), |baud_rate| {
+            if baud_rate == 250000 {
+                return Err(LssDriverError::FailedOpeningSerialPort {
+                    port: "virtual".to_owned(),
+                    source: tokio_serial::Error::new(
+                        tokio_serial::ErrorKind::InvalidInput,
+                        "Unsupported baud rate",
+                    ),
+                });
+            }
+            Ok(Box::new(BaudGate {
+                bus: bus.clone(),
+                matches: baud_rate == 460800,
+            }))
+        })
+        .await
+        .unwrap();
+        assert_eq!(baud_rate, 460800);
+
+        let err = detect_baud_rate("virtual", DriverSettings::default(), |_| {
+            Err(LssDriverError::FailedOpeningSerialPort {
+                port: "virtual".to_owned(),
+                source: tokio_serial::Error::new(tokio_serial::ErrorKind::NoDevice, "Unplugged"),
+            })
+        })
+        .await
+        .err()
+        .unwrap();
+        assert!(matches!(
+            err,
+            LssDriverError::FailedOpeningSerialPort { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn silent_bus_fails_detection() {
+        let bus = VirtualBus::new(vec![]);
+        let err = detect_baud_rate("virtual", DriverSettings::default(), |_| {
+            Ok(Box::new(bus.clone()))
+        })
+        .await
+        .err()
+        .unwrap();
+        assert!(matches!(err, LssDriverError::BaudRateNotDetected { .. }));
+        assert!(err.to_string().contains("virtual"));
+    }
+
+    #[tokio::test]
+    async fn hooks_see_commands_and_responses() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let bus = VirtualBus::new(vec![VirtualServo::new(5, Model::ST1).with_position(13.2)]);
+        let command_log = log.clone();
+        let response_log = log.clone();
+        let mut driver = LSSDriverBuilder::with_transport(Box::new(bus))
+            .on_command(move |command| {
+                command_log
+                    .lock()
+                    .unwrap()
+                    .push(command.as_str().to_owned())
+            })
+            .on_response(move |response| {
+                response_log
+                    .lock()
+                    .unwrap()
+                    .push(response.as_str().to_owned())
+            })
+            .build()
+            .unwrap();
+        driver.limp(5).await.unwrap();
+        driver.query_position(5).await.unwrap();
+        assert_eq!(*log.lock().unwrap(), vec!["#5L\r", "#5QD\r", "*5QD132\r"]);
+    }
+
+    #[tokio::test]
+    async fn settings_are_applied() {
+        let bus = VirtualBus::new(vec![]);
+        let driver = LSSDriverBuilder::with_transport(Box::new(bus))
+            .timeout(Duration::from_millis(30))
+            .retry_policy(RetryPolicy::new(2, Duration::ZERO))
+            .response_validation(ResponseValidation::Disabled)
+            .build()
+            .unwrap();
+        assert_eq!(driver.settings.timeout, Some(Duration::from_millis(30)));
+        assert_eq!(driver.settings.retry_policy.retries, 2);
+        assert_eq!(
+            driver.settings.response_validation,
+            ResponseValidation::Disabled
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_port_error_names_port() {
+        let err = LSSDriverBuilder::new("/dev/does-not-exist")
+            .build()
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err,
+            LssDriverError::FailedOpeningSerialPort { .. }
+        ));
+        assert!(err.to_string().contains("/dev/does-not-exist"));
+        assert!(std::error::Error::source(&err).is_some());
+    }
+}
@@ -0,0 +1,580 @@
+use crate::message_types::{LedColor, LssDriverError, MotorStatus, SafeModeStatus};
+use crate::LSSDriver;
+use std::collections::BTreeMap;
+use std::ops::DerefMut;
+use tokio::time::{Duration, Instant};
+
+type DriverResult<T> = Result<T, LssDriverError>;
+
+/// How bad the state of a servo is
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Ok,
+    Warning,
+    Critical,
+}
+
+/// Limits checked by [HealthMonitor]
+///
+/// Defaults are conservative values for LSS servos on a 12V supply
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct HealthThresholds {
+    /// Temperature in celsius that is reported as a warning
+    pub warning_temperature: f32,
+    /// Temperature in celsius that is critical
+    pub critical_temperature: f32,
+    /// Lowest voltage in volts that isn't reported as a warning
+    pub min_voltage: f32,
+    /// Highest voltage in volts that isn't reported as a warning
+    pub max_voltage: f32,
+    /// Current in Amps that is critical if it lasts for `overcurrent_time`
+    pub max_current: f32,
+    /// How long current has to stay above `max_current`
+    pub overcurrent_time: Duration,
+}
+
+impl Default for HealthThresholds {
+    fn default() -> Self {
+        HealthThresholds {
+            warning_temperature: 60.0,
+            critical_temperature: 75.0,
+            min_voltage: 6.0,
+            max_voltage: 13.0,
+            max_current: 1.5,
+            overcurrent_time: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Problem found by [HealthMonitor]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum HealthCondition {
+    /// Temperature in celsius above the warning threshold
+    HighTemperature(f32),
+    /// Temperature in celsius above the critical threshold
+    CriticalTemperature(f32),
+    /// Voltage in volts below the minimum
+    UnderVoltage(f32),
+    /// Voltage in volts above the maximum
+    OverVoltage(f32),
+    /// Current in Amps that stayed above the maximum for `duration`
+    Overcurrent { current: f32, duration: Duration },
+    /// Servo tripped its own safe mode
+    SafeMode(SafeModeStatus),
+    /// Servo didn't answer or its reply couldn't be read
+    Unreachable,
+}
+
+impl HealthCondition {
+    pub fn severity(&self) -> Severity {
+        match self {
+            HealthCondition::HighTemperature(_)
+            | HealthCondition::UnderVoltage(_)
+            | HealthCondition::OverVoltage(_) => Severity::Warning,
+            HealthCondition::CriticalTemperature(_)
+            | HealthCondition::Overcurrent { .. }
+            | HealthCondition::SafeMode(_)
+            | HealthCondition::Unreachable => Severity::Critical,
+        }
+    }
+
+    /// Whether this condition makes [HealthMonitor] take its [ProtectiveAction]
+    ///
+    /// Every critical condition does except [HealthCondition::Unreachable].
+    /// A failed read says nothing about the servo itself, so it never limps or halts it
+    pub fn triggers_action(&self) -> bool {
+        self.severity() == Severity::Critical && *self != HealthCondition::Unreachable
+    }
+}
+
+/// What [HealthMonitor] does when a servo becomes critical
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ProtectiveAction {
+    /// Only report it
+    None,
+    /// [LSSDriver::limp]
+    Limp,
+    /// [LSSDriver::halt_hold]
+    HaltHold,
+}
+
+/// LED colors [HealthMonitor] uses to show severity
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LedFeedback {
+    pub ok: LedColor,
+    pub warning: LedColor,
+    pub critical: LedColor,
+}
+
+impl LedFeedback {
+    /// Color for `severity`
+    pub fn color(&self, severity: Severity) -> LedColor {
+        match severity {
+            Severity::Ok => self.ok,
+            Severity::Warning => self.warning,
+            Severity::Critical => self.critical,
+        }
+    }
+}
+
+impl Default for LedFeedback {
+    fn default() -> Self {
+        LedFeedback {
+            ok: LedColor::Green,
+            warning: LedColor::Yellow,
+            critical: LedColor::Red,
+        }
+    }
+}
+
+/// Change of severity of a servo
+#[derive(Clone, Debug, PartialEq)]
+pub struct HealthEvent {
+    /// ID of the servo
+    pub id: u8,
+    /// When the values were read
+    pub timestamp: Instant,
+    /// New severity
+    pub severity: Severity,
+    /// Everything that is wrong. Empty if the servo recovered
+    pub conditions: Vec<HealthCondition>,
+    /// Action taken because of this event. `None` if it couldn't be sent
+    pub action: Option<ProtectiveAction>,
+}
+
+/// Record of a [ProtectiveAction] taken by [HealthMonitor]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ActionRecord {
+    /// ID of the servo
+    pub id: u8,
+    /// When the action was taken
+    pub timestamp: Instant,
+    pub action: ProtectiveAction,
+    /// First condition that [triggered](HealthCondition::triggers_action) the action
+    pub reason: HealthCondition,
+}
+
+struct WatchedServo {
+    thresholds: HealthThresholds,
+    severity: Severity,
+    overcurrent_since: Option<Instant>,
+    failed_reads: u32,
+    protected: bool,
+}
+
+/// Watches temperature, voltage, current and safe mode of servos
+///
+/// Every [check](HealthMonitor::check) reads all watched servos and returns an event
+/// for each servo whose severity changed. When a servo becomes critical the configured
+/// [ProtectiveAction] is taken once and recorded in [actions](HealthMonitor::actions).
+/// A servo that can't be read is reported as [HealthCondition::Unreachable] and counted
+/// in [failed_reads](HealthMonitor::failed_reads), but no action is taken because of it.
+///
+/// Use a [SharedLSSDriver](crate::SharedLSSDriver) to keep using the bus from other tasks.
+///
+/// # Example
+///
+/// ```no_run
+/// use lss_driver::{HealthMonitor, HealthThresholds, LSSDriver, LedFeedback, ProtectiveAction};
+/// use std::time::Duration;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let mut driver = LSSDriver::new("COM1").unwrap();
+///     let mut monitor = HealthMonitor::new(&mut driver)
+///         .watch(1, HealthThresholds::default())
+///         .watch(2, HealthThresholds::default())
+///         .led_feedback(LedFeedback::default())
+///         .critical_action(ProtectiveAction::Limp);
+///     loop {
+///         for event in monitor.check().await {
+///             println!("{:?}", event);
+///         }
+///         tokio::time::sleep(Duration::from_millis(500)).await;
+///     }
+/// }
+/// ```
+pub struct HealthMonitor<D> {
+    driver: D,
+    servos: BTreeMap<u8, WatchedServo>,
+    led_feedback: Option<LedFeedback>,
+    critical_action: ProtectiveAction,
+    actions: Vec<ActionRecord>,
+}
+
+impl<D: DerefMut<Target = LSSDriver>> HealthMonitor<D> {
+    /// Create monitor without any watched servos
+    ///
+    /// Doesn't change LEDs and only reports critical servos by default
+    ///
+    /// # Arguments
+    ///
+    /// * `driver` - Driver to use. e.g. `&mut LSSDriver` or [SharedLSSDriver](crate::SharedLSSDriver)
+    pub fn new(driver: D) -> HealthMonitor<D> {
+        HealthMonitor {
+            driver,
+            servos: BTreeMap::new(),
+            led_feedback: None,
+            critical_action: ProtectiveAction::None,
+            actions: vec![],
+        }
+    }
+
+    /// Watch servo `id`
+    ///
+    /// Watching the same servo again replaces its thresholds
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of the servo
+    /// * `thresholds` - Limits for this servo
+    pub fn watch(mut self, id: u8, thresholds: HealthThresholds) -> HealthMonitor<D> {
+        self.servos.insert(
+            id,
+            WatchedServo {
+                thresholds,
+                severity: Severity::Ok,
+                overcurrent_since: None,
+                failed_reads: 0,
+                protected: false,
+            },
+        );
+        self
+    }
+
+    /// Set LED color of servos when their severity changes
+    pub fn led_feedback(mut self, led_feedback: LedFeedback) -> HealthMonitor<D> {
+        self.led_feedback = Some(led_feedback);
+        self
+    }
+
+    /// Action taken when a servo becomes critical
+    pub fn critical_action(mut self, critical_action: ProtectiveAction) -> HealthMonitor<D> {
+        self.critical_action = critical_action;
+        self
+    }
+
+    /// Last known severity of servo `id`. `None` if it isn't watched
+    pub fn severity(&self, id: u8) -> Option<Severity> {
+        self.servos.get(&id).map(|servo| servo.severity)
+    }
+
+    /// Checks in a row in which servo `id` couldn't be read. `None` if it isn't watched
+    pub fn failed_reads(&self, id: u8) -> Option<u32> {
+        self.servos.get(&id).map(|servo| servo.failed_reads)
+    }
+
+    /// Every protective action taken so far
+    pub fn actions(&self) -> &[ActionRecord] {
+        &self.actions
+    }
+
+    /// Give back the driver
+    pub fn into_driver(self) -> D {
+        self.driver
+    }
+
+    /// Read all watched servos once
+    ///
+    /// Returns events for servos whose severity changed or that a protective action was taken on.
+    /// A servo that fails to answer doesn't stop the others from being checked.
+    /// An action that can't be sent is tried again on the next check.
+    /// If the LED color can't be sent the severity isn't updated,
+    /// so the event is reported again on the next check.
+    /// Overcurrent is only detected if checks happen more often than `overcurrent_time`
+    pub async fn check(&mut self) -> Vec<HealthEvent> {
+        let mut events = vec![];
+        for (&id, servo) in self.servos.iter_mut() {
+            let driver = &mut *self.driver;
+            let conditions = match read_conditions(driver, id, servo).await {
+                Ok(conditions) => {
+                    servo.failed_reads = 0;
+                    conditions
+                }
+                Err(_) => {
+                    servo.failed_reads += 1;
+                    vec![HealthCondition::Unreachable]
+                }
+            };
+            let now = Instant::now();
+            let severity = conditions
+                .iter()
+                .map(HealthCondition::severity)
+                .max()
+                .unwrap_or(Severity::Ok);
+            if severity != Severity::Critical {
+                servo.protected = false;
+            }
+            let mut action = None;
+            let reason = conditions
+                .iter()
+                .find(|condition| condition.triggers_action());
+            if let Some(&reason) = reason {
+                if !servo.protected && self.critical_action != ProtectiveAction::None {
+                    let result = match self.critical_action {
+                        ProtectiveAction::Limp => driver.limp(id).await,
+                        ProtectiveAction::HaltHold => driver.halt_hold(id).await,
+                        ProtectiveAction::None => Ok(()),
+                    };
+                    if result.is_ok() {
+                        self.actions.push(ActionRecord {
+                            id,
+                            timestamp: now,
+                            action: self.critical_action,
+                            reason,
+                        });
+                        servo.protected = true;
+                        action = Some(self.critical_action);
+                    }
+                }
+            }
+            if severity == servo.severity && action.is_none() {
+                continue;
+            }
+            let mut sent = true;
+            if let Some(led_feedback) = &self.led_feedback {
+                if severity != servo.severity {
+                    sent = driver
+                        .set_color(id, led_feedback.color(severity))
+                        .await
+                        .is_ok();
+                }
+            }
+            events.push(HealthEvent {
+                id,
+                timestamp: now,
+                severity,
+                conditions,
+                action,
+            });
+            if sent {
+                servo.severity = severity;
+            }
+        }
+        events
+    }
+}
+
+/// Read values of servo `id` and compare them to its thresholds
+async fn read_conditions(
+    driver: &mut LSSDriver,
+    id: u8,
+    servo: &mut WatchedServo,
+) -> DriverResult<Vec<HealthCondition>> {
+    let temperature = driver.query_temperature(id).await?;
+    let voltage = driver.query_voltage(id).await?;
+    let current = driver.query_current(id).await?;
+    let status = driver.query_status(id).await?;
+    let now = Instant::now();
+    let thresholds = servo.thresholds;
+    let mut conditions = vec![];
+    if temperature >= thresholds.critical_temperature {
+        conditions.push(HealthCondition::CriticalTemperature(temperature));
+    } else if temperature >= thresholds.warning_temperature {
+        conditions.push(HealthCondition::HighTemperature(temperature));
+    }
+    if voltage < thresholds.min_voltage {
+        conditions.push(HealthCondition::UnderVoltage(voltage));
+    } else if voltage > thresholds.max_voltage {
+        conditions.push(HealthCondition::OverVoltage(voltage));
+    }
+    if current > thresholds.max_current {
+        let since = *servo.overcurrent_since.get_or_insert(now);
+        let duration = now - since;
+        if duration >= thresholds.overcurrent_time {
+            conditions.push(HealthCondition::Overcurrent { current, duration });
+        }
+    } else {
+        servo.overcurrent_since = None;
+    }
+    if status == MotorStatus::SafeMode {
+        let reason = driver.query_safety_status(id).await?;
+        conditions.push(HealthCondition::SafeMode(reason));
+    }
+    Ok(conditions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulator::{Faults, VirtualBus, VirtualServo};
+    use crate::Model;
+
+    fn bus_with_servos() -> (LSSDriver, VirtualBus) {
+        let bus = VirtualBus::new(vec![
+            VirtualServo::new(1, Model::ST1),
+            VirtualServo::new(2, Model::ST1),
+        ]);
+        (LSSDriver::with_driver(Box::new(bus.clone())), bus)
+    }
+
+    #[tokio::test]
+    async fn temperature_escalates_and_limps() {
+        let (mut driver, bus) = bus_with_servos();
+        driver.move_to_position(2, 90.0).await.unwrap();
+        let mut monitor = HealthMonitor::new(&mut driver)
+            .watch(1, HealthThresholds::default())
+            .watch(2, HealthThresholds::default())
+            .led_feedback(LedFeedback::default())
+            .critical_action(ProtectiveAction::Limp);
+        assert!(monitor.check().await.is_empty());
+
+        bus.servo(2, |servo| servo.set_temperature(65.0));
+        let events = monitor.check().await;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].id, 2);
+        assert_eq!(events[0].severity, Severity::Warning);
+        assert_eq!(
+            events[0].conditions,
+            vec![HealthCondition::HighTemperature(65.0)]
+        );
+        assert_eq!(events[0].action, None);
+        assert_eq!(bus.servo(2, |servo| servo.color()), Some(LedColor::Yellow));
+        // no event while nothing changes
+        assert!(monitor.check().await.is_empty());
+
+        bus.servo(2, |servo| servo.set_temperature(80.0));
+        let events = monitor.check().await;
+        assert_eq!(events[0].severity, Severity::Critical);
+        assert_eq!(events[0].action, Some(ProtectiveAction::Limp));
+        assert_eq!(
+            bus.servo(2, |servo| servo.status()),
+            Some(MotorStatus::Limp)
+        );
+        assert_eq!(bus.servo(2, |servo| servo.color()), Some(LedColor::Red));
+        assert_eq!(bus.servo(1, |servo| servo.color()), Some(LedColor::Off));
+        assert_eq!(monitor.actions().len(), 1);
+        assert_eq!(
+            monitor.actions()[0].reason,
+            HealthCondition::CriticalTemperature(80.0)
+        );
+
+        bus.servo(2, |servo| servo.set_temperature(40.0));
+        let events = monitor.check().await;
+        assert_eq!(events[0].severity, Severity::Ok);
+        assert!(events[0].conditions.is_empty());
+        assert_eq!(monitor.severity(2), Some(Severity::Ok));
+        assert_eq!(bus.servo(2, |servo| servo.color()), Some(LedColor::Green));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn overcurrent_has_to_be_sustained() {
+        let (mut driver, bus) = bus_with_servos();
+        let thresholds = HealthThresholds {
+            overcurrent_time: Duration::from_millis(500),
+            ..HealthThresholds::default()
+        };
+        let mut monitor = HealthMonitor::new(&mut driver)
+            .watch(1, thresholds)
+            .critical_action(ProtectiveAction::HaltHold);
+        bus.servo(1, |servo| servo.set_current(2.0));
+        assert!(monitor.check().await.is_empty());
+        tokio::time::advance(Duration::from_millis(300)).await;
+        assert!(monitor.check().await.is_empty());
+        tokio::time::advance(Duration::from_millis(300)).await;
+        let events = monitor.check().await;
+        assert_eq!(events[0].action, Some(ProtectiveAction::HaltHold));
+        assert_eq!(
+            events[0].conditions,
+            vec![HealthCondition::Overcurrent {
+                current: 2.0,
+                duration: Duration::from_millis(600)
+            }]
+        );
+        assert_eq!(
+            bus.servo(1, |servo| servo.status()),
+            Some(MotorStatus::Holding)
+        );
+        // LED is left alone without feedback
+        assert_eq!(bus.servo(1, |servo| servo.color()), Some(LedColor::Off));
+    }
+
+    #[tokio::test]
+    async fn safe_mode_and_voltage_are_reported() {
+        let (mut driver, bus) = bus_with_servos();
+        let mut monitor = HealthMonitor::new(&mut driver).watch(1, HealthThresholds::default());
+        bus.servo(1, |servo| {
+            servo.set_voltage(5.0);
+            servo.trip_safe_mode(SafeModeStatus::InputVoltageOutOfRange)
+        });
+        let events = monitor.check().await;
+        assert_eq!(events[0].severity, Severity::Critical);
+        assert_eq!(
+            events[0].conditions,
+            vec![
+                HealthCondition::UnderVoltage(5.0),
+                HealthCondition::SafeMode(SafeModeStatus::InputVoltageOutOfRange)
+            ]
+        );
+        // only reported by default
+        assert_eq!(events[0].action, None);
+        assert!(monitor.actions().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn silent_servo_doesnt_stop_the_check() {
+        let bus = VirtualBus::new(vec![VirtualServo::new(2, Model::ST1)]);
+        let mut driver = LSSDriver::with_driver(Box::new(bus.clone()));
+        // servo 1 isn't on the bus and never answers
+        let mut monitor = HealthMonitor::new(&mut driver)
+            .watch(1, HealthThresholds::default())
+            .watch(2, HealthThresholds::default())
+            .critical_action(ProtectiveAction::Limp);
+        bus.servo(2, |servo| servo.set_temperature(80.0));
+        let events = monitor.check().await;
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].id, 1);
+        assert_eq!(events[0].conditions, vec![HealthCondition::Unreachable]);
+        assert_eq!(events[0].severity, Severity::Critical);
+        assert_eq!(events[1].id, 2);
+        assert_eq!(
+            events[1].conditions,
+            vec![HealthCondition::CriticalTemperature(80.0)]
+        );
+        assert_eq!(events[1].action, Some(ProtectiveAction::Limp));
+        assert_eq!(
+            bus.servo(2, |servo| servo.status()),
+            Some(MotorStatus::Limp)
+        );
+        // a failed read is reported but never acted on
+        assert_eq!(events[0].action, None);
+        assert_eq!(monitor.actions().len(), 1);
+        assert_eq!(monitor.actions()[0].id, 2);
+        assert_eq!(monitor.severity(1), Some(Severity::Critical));
+        assert_eq!(monitor.severity(2), Some(Severity::Critical));
+        assert_eq!(monitor.failed_reads(1), Some(1));
+        assert_eq!(monitor.failed_reads(2), Some(0));
+        // nothing changed so nothing is reported again
+        assert!(monitor.check().await.is_empty());
+        assert_eq!(monitor.failed_reads(1), Some(2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn critical_servo_is_protected_after_it_answers_again() {
+        let (mut driver, bus) = bus_with_servos();
+        let mut monitor = HealthMonitor::new(&mut driver)
+            .watch(1, HealthThresholds::default())
+            .critical_action(ProtectiveAction::Limp);
+        bus.set_faults(Faults {
+            drop_every: Some(1),
+            ..Faults::default()
+        });
+        let events = monitor.check().await;
+        assert_eq!(events[0].conditions, vec![HealthCondition::Unreachable]);
+        assert!(monitor.actions().is_empty());
+
+        bus.set_faults(Faults::default());
+        bus.servo(1, |servo| servo.set_temperature(80.0));
+        // severity stays critical but the servo is limped now that it is really overheating
+        let events = monitor.check().await;
+        assert_eq!(events[0].severity, Severity::Critical);
+        assert_eq!(events[0].action, Some(ProtectiveAction::Limp));
+        assert_eq!(
+            monitor.actions()[0].reason,
+            HealthCondition::CriticalTemperature(80.0)
+        );
+        assert_eq!(monitor.failed_reads(1), Some(0));
+        assert!(monitor.check().await.is_empty());
+        assert_eq!(monitor.actions().len(), 1);
+    }
+}
@@ -1,20 +1,72 @@
 #![doc = include_str!("../README.md")]
 
+mod builder;
+mod health;
 mod message_types;
+mod poller;
+pub mod protocol;
 mod serial_driver;
+mod servo;
+mod shared_driver;
+pub mod simulator;
+mod tcp_driver;
+pub mod transport;
 
+use builder::DriverSettings;
+pub use builder::{CallTimeout, LSSDriverBuilder};
+pub use health::{
+    ActionRecord, HealthCondition, HealthEvent, HealthMonitor, HealthThresholds, LedFeedback,
+    ProtectiveAction, Severity,
+};
 pub use message_types::*;
-use serial_driver::{FramedDriver, FramedSerialDriver, LssCommand};
+pub use poller::{
+    PollerStats, TelemetryField, TelemetryPoller, TelemetrySample, TelemetryStream, TelemetryValue,
+};
+use protocol::{Command, CommandFrame, Parameter, Query, Reply, ReplyFrame, Setting};
+pub use serial_driver::FramedSerialDriver;
+use serial_driver::DEFAULT_BAUD_RATE;
+pub use servo::Servo;
+pub use shared_driver::SharedLSSDriver;
+use std::collections::HashMap;
 use std::str;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+pub use tcp_driver::FramedTcpDriver;
+use transport::{FramedDriver, LssCommand, LssResponse, Transaction, TransactionResponses};
 
 /// ID used to talk to all motors on a bus at once
 pub const BROADCAST_ID: u8 = 254;
 
+/// ID servos have out of the factory
+pub const FACTORY_ID: u8 = 0;
+
+/// How long servos need to save to EEPROM and reboot after [LSSDriver::restore_factory_defaults]
+pub const FACTORY_DEFAULTS_SETTLE_TIME: Duration = Duration::from_millis(1000);
+
+/// How often [LSSDriver::move_group] polls the status of moving servos
+pub const GROUP_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// How long after the expected arrival [LSSDriver::move_group] keeps waiting for servos
+pub const GROUP_MOVE_MARGIN: Duration = Duration::from_millis(1000);
+
+/// How many times in a row [LSSDriver::wait_until_any_fault] tolerates a servo not answering
+pub const MAX_FAILED_WATCH_READS: u32 = 3;
+
+/// Baud rates supported by LSS servos. Servos use 115200 out of the factory
+pub const SUPPORTED_BAUD_RATES: [u32; 9] = [
+    9600, 19200, 38400, 57600, 115200, 230400, 250000, 460800, 500000,
+];
+
 type DriverResult<T> = Result<T, LssDriverError>;
 
 /// Driver for the LSS servo
+///
+/// Use [SharedLSSDriver] if multiple tasks need to use the bus at the same time
 pub struct LSSDriver {
     driver: Box<dyn FramedDriver + Send + Sync>,
+    settings: DriverSettings,
+    /// Expected target position of each servo in degrees. Shared between clones
+    targets: Arc<Mutex<HashMap<u8, f32>>>,
 }
 
 impl LSSDriver {
@@ -33,10 +85,7 @@ impl LSSDriver {
     /// let mut driver = LSSDriver::new("COM1").unwrap();
     /// ```
     pub fn new(port: &str) -> DriverResult<LSSDriver> {
-        let driver = FramedSerialDriver::new(port)?;
-        Ok(LSSDriver {
-            driver: Box::new(driver),
-        })
+        LSSDriver::builder(port).build()
     }
 
     /// Create new driver on a serial port with custom baud rate
@@ -53,40 +102,1185 @@ impl LSSDriver {
     /// let mut driver = LSSDriver::with_baud_rate("COM1", 115200).unwrap();
     /// ```
     pub fn with_baud_rate(port: &str, baud_rate: u32) -> DriverResult<LSSDriver> {
-        let driver = FramedSerialDriver::with_baud_rate(port, baud_rate)?;
-        Ok(LSSDriver {
-            driver: Box::new(driver),
-        })
+        LSSDriver::builder(port).baud_rate(baud_rate).build()
+    }
+
+    /// Start building a driver on a serial port with custom settings
+    ///
+    /// # Arguments
+    ///
+    /// * `port` - Port to use. e.g. COM1 or /dev/ttyACM0
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use lss_driver::{LSSDriver, RetryPolicy};
+    /// use std::time::Duration;
+    /// let mut driver = LSSDriver::builder("COM1")
+    ///     .baud_rate(9600)
+    ///     .timeout(Duration::from_millis(50))
+    ///     .retry_policy(RetryPolicy::new(2, Duration::from_millis(10)))
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn builder(port: &str) -> LSSDriverBuilder {
+        LSSDriverBuilder::new(port)
+    }
+
+    /// Create new driver on a serial port with unknown baud rate
+    ///
+    /// Reopens the port at every baud rate in [SUPPORTED_BAUD_RATES] until a servo answers.
+    /// Returns the driver and the detected baud rate.
+    /// Use [LSSDriverBuilder::detect_baud_rate] to also change the settings
+    ///
+    /// # Arguments
+    ///
+    /// * `port` - Port to use. e.g. COM1 or /dev/ttyACM0
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use lss_driver::LSSDriver;
+    /// async fn async_main(){
+    ///     let (mut driver, baud_rate) = LSSDriver::with_detected_baud_rate("COM1").await.unwrap();
+    ///     println!("Servos use {} baud", baud_rate);
+    /// }
+    /// ```
+    pub async fn with_detected_baud_rate(port: &str) -> DriverResult<(LSSDriver, u32)> {
+        LSSDriver::builder(port).detect_baud_rate().await
+    }
+
+    /// Create new driver connected to a serial-to-Ethernet bridge over TCP
+    ///
+    /// The bridge (e.g. ser2net) is expected to forward raw bytes to the servo bus.
+    /// If the connection drops commands fail with [LssDriverError::ConnectionLost]
+    /// and the driver reconnects on the next command.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - Address of the bridge. e.g. 192.168.1.10:4000
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use lss_driver::LSSDriver;
+    /// async fn async_main(){
+    ///     let mut driver = LSSDriver::with_tcp("192.168.1.10:4000").await.unwrap();
+    /// }
+    /// ```
+    pub async fn with_tcp(address: &str) -> DriverResult<LSSDriver> {
+        let driver = FramedTcpDriver::new(address).await?;
+        LSSDriverBuilder::with_transport(Box::new(driver)).build()
     }
 
     /// Creates new LSS driver with a custom implementation of the transport
     ///
     /// This is used for tests and can be used if you want to reimplement the driver over network
+    ///
+    /// Look at [FramedDriver] for the contract the transport has to uphold.
+    /// Use [LSSDriverBuilder::with_transport] to also change the settings
     pub fn with_driver(driver: Box<dyn FramedDriver + Send + Sync>) -> LSSDriver {
-        LSSDriver { driver }
+        LSSDriver::with_settings(driver, DriverSettings::default())
+    }
+
+    pub(crate) fn with_settings(
+        driver: Box<dyn FramedDriver + Send + Sync>,
+        settings: DriverSettings,
+    ) -> LSSDriver {
+        LSSDriver {
+            driver,
+            settings,
+            targets: Arc::default(),
+        }
+    }
+
+    /// Create a driver with the same settings on top of a different transport
+    pub(crate) fn clone_with_driver(
+        &self,
+        driver: Box<dyn FramedDriver + Send + Sync>,
+    ) -> LSSDriver {
+        LSSDriver {
+            driver,
+            settings: self.settings.clone(),
+            targets: self.targets.clone(),
+        }
+    }
+
+    /// Swap transport for a different one and return the old one
+    pub(crate) fn replace_driver(
+        self,
+        driver: Box<dyn FramedDriver + Send + Sync>,
+    ) -> (LSSDriver, Box<dyn FramedDriver + Send + Sync>) {
+        let new_driver = self.clone_with_driver(driver);
+        (new_driver, self.driver)
+    }
+
+    /// Set how responses are matched against queries
+    ///
+    /// Defaults to [ResponseValidation::Strict]
+    ///
+    /// # Arguments
+    ///
+    /// * `response_validation` - Validation mode
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use lss_driver::{LSSDriver, ResponseValidation};
+    /// let mut driver = LSSDriver::new("COM1").unwrap();
+    /// driver.set_response_validation(ResponseValidation::DiscardStale);
+    /// ```
+    pub fn set_response_validation(&mut self, response_validation: ResponseValidation) {
+        self.settings.response_validation = response_validation;
+    }
+
+    /// Set how long to wait for responses
+    ///
+    /// Defaults to the timeout of the transport. 10ms for serial ports
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - How long to wait for each response
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use lss_driver::LSSDriver;
+    /// use std::time::Duration;
+    /// let mut driver = LSSDriver::with_baud_rate("COM1", 9600).unwrap();
+    /// driver.set_timeout(Duration::from_millis(50));
+    /// ```
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.settings.timeout = Some(timeout);
+    }
+
+    /// Use a different timeout for the calls made through the returned handle
+    ///
+    /// The previous timeout is restored when the handle is dropped
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - How long to wait for each response
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use lss_driver::LSSDriver;
+    /// use std::time::Duration;
+    /// async fn async_main(){
+    ///     let mut driver = LSSDriver::new("COM1").unwrap();
+    ///     let serial_number = driver
+    ///         .call_timeout(Duration::from_millis(100))
+    ///         .query_serial_number(5)
+    ///         .await
+    ///         .unwrap();
+    /// }
+    /// ```
+    pub fn call_timeout(&mut self, timeout: Duration) -> CallTimeout<'_> {
+        CallTimeout::new(self, timeout)
+    }
+
+    /// Handle to servo `id` that doesn't need the ID for every call
+    ///
+    /// Borrows the driver. Use [SharedLSSDriver::servo] for handles that can be kept around
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of the servo
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use lss_driver::LSSDriver;
+    /// async fn async_main(){
+    ///     let mut driver = LSSDriver::new("COM1").unwrap();
+    ///     let mut servo = driver.servo(5);
+    ///     servo.move_to_position(90.0).await;
+    /// }
+    /// ```
+    pub fn servo(&mut self, id: u8) -> Servo<&mut LSSDriver> {
+        Servo::new(self, id)
+    }
+
+    /// Set when failed commands are retried
+    ///
+    /// Defaults to no retries
+    ///
+    /// # Arguments
+    ///
+    /// * `retry_policy` - Retry policy
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use lss_driver::{LSSDriver, RetryPolicy};
+    /// use std::time::Duration;
+    /// let mut driver = LSSDriver::new("COM1").unwrap();
+    /// driver.set_retry_policy(RetryPolicy::new(3, Duration::from_millis(5)));
+    /// ```
+    pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy) {
+        self.settings.retry_policy = retry_policy;
+    }
+
+    /// Send a write command
+    ///
+    /// Only retried if the retry policy allows retrying writes
+    async fn send(&mut self, frame: CommandFrame) -> DriverResult<()> {
+        self.send_all(vec![frame]).await
+    }
+
+    /// Send write commands back-to-back in a single write
+    ///
+    /// Only retried if the retry policy allows retrying writes
+    async fn send_all(&mut self, frames: Vec<CommandFrame>) -> DriverResult<()> {
+        let command = LssCommand::batch(&frames);
+        let retry_policy = self.settings.retry_policy;
+        let retries = if retry_policy.retry_writes {
+            retry_policy.retries
+        } else {
+            0
+        };
+        let mut attempt = 0;
+        loop {
+            self.settings.log_command(&command);
+            match self.driver.send(command.clone()).await {
+                Err(_) if attempt < retries => {
+                    attempt += 1;
+                    tokio::time::sleep(retry_policy.backoff).await;
+                }
+                Err(source) => {
+                    let id = command.id().unwrap_or(BROADCAST_ID);
+                    return Err(LssDriverError::command_failed(
+                        id,
+                        command.as_str(),
+                        None,
+                        source,
+                    ));
+                }
+                Ok(()) => {
+                    for frame in &frames {
+                        self.track_target(frame.id, frame.command);
+                    }
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Update expected target of servo `id` after `command` was sent
+    fn track_target(&self, id: u8, command: Command) {
+        let mut targets = self.targets.lock().unwrap();
+        let update = |target: Option<f32>| match command {
+            Command::Move(position) => Some(position as f32 / 10.0),
+            Command::MoveRelative(delta) => target.map(|target| target + delta as f32 / 10.0),
+            Command::Set(parameter, _) | Command::Configure(parameter, _)
+                if !matches!(parameter, Parameter::OriginOffset | Parameter::Gyre) =>
+            {
+                target
+            }
+            Command::ConfigureSetting(..)
+            | Command::ConfigureRcMode(_)
+            | Command::Query(_)
+            | Command::Default
+            | Command::Update
+            | Command::ClearFirstPosition => target,
+            // anything else moves the servo some other way or changes what the positions mean
+            _ => None,
+        };
+        if id == BROADCAST_ID {
+            targets.retain(|_, target| match update(Some(*target)) {
+                Some(new_target) => {
+                    *target = new_target;
+                    true
+                }
+                None => false,
+            });
+        } else {
+            match update(targets.get(&id).copied()) {
+                Some(target) => targets.insert(id, target),
+                None => targets.remove(&id),
+            };
+        }
+    }
+
+    /// Query numeric value from servo `id` and convert it
+    async fn query_value<T>(
+        &mut self,
+        id: u8,
+        query: Query,
+        convert: impl FnOnce(i32) -> DriverResult<T>,
+    ) -> DriverResult<T> {
+        self.query_parsed(id, query, |reply| match reply {
+            Reply::Value(_, value) => convert(value),
+            Reply::Text(..) | Reply::Empty(_) => Err(LssDriverError::PacketParsingError(
+                "Expected number".to_owned(),
+            )),
+        })
+        .await
+    }
+
+    /// Query text value from servo `id`
+    async fn query_text(&mut self, id: u8, query: Query) -> DriverResult<String> {
+        self.query_parsed(id, query, |reply| match reply {
+            Reply::Text(_, text) => Ok(text),
+            Reply::Value(..) | Reply::Empty(_) => Err(LssDriverError::PacketParsingError(
+                "Expected text".to_owned(),
+            )),
+        })
+        .await
+    }
+
+    /// Send a query and parse the reply
+    ///
+    /// Errors are wrapped in [LssDriverError::CommandFailed] with the command and response
+    async fn query_parsed<T>(
+        &mut self,
+        id: u8,
+        query: Query,
+        parse: impl FnOnce(Reply) -> DriverResult<T>,
+    ) -> DriverResult<T> {
+        let command = LssCommand::from(CommandFrame::new(id, Command::Query(query)));
+        let response = self
+            .query_matching(command.clone(), id, query.echo(), query.is_text())
+            .await
+            .map_err(|source| LssDriverError::command_failed(id, command.as_str(), None, source))?;
+        response
+            .as_str()
+            .parse::<ReplyFrame>()
+            .and_then(|frame| parse(frame.reply))
+            .map_err(|source| {
+                LssDriverError::command_failed(
+                    id,
+                    command.as_str(),
+                    Some(response.as_str()),
+                    source,
+                )
+            })
+    }
+
+    /// Send a query and wait for a response echoing `echo` from servo `id`
+    ///
+    /// `is_text` should be set for queries that return text instead of a number
+    ///
+    /// Retried according to the retry policy
+    async fn query_matching(
+        &mut self,
+        command: LssCommand,
+        id: u8,
+        echo: &str,
+        is_text: bool,
+    ) -> DriverResult<LssResponse> {
+        let mut attempt = 0;
+        loop {
+            match self
+                .query_matching_once(command.clone(), id, echo, is_text)
+                .await
+            {
+                Err(_) if attempt < self.settings.retry_policy.retries => {
+                    attempt += 1;
+                    tokio::time::sleep(self.settings.retry_policy.backoff).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn query_matching_once(
+        &mut self,
+        command: LssCommand,
+        id: u8,
+        echo: &str,
+        is_text: bool,
+    ) -> DriverResult<LssResponse> {
+        let owned_echo = echo.to_owned();
+        let matches = move |response: &LssResponse| {
+            if is_text {
+                response.is_text_response_to(id, &owned_echo)
+            } else {
+                response.is_response_to(id, &owned_echo)
+            }
+        };
+        if self.settings.response_validation == ResponseValidation::DiscardStale {
+            // stale responses are read in the same transaction so that
+            // nothing else can be sent on a shared bus in between
+            let timeout = self.settings.timeout;
+            let mut responses = self
+                .transaction(Transaction {
+                    commands: vec![command],
+                    expected_replies: 1,
+                    timeout,
+                    drain_stale: false,
+                    accept: Box::new(matches),
+                })
+                .await?;
+            return match responses.error {
+                Some(error) => Err(error),
+                None => responses
+                    .responses
+                    .pop()
+                    .ok_or(LssDriverError::TimeoutError),
+            };
+        }
+        self.settings.log_command(&command);
+        let response = match self.settings.timeout {
+            Some(timeout) => self.driver.query_timeout(command, timeout).await?,
+            None => self.driver.query(command).await?,
+        };
+        self.settings.log_response(&response);
+        if self.settings.response_validation == ResponseValidation::Disabled || matches(&response) {
+            return Ok(response);
+        }
+        let expected = if id == BROADCAST_ID {
+            format!("*{}", echo)
+        } else {
+            format!("*{}{}", id, echo)
+        };
+        Err(LssDriverError::UnexpectedResponse {
+            expected,
+            actual: response.as_str().to_owned(),
+        })
+    }
+
+    /// Run a [Transaction] on the transport and log everything sent and read
+    async fn transaction(
+        &mut self,
+        transaction: Transaction,
+    ) -> DriverResult<TransactionResponses> {
+        for command in &transaction.commands {
+            self.settings.log_command(command);
+        }
+        let responses = self.driver.transaction(transaction).await?;
+        for response in responses.discarded.iter().chain(&responses.responses) {
+            self.settings.log_response(response);
+        }
+        Ok(responses)
+    }
+
+    /// Soft reset
+    /// This command does a "soft reset" and reverts all commands to those stored in EEPROM
+    ///
+    /// [wiki](https://www.robotshop.com/info/wiki/lynxmotion/view/lynxmotion-smart-servo/lss-communication-protocol/#HReset)
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to reset
+    pub async fn reset(&mut self, id: u8) -> DriverResult<()> {
+        self.send(CommandFrame::new(id, Command::Reset)).await?;
+        Ok(())
+    }
+
+    /// Restore factory defaults and verify they took effect
+    ///
+    /// Sends `DEFAULT` and `CONFIRM`, waits [FACTORY_DEFAULTS_SETTLE_TIME], resets the servo
+    /// and waits again. Then checks that a servo answers on [FACTORY_ID] at 115200 baud
+    /// with the LED off, so the port has to be open at 115200.
+    /// Fails with [LssDriverError::VerificationFailed] if it reports anything else.
+    ///
+    /// Every setting saved in EEPROM is lost including ID and baud rate.
+    /// Refuses to run on [BROADCAST_ID] with [LssDriverError::BroadcastNotAllowed]
+    /// unless `allow_broadcast` is set. Broadcasts aren't verified because
+    /// all servos end up with the same ID.
+    ///
+    /// [wiki](https://www.robotshop.com/info/wiki/lynxmotion/view/lynxmotion-smart-servo/lss-communication-protocol/#HDefault26confirm)
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to restore
+    /// * `allow_broadcast` - Allow restoring all servos on the bus
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use lss_driver::{LSSDriver, FACTORY_ID};
+    /// async fn async_main(){
+    ///     let mut driver = LSSDriver::new("COM1").unwrap();
+    ///     driver.restore_factory_defaults(5, false).await.unwrap();
+    ///     driver.set_id(FACTORY_ID, 5).await.unwrap();
+    /// }
+    /// ```
+    pub async fn restore_factory_defaults(
+        &mut self,
+        id: u8,
+        allow_broadcast: bool,
+    ) -> DriverResult<()> {
+        if id == BROADCAST_ID && !allow_broadcast {
+            return Err(LssDriverError::BroadcastNotAllowed {
+                command: "DEFAULT".to_owned(),
+            });
+        }
+        self.send_confirmed(id, Command::Default).await?;
+        tokio::time::sleep(FACTORY_DEFAULTS_SETTLE_TIME).await;
+        self.reset(id).await?;
+        tokio::time::sleep(FACTORY_DEFAULTS_SETTLE_TIME).await;
+        if id == BROADCAST_ID {
+            return Ok(());
+        }
+        let verify = |setting, expected: String, actual: String| {
+            if expected == actual {
+                Ok(())
+            } else {
+                Err(LssDriverError::VerificationFailed {
+                    id: FACTORY_ID,
+                    setting,
+                    expected,
+                    actual,
+                })
+            }
+        };
+        let actual_id = self.query_id(FACTORY_ID).await?;
+        verify("ID", FACTORY_ID.to_string(), actual_id.to_string())?;
+        let baud_rate = self.query_baud_rate(FACTORY_ID).await?;
+        verify(
+            "baud rate",
+            DEFAULT_BAUD_RATE.to_string(),
+            baud_rate.to_string(),
+        )?;
+        let color = self.query_color(FACTORY_ID).await?;
+        verify(
+            "LED color",
+            format!("{:?}", LedColor::Off),
+            format!("{:?}", color),
+        )
+    }
+
+    /// Put a servo into firmware update mode
+    ///
+    /// Sends `UPDATE` and `CONFIRM`. The servo stops responding to commands
+    /// until it is flashed with the LSS Config software or power cycled.
+    /// Always refuses to run on [BROADCAST_ID] with [LssDriverError::BroadcastNotAllowed]
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to update
+    pub async fn enter_firmware_update_mode(&mut self, id: u8) -> DriverResult<()> {
+        if id == BROADCAST_ID {
+            return Err(LssDriverError::BroadcastNotAllowed {
+                command: "UPDATE".to_owned(),
+            });
+        }
+        self.send_confirmed(id, Command::Update).await
+    }
+
+    /// Configure how the servo is controlled after the next reset or power cycle
+    ///
+    /// Leaving [RcMode::Serial] makes the servo ignore all serial commands, including this one.
+    /// It can only be switched back with the button menu or the LSS Config software.
+    /// Because of that, [RcMode::Position] and [RcMode::Wheel] are refused with
+    /// [LssDriverError::NotConfirmed] unless `confirm` is set.
+    ///
+    /// [wiki](https://www.robotshop.com/info/wiki/lynxmotion/view/lynxmotion-smart-servo/lss-communication-protocol/#HConfigureRCMode28CRC29)
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to control
+    /// * `mode` - Control mode to use after reset
+    /// * `confirm` - Confirm that the servo may become unreachable over serial
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use lss_driver::{LSSDriver, RcMode};
+    /// async fn async_main(){
+    ///     let mut driver = LSSDriver::with_baud_rate("COM1", 115200).unwrap();
+    ///     driver.configure_rc_mode(5, RcMode::Position, true).await;
+    ///     driver.reset(5).await;
+    /// }
+    /// ```
+    pub async fn configure_rc_mode(
+        &mut self,
+        id: u8,
+        mode: RcMode,
+        confirm: bool,
+    ) -> DriverResult<()> {
+        if mode != RcMode::Serial && !confirm {
+            return Err(LssDriverError::NotConfirmed {
+                command: format!("CRC{}", mode as i32),
+                reason: "servo will stop responding to serial commands",
+            });
+        }
+        self.send(CommandFrame::new(id, Command::ConfigureRcMode(mode as i32)))
+            .await?;
+        Ok(())
+    }
+
+    /// Send a command that only takes effect after `CONFIRM`
+    async fn send_confirmed(&mut self, id: u8, command: Command) -> DriverResult<()> {
+        self.send(CommandFrame::new(id, command)).await?;
+        self.send(CommandFrame::new(id, Command::Confirm)).await?;
+        Ok(())
+    }
+
+    /// Query value of ID
+    /// Especially useful with BROADCAST_ID
+    ///
+    /// [wiki](https://www.robotshop.com/info/wiki/lynxmotion/view/lynxmotion-smart-servo/lss-communication-protocol/#HIdentificationNumber28ID29)
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to control
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use lss_driver::LSSDriver;
+    /// async fn async_main(){
+    ///     let mut driver = LSSDriver::with_baud_rate("COM1", 115200).unwrap();
+    ///     let id = driver.query_id(lss_driver::BROADCAST_ID).await.unwrap();
+    /// }
+    /// ```
+    pub async fn query_id(&mut self, id: u8) -> DriverResult<u8> {
+        self.query_value(id, Query::Setting(Setting::Id), |value| Ok(value as u8))
+            .await
+    }
+
+    /// Set value of ID
+    /// Saved to EEPROM
+    /// Only takes effect after restart
+    ///
+    /// [wiki](https://www.robotshop.com/info/wiki/lynxmotion/view/lynxmotion-smart-servo/lss-communication-protocol/#HIdentificationNumber28ID29)
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to control
+    /// * `new_id` - ID You want that servo to have
+    pub async fn set_id(&mut self, id: u8, new_id: u8) -> DriverResult<()> {
+        self.send(CommandFrame::new(
+            id,
+            Command::ConfigureSetting(Setting::Id, new_id as i32),
+        ))
+        .await?;
+        Ok(())
+    }
+
+    /// Set baud rate of a servo
+    ///
+    /// Saved to EEPROM. Only takes effect after [reset](LSSDriver::reset) or restart.
+    /// Reopen the port with the new baud rate afterwards. e.g. with [LSSDriver::with_baud_rate]
+    ///
+    /// Fails with [LssDriverError::UnsupportedBaudRate] without sending anything
+    /// if the baud rate isn't in [SUPPORTED_BAUD_RATES]
+    ///
+    /// [wiki](https://www.robotshop.com/info/wiki/lynxmotion/view/lynxmotion-smart-servo/lss-communication-protocol/#HBaudRate28B29)
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to control
+    /// * `baud_rate` - Baudrate. e.g. 115200
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use lss_driver::{LSSDriver, BROADCAST_ID};
+    /// async fn async_main(){
+    ///     let mut driver = LSSDriver::new("COM1").unwrap();
+    ///     driver.configure_baud_rate(BROADCAST_ID, 500000).await.unwrap();
+    ///     driver.reset(BROADCAST_ID).await.unwrap();
+    ///     let mut driver = LSSDriver::with_baud_rate("COM1", 500000).unwrap();
+    /// }
+    /// ```
+    pub async fn configure_baud_rate(&mut self, id: u8, baud_rate: u32) -> DriverResult<()> {
+        if !SUPPORTED_BAUD_RATES.contains(&baud_rate) {
+            return Err(LssDriverError::UnsupportedBaudRate(baud_rate));
+        }
+        self.send(CommandFrame::new(
+            id,
+            Command::ConfigureSetting(Setting::BaudRate, baud_rate as i32),
+        ))
+        .await?;
+        Ok(())
+    }
+
+    /// Query baud rate of a servo
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to query
+    pub async fn query_baud_rate(&mut self, id: u8) -> DriverResult<u32> {
+        self.query_value(id, Query::Setting(Setting::BaudRate), |value| {
+            Ok(value as u32)
+        })
+        .await
+    }
+
+    /// set color for driver with id
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to control
+    /// * `color` - Color to set
+    pub async fn set_color(&mut self, id: u8, color: LedColor) -> DriverResult<()> {
+        self.send(CommandFrame::new(
+            id,
+            Command::Set(Parameter::LedColor, color as i32),
+        ))
+        .await?;
+        Ok(())
+    }
+
+    /// configure color for motor with id (value will be saved)
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to control
+    /// * `color` - Color to set
+    pub async fn configure_color(&mut self, id: u8, color: LedColor) -> DriverResult<()> {
+        self.send(CommandFrame::new(
+            id,
+            Command::Configure(Parameter::LedColor, color as i32),
+        ))
+        .await?;
+        Ok(())
+    }
+
+    /// Query color of servo LED
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to query
+    pub async fn query_color(&mut self, id: u8) -> DriverResult<LedColor> {
+        self.query_value(
+            id,
+            Query::Parameter(Parameter::LedColor),
+            LedColor::from_i32,
+        )
+        .await
+    }
+
+    /// Query color of servo LED saved in EEPROM
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to query
+    pub async fn query_configured_color(&mut self, id: u8) -> DriverResult<LedColor> {
+        self.query_value(
+            id,
+            Query::ConfiguredParameter(Parameter::LedColor),
+            LedColor::from_i32,
+        )
+        .await
+    }
+
+    /// Move to absolute position in degrees
+    ///
+    /// Supports virtual positions that are more than 360 degrees
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to control
+    /// * `position` - Absolute position in degrees
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use lss_driver::LSSDriver;
+    /// async fn async_main(){
+    ///     let mut driver = LSSDriver::with_baud_rate("COM1", 115200).unwrap();
+    ///     driver.move_to_position(5, 180.0).await;
+    ///     driver.move_to_position(5, 480.0).await;
+    /// }
+    /// ```
+    pub async fn move_to_position(&mut self, id: u8, position: f32) -> DriverResult<()> {
+        let angle = (position * 10.0).round() as i32;
+        self.send(CommandFrame::new(id, Command::Move(angle)))
+            .await?;
+        Ok(())
+    }
+
+    /// Move to absolute position in degrees with modifier
+    ///
+    /// Supports virtual positions that are more than 360 degrees
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to control
+    /// * `position` - Absolute position in degrees
+    /// * `modifier` - Modifier applied to this motion. Look at the type for more info.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use lss_driver::LSSDriver;
+    /// use lss_driver::CommandModifier;
+    /// async fn async_main(){
+    ///     let mut driver = LSSDriver::with_baud_rate("COM1", 115200).unwrap();
+    ///     driver.move_to_position_with_modifier(5, 180.0, CommandModifier::Timed(2500)).await;
+    ///     driver.move_to_position_with_modifier(5, 480.0, CommandModifier::Timed(2500)).await;
+    /// }
+    /// ```
+    pub async fn move_to_position_with_modifier(
+        &mut self,
+        id: u8,
+        position: f32,
+        modifier: CommandModifier,
+    ) -> DriverResult<()> {
+        let angle = (position * 10.0).round() as i32;
+        self.send(CommandFrame::new(id, Command::Move(angle)).with_command_modifiers(&[modifier]))
+            .await?;
+        Ok(())
+    }
+
+    /// Move to absolute position in degrees with multiple modifiers
+    ///
+    /// Supports virtual positions that are more than 360 degrees
+    /// Be careful about which modifiers are supported together
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to control
+    /// * `position` - Absolute position in degrees
+    /// * `modifiers` - Array of modifiers applied to this motion. Look at the type for more info.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use lss_driver::LSSDriver;
+    /// use lss_driver::CommandModifier;
+    /// async fn async_main(){
+    ///     let mut driver = LSSDriver::with_baud_rate("COM1", 115200).unwrap();
+    ///     driver.move_to_position_with_modifiers(5, 180.0, &[CommandModifier::Timed(2500), CommandModifier::CurrentHold(400)]).await;
+    /// }
+    /// ```
+    pub async fn move_to_position_with_modifiers(
+        &mut self,
+        id: u8,
+        position: f32,
+        modifiers: &[CommandModifier],
+    ) -> DriverResult<()> {
+        let angle = (position * 10.0).round() as i32;
+        self.send(CommandFrame::new(id, Command::Move(angle)).with_command_modifiers(modifiers))
+            .await?;
+        Ok(())
+    }
+
+    /// Move to absolute position in degrees
+    ///
+    /// Same as `move_to_position`
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to control
+    /// * `position` - Absolute position in degrees
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use lss_driver::LSSDriver;
+    /// async fn async_main(){
+    ///     let mut driver = LSSDriver::with_baud_rate("COM1", 115200).unwrap();
+    ///     driver.set_target_position(5, 180.0).await;
+    ///     driver.set_target_position(5, 480.0).await;
+    /// }
+    /// ```
+    pub async fn set_target_position(&mut self, id: u8, position: f32) -> DriverResult<()> {
+        self.move_to_position(id, position).await
+    }
+
+    /// Move relative to the current position in degrees
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to control
+    /// * `delta` - Relative move in degrees. Negative values move counterclockwise
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use lss_driver::LSSDriver;
+    /// async fn async_main(){
+    ///     let mut driver = LSSDriver::with_baud_rate("COM1", 115200).unwrap();
+    ///     driver.move_relative(5, 12.3).await;
+    ///     driver.move_relative(5, -45.0).await;
+    /// }
+    /// ```
+    pub async fn move_relative(&mut self, id: u8, delta: f32) -> DriverResult<()> {
+        self.move_relative_with_modifiers(id, delta, &[]).await
+    }
+
+    /// Move relative to the current position in degrees with modifier
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to control
+    /// * `delta` - Relative move in degrees. Negative values move counterclockwise
+    /// * `modifier` - Modifier applied to this motion. Look at the type for more info.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use lss_driver::LSSDriver;
+    /// use lss_driver::CommandModifier;
+    /// async fn async_main(){
+    ///     let mut driver = LSSDriver::with_baud_rate("COM1", 115200).unwrap();
+    ///     driver.move_relative_with_modifier(5, 90.0, CommandModifier::Timed(2500)).await;
+    /// }
+    /// ```
+    pub async fn move_relative_with_modifier(
+        &mut self,
+        id: u8,
+        delta: f32,
+        modifier: CommandModifier,
+    ) -> DriverResult<()> {
+        self.move_relative_with_modifiers(id, delta, &[modifier])
+            .await
+    }
+
+    /// Move relative to the current position in degrees with multiple modifiers
+    ///
+    /// Be careful about which modifiers are supported together
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to control
+    /// * `delta` - Relative move in degrees. Negative values move counterclockwise
+    /// * `modifiers` - Array of modifiers applied to this motion. Look at the type for more info.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use lss_driver::LSSDriver;
+    /// use lss_driver::CommandModifier;
+    /// async fn async_main(){
+    ///     let mut driver = LSSDriver::with_baud_rate("COM1", 115200).unwrap();
+    ///     driver.move_relative_with_modifiers(5, 90.0, &[CommandModifier::Timed(2500), CommandModifier::CurrentHold(400)]).await;
+    /// }
+    /// ```
+    pub async fn move_relative_with_modifiers(
+        &mut self,
+        id: u8,
+        delta: f32,
+        modifiers: &[CommandModifier],
+    ) -> DriverResult<()> {
+        let angle = (delta * 10.0).round() as i32;
+        self.send(
+            CommandFrame::new(id, Command::MoveRelative(angle)).with_command_modifiers(modifiers),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Target position in degrees the driver expects servo `id` to be heading to
+    ///
+    /// Set by absolute moves and [LSSDriver::query_target_position] and moved by relative moves.
+    /// `None` if it isn't known. e.g. before the first absolute move or after a limp, halt, reset,
+    /// wheel mode or origin offset change.
+    /// Relative moves start from the current position, so the expectation only holds
+    /// if the servo reached its previous target.
+    /// Shared between clones of [SharedLSSDriver].
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo
+    pub fn expected_target(&self, id: u8) -> Option<f32> {
+        self.targets.lock().unwrap().get(&id).copied()
+    }
+
+    /// Step the expected target of a servo by `delta` degrees
+    ///
+    /// Sends an absolute move to [LSSDriver::expected_target] plus `delta`,
+    /// so repeated steps don't lag behind while the servo is still moving.
+    /// Queries the target position first if it isn't known.
+    /// Useful for jogging joints from teleop input.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to control
+    /// * `delta` - Step in degrees. Negative values move counterclockwise
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use lss_driver::LSSDriver;
+    /// async fn async_main(){
+    ///     let mut driver = LSSDriver::with_baud_rate("COM1", 115200).unwrap();
+    ///     let target = driver.step_target(5, 2.5).await.unwrap();
+    /// }
+    /// ```
+    pub async fn step_target(&mut self, id: u8, delta: f32) -> DriverResult<f32> {
+        let target = match self.expected_target(id) {
+            Some(target) => target,
+            None => self.query_target_position(id).await?,
+        };
+        self.move_to_position(id, target + delta).await?;
+        Ok(self.expected_target(id).unwrap_or(target + delta))
+    }
+
+    /// Move several servos so that they start and arrive at the same time
+    ///
+    /// Same as [start_group_move](LSSDriver::start_group_move) but also waits until all servos
+    /// report [MotorStatus::Holding]. Status is polled every [GROUP_POLL_INTERVAL].
+    /// Fails with [LssDriverError::WaitTimeout] if a servo is still moving
+    /// [GROUP_MOVE_MARGIN] after the expected arrival
+    /// and with [LssDriverError::ServoFault] if a servo reports a fault.
+    ///
+    /// # Arguments
+    ///
+    /// * `targets` - Absolute target position in degrees for each servo ID
+    /// * `timing` - How fast to move. Look at the type for more info.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use lss_driver::{LSSDriver, GroupTiming};
+    /// use std::collections::HashMap;
+    /// use std::time::Duration;
+    /// async fn async_main(){
+    ///     let mut driver = LSSDriver::with_baud_rate("COM1", 115200).unwrap();
+    ///     let targets = HashMap::from([(1, 90.0), (2, -45.0), (3, 10.0)]);
+    ///     driver.move_group(&targets, GroupTiming::Timed(Duration::from_secs(2))).await;
+    /// }
+    /// ```
+    pub async fn move_group(
+        &mut self,
+        targets: &HashMap<u8, f32>,
+        timing: GroupTiming,
+    ) -> DriverResult<()> {
+        let duration = self.start_group_move(targets, timing).await?;
+        let deadline = tokio::time::Instant::now() + duration + GROUP_MOVE_MARGIN;
+        let mut moving: Vec<u8> = targets.keys().copied().collect();
+        moving.sort_unstable();
+        loop {
+            tokio::time::sleep(GROUP_POLL_INTERVAL).await;
+            let mut still_moving = vec![];
+            for id in moving {
+                let status = self.query_status(id).await?;
+                check_fault(id, status)?;
+                if status != MotorStatus::Holding {
+                    still_moving.push((id, status));
+                }
+            }
+            match still_moving.first() {
+                None => return Ok(()),
+                Some(&(id, status)) if tokio::time::Instant::now() >= deadline => {
+                    return Err(LssDriverError::WaitTimeout { id, status })
+                }
+                Some(_) => moving = still_moving.into_iter().map(|(id, _)| id).collect(),
+            }
+        }
+    }
+
+    /// Start moving several servos so that they start and arrive at the same time
+    ///
+    /// Computes a modifier for every servo from `timing`.
+    /// Current positions are only queried for [GroupTiming::Speed].
+    /// Moves are sent back-to-back in a single write so all servos start together.
+    /// Returns how long the move is expected to take.
+    /// Fails with [LssDriverError::InvalidArgument] if the speed isn't positive
+    /// or is so slow that the duration can't be represented.
+    ///
+    /// # Arguments
+    ///
+    /// * `targets` - Absolute target position in degrees for each servo ID
+    /// * `timing` - How fast to move. Look at the type for more info.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use lss_driver::{LSSDriver, GroupTiming};
+    /// use std::collections::HashMap;
+    /// async fn async_main(){
+    ///     let mut driver = LSSDriver::with_baud_rate("COM1", 115200).unwrap();
+    ///     let targets = HashMap::from([(1, 90.0), (2, -45.0)]);
+    ///     let duration = driver.start_group_move(&targets, GroupTiming::Speed(60.0)).await.unwrap();
+    /// }
+    /// ```
+    pub async fn start_group_move(
+        &mut self,
+        targets: &HashMap<u8, f32>,
+        timing: GroupTiming,
+    ) -> DriverResult<Duration> {
+        if let GroupTiming::Speed(speed) = timing {
+            if !(speed > 0.0 && speed.is_finite()) {
+                return Err(LssDriverError::InvalidArgument {
+                    name: "speed",
+                    reason: format!("{} °/s isn't a positive speed", speed),
+                });
+            }
+        }
+        let mut ids: Vec<u8> = targets.keys().copied().collect();
+        ids.sort_unstable();
+        let mut moves = Vec::with_capacity(ids.len());
+        for id in ids {
+            let target = targets[&id];
+            let distance = match timing {
+                GroupTiming::Timed(_) => 0.0,
+                GroupTiming::Speed(_) => (target - self.query_position(id).await?).abs(),
+            };
+            moves.push((id, target, distance));
+        }
+        let longest = moves
+            .iter()
+            .map(|(_, _, distance)| *distance)
+            .fold(0.0, f32::max);
+        let duration = match timing {
+            GroupTiming::Timed(duration) => duration,
+            GroupTiming::Speed(speed) => {
+                Duration::try_from_secs_f32(longest / speed).map_err(|_| {
+                    LssDriverError::InvalidArgument {
+                        name: "speed",
+                        reason: format!("{} °/s is too slow to move {} degrees", speed, longest),
+                    }
+                })?
+            }
+        };
+        let frames = moves
+            .into_iter()
+            .map(|(id, target, distance)| {
+                let modifier = match timing {
+                    GroupTiming::Timed(duration) => CommandModifier::TimedDuration(duration),
+                    GroupTiming::Speed(speed) => {
+                        let speed = if longest > 0.0 {
+                            speed * distance / longest
+                        } else {
+                            speed
+                        };
+                        // SD is in tenths of °/s. 0 would mean default speed
+                        CommandModifier::SpeedDegrees((speed * 10.0).round().max(1.0) as u32)
+                    }
+                };
+                CommandFrame::new(id, Command::Move((target * 10.0).round() as i32))
+                    .with_command_modifiers(&[modifier])
+            })
+            .collect();
+        self.send_all(frames).await?;
+        Ok(duration)
+    }
+
+    /// Query absolute current position in degrees
+    ///
+    /// Supports virtual positions that are more than 360 degrees
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to query
+    pub async fn query_position(&mut self, id: u8) -> DriverResult<f32> {
+        self.query_value(id, Query::Position, |value| Ok(value as f32 / 10.0))
+            .await
     }
 
-    /// Soft reset
-    /// This command does a "soft reset" and reverts all commands to those stored in EEPROM
+    /// Query absolute target position in degrees
     ///
-    /// [wiki](https://www.robotshop.com/info/wiki/lynxmotion/view/lynxmotion-smart-servo/lss-communication-protocol/#HReset)
+    /// Supports virtual positions that are more than 360 degrees.
+    /// Also updates [LSSDriver::expected_target]
     ///
     /// # Arguments
     ///
-    /// * `id` - ID of servo you want to reset
-    pub async fn reset(&mut self, id: u8) -> DriverResult<()> {
-        self.driver.send(LssCommand::simple(id, "RESET")).await?;
-        Ok(())
+    /// * `id` - ID of servo you want to query
+    pub async fn query_target_position(&mut self, id: u8) -> DriverResult<f32> {
+        let target = self
+            .query_value(id, Query::TargetPosition, |value| Ok(value as f32 / 10.0))
+            .await?;
+        if id != BROADCAST_ID {
+            self.targets.lock().unwrap().insert(id, target);
+        }
+        Ok(target)
     }
 
-    /// Query value of ID
-    /// Especially useful with BROADCAST_ID
-    ///
-    /// [wiki](https://www.robotshop.com/info/wiki/lynxmotion/view/lynxmotion-smart-servo/lss-communication-protocol/#HIdentificationNumber28ID29)
+    /// Set continuous rotation speed in °/s
     ///
     /// # Arguments
     ///
     /// * `id` - ID of servo you want to control
+    /// * `speed` - Speed in °/s. Rounded to whole °/s
     ///
     /// # Example
     ///
@@ -94,79 +1288,80 @@ impl LSSDriver {
     /// use lss_driver::LSSDriver;
     /// async fn async_main(){
     ///     let mut driver = LSSDriver::with_baud_rate("COM1", 115200).unwrap();
-    ///     let id = driver.query_id(lss_driver::BROADCAST_ID).await.unwrap();
+    ///     driver.set_rotation_speed(5, 90.0).await;
     /// }
     /// ```
-    pub async fn query_id(&mut self, id: u8) -> DriverResult<u8> {
-        self.driver.send(LssCommand::simple(id, "QID")).await?;
-        let response = self.driver.receive().await?;
-        let value = response.get_val("QID")?;
-        Ok(value as u8)
+    pub async fn set_rotation_speed(&mut self, id: u8, speed: f32) -> DriverResult<()> {
+        self.set_rotation_speed_with_modifiers(id, speed, &[]).await
     }
 
-    /// Set value of ID
-    /// Saved to EEPROM
-    /// Only takes effect after restart
-    ///
-    /// [wiki](https://www.robotshop.com/info/wiki/lynxmotion/view/lynxmotion-smart-servo/lss-communication-protocol/#HIdentificationNumber28ID29)
+    /// Set continuous rotation speed in °/s with modifier
     ///
     /// # Arguments
     ///
     /// * `id` - ID of servo you want to control
-    /// * `new_id` - ID You want that servo to have
-    pub async fn set_id(&mut self, id: u8, new_id: u8) -> DriverResult<()> {
-        self.driver
-            .send(LssCommand::with_param(id, "CID", new_id as i32))
-            .await?;
-        Ok(())
+    /// * `speed` - Speed in °/s. Rounded to whole °/s
+    /// * `modifier` - Modifier applied to this motion. Look at the type for more info.
+    pub async fn set_rotation_speed_with_modifier(
+        &mut self,
+        id: u8,
+        speed: f32,
+        modifier: CommandModifier,
+    ) -> DriverResult<()> {
+        self.set_rotation_speed_with_modifiers(id, speed, &[modifier])
+            .await
     }
 
-    /// set color for driver with id
+    /// Set continuous rotation speed in °/s with multiple modifiers
+    ///
+    /// Wheel mode supports the timed (T) and current (CH, CL) modifiers
     ///
     /// # Arguments
     ///
     /// * `id` - ID of servo you want to control
-    /// * `color` - Color to set
-    pub async fn set_color(&mut self, id: u8, color: LedColor) -> DriverResult<()> {
-        self.driver
-            .send(LssCommand::with_param(id, "LED", color as i32))
-            .await?;
-        Ok(())
-    }
-
-    /// configure color for motor with id (value will be saved)
+    /// * `speed` - Speed in °/s. Rounded to whole °/s
+    /// * `modifiers` - Array of modifiers applied to this motion. Look at the type for more info.
     ///
-    /// # Arguments
+    /// # Example
     ///
-    /// * `id` - ID of servo you want to control
-    /// * `color` - Color to set
-    pub async fn configure_color(&mut self, id: u8, color: LedColor) -> DriverResult<()> {
-        self.driver
-            .send(LssCommand::with_param(id, "CLED", color as i32))
-            .await?;
+    /// ```no_run
+    /// use lss_driver::LSSDriver;
+    /// use lss_driver::CommandModifier;
+    /// async fn async_main(){
+    ///     let mut driver = LSSDriver::with_baud_rate("COM1", 115200).unwrap();
+    ///     driver.set_rotation_speed_with_modifiers(5, 90.0, &[CommandModifier::CurrentHold(400)]).await;
+    /// }
+    /// ```
+    pub async fn set_rotation_speed_with_modifiers(
+        &mut self,
+        id: u8,
+        speed: f32,
+        modifiers: &[CommandModifier],
+    ) -> DriverResult<()> {
+        let speed = speed.round() as i32;
+        self.send(
+            CommandFrame::new(id, Command::WheelSpeed(speed)).with_command_modifiers(modifiers),
+        )
+        .await?;
         Ok(())
     }
 
-    /// Query color of servo LED
+    /// Query absolute rotation speed in °/s
     ///
     /// # Arguments
     ///
     /// * `id` - ID of servo you want to query
-    pub async fn query_color(&mut self, id: u8) -> DriverResult<LedColor> {
-        self.driver.send(LssCommand::simple(id, "QLED")).await?;
-        let response = self.driver.receive().await?;
-        let (_, value) = response.separate("QLED")?;
-        LedColor::from_i32(value)
+    pub async fn query_rotation_speed(&mut self, id: u8) -> DriverResult<f32> {
+        self.query_value(id, Query::WheelSpeed, |value| Ok(value as f32))
+            .await
     }
 
-    /// Move to absolute position in degrees
-    ///
-    /// Supports virtual positions that are more than 360 degrees
+    /// Set continuous rotation speed in RPM
     ///
     /// # Arguments
     ///
     /// * `id` - ID of servo you want to control
-    /// * `position` - Absolute position in degrees
+    /// * `rpm` - Speed in RPM. Rounded to whole RPM
     ///
     /// # Example
     ///
@@ -174,61 +1369,40 @@ impl LSSDriver {
     /// use lss_driver::LSSDriver;
     /// async fn async_main(){
     ///     let mut driver = LSSDriver::with_baud_rate("COM1", 115200).unwrap();
-    ///     driver.move_to_position(5, 180.0).await;
-    ///     driver.move_to_position(5, 480.0).await;
+    ///     driver.set_rotation_speed_rpm(5, 30.0).await;
+    ///     driver.set_rotation_speed_rpm(6, -30.0).await;
     /// }
     /// ```
-    pub async fn move_to_position(&mut self, id: u8, position: f32) -> DriverResult<()> {
-        let angle = (position * 10.0).round() as i32;
-        self.driver
-            .send(LssCommand::with_param(id, "D", angle))
-            .await?;
-        Ok(())
+    pub async fn set_rotation_speed_rpm(&mut self, id: u8, rpm: f32) -> DriverResult<()> {
+        self.set_rotation_speed_rpm_with_modifiers(id, rpm, &[])
+            .await
     }
 
-    /// Move to absolute position in degrees with modifier
-    ///
-    /// Supports virtual positions that are more than 360 degrees
+    /// Set continuous rotation speed in RPM with modifier
     ///
     /// # Arguments
     ///
     /// * `id` - ID of servo you want to control
-    /// * `position` - Absolute position in degrees
+    /// * `rpm` - Speed in RPM. Rounded to whole RPM
     /// * `modifier` - Modifier applied to this motion. Look at the type for more info.
-    ///
-    /// # Example
-    ///
-    /// ```no_run
-    /// use lss_driver::LSSDriver;
-    /// use lss_driver::CommandModifier;
-    /// async fn async_main(){
-    ///     let mut driver = LSSDriver::with_baud_rate("COM1", 115200).unwrap();
-    ///     driver.move_to_position_with_modifier(5, 180.0, CommandModifier::Timed(2500)).await;
-    ///     driver.move_to_position_with_modifier(5, 480.0, CommandModifier::Timed(2500)).await;
-    /// }
-    /// ```
-    pub async fn move_to_position_with_modifier(
+    pub async fn set_rotation_speed_rpm_with_modifier(
         &mut self,
         id: u8,
-        position: f32,
+        rpm: f32,
         modifier: CommandModifier,
     ) -> DriverResult<()> {
-        let angle = (position * 10.0).round() as i32;
-        self.driver
-            .send(LssCommand::with_param_modifier(id, "D", angle, modifier))
-            .await?;
-        Ok(())
+        self.set_rotation_speed_rpm_with_modifiers(id, rpm, &[modifier])
+            .await
     }
 
-    /// Move to absolute position in degrees with multiple modifiers
+    /// Set continuous rotation speed in RPM with multiple modifiers
     ///
-    /// Supports virtual positions that are more than 360 degrees
-    /// Be careful about which modifiers are supported together
+    /// Wheel mode supports the timed (T) and current (CH, CL) modifiers
     ///
     /// # Arguments
     ///
     /// * `id` - ID of servo you want to control
-    /// * `position` - Absolute position in degrees
+    /// * `rpm` - Speed in RPM. Rounded to whole RPM
     /// * `modifiers` - Array of modifiers applied to this motion. Look at the type for more info.
     ///
     /// # Example
@@ -238,148 +1412,194 @@ impl LSSDriver {
     /// use lss_driver::CommandModifier;
     /// async fn async_main(){
     ///     let mut driver = LSSDriver::with_baud_rate("COM1", 115200).unwrap();
-    ///     driver.move_to_position_with_modifiers(5, 180.0, &[CommandModifier::Timed(2500), CommandModifier::CurrentHold(400)]).await;
+    ///     driver.set_rotation_speed_rpm_with_modifiers(5, 30.0, &[CommandModifier::CurrentLimp(600)]).await;
     /// }
     /// ```
-    pub async fn move_to_position_with_modifiers(
+    pub async fn set_rotation_speed_rpm_with_modifiers(
         &mut self,
         id: u8,
-        position: f32,
+        rpm: f32,
         modifiers: &[CommandModifier],
     ) -> DriverResult<()> {
-        let angle = (position * 10.0).round() as i32;
-        self.driver
-            .send(LssCommand::with_param_modifiers(id, "D", angle, modifiers))
-            .await?;
+        let rpm = rpm.round() as i32;
+        self.send(
+            CommandFrame::new(id, Command::WheelSpeedRpm(rpm)).with_command_modifiers(modifiers),
+        )
+        .await?;
         Ok(())
     }
 
-    /// Move to absolute position in degrees
-    ///
-    /// Same as `move_to_position`
+    /// Query rotation speed in RPM
     ///
     /// # Arguments
     ///
-    /// * `id` - ID of servo you want to control
-    /// * `position` - Absolute position in degrees
-    ///
-    /// # Example
-    ///
-    /// ```no_run
-    /// use lss_driver::LSSDriver;
-    /// async fn async_main(){
-    ///     let mut driver = LSSDriver::with_baud_rate("COM1", 115200).unwrap();
-    ///     driver.set_target_position(5, 180.0).await;
-    ///     driver.set_target_position(5, 480.0).await;
-    /// }
-    /// ```
-    pub async fn set_target_position(&mut self, id: u8, position: f32) -> DriverResult<()> {
-        self.move_to_position(id, position).await
+    /// * `id` - ID of servo you want to query
+    pub async fn query_rotation_speed_rpm(&mut self, id: u8) -> DriverResult<f32> {
+        self.query_value(id, Query::WheelSpeedRpm, |value| Ok(value as f32))
+            .await
     }
 
-    /// Query absolute current position in degrees
+    /// Query status of a motor
     ///
-    /// Supports virtual positions that are more than 360 degrees
+    /// View more on [wiki](https://www.robotshop.com/info/wiki/lynxmotion/view/lynxmotion-smart-servo/lss-communication-protocol/#HQueryStatus28Q29)
     ///
     /// # Arguments
     ///
     /// * `id` - ID of servo you want to query
-    pub async fn query_position(&mut self, id: u8) -> DriverResult<f32> {
-        self.driver.send(LssCommand::simple(id, "QD")).await?;
-        let response = self.driver.receive().await?;
-        let (_, value) = response.separate("QD")?;
-        Ok(value as f32 / 10.0)
+    pub async fn query_status(&mut self, id: u8) -> DriverResult<MotorStatus> {
+        self.query_value(id, Query::Status, MotorStatus::from_i32)
+            .await
     }
 
-    /// Query absolute target position in degrees
+    /// Query safety status of a motor
     ///
-    /// Supports virtual positions that are more than 360 degrees
+    /// View more on [wiki](https://www.robotshop.com/info/wiki/lynxmotion/view/lynxmotion-smart-servo/lss-communication-protocol/#HQueryStatus28Q29)
     ///
     /// # Arguments
     ///
     /// * `id` - ID of servo you want to query
-    pub async fn query_target_position(&mut self, id: u8) -> DriverResult<f32> {
-        self.driver.send(LssCommand::simple(id, "QDT")).await?;
-        let response = self.driver.receive().await?;
-        let (_, value) = response.separate("QDT")?;
-        Ok(value as f32 / 10.0)
+    pub async fn query_safety_status(&mut self, id: u8) -> DriverResult<SafeModeStatus> {
+        self.query_value(id, Query::SafetyStatus, SafeModeStatus::from_i32)
+            .await
     }
 
-    /// Set continuous rotation speed in °/s
+    /// Wait until servo reports `status`
+    ///
+    /// Fails early with [LssDriverError::ServoFault] if the servo reports a fault
+    /// other than the one waited for and with [LssDriverError::WaitTimeout] after `options.timeout`
     ///
     /// # Arguments
     ///
-    /// * `id` - ID of servo you want to control
-    /// * `speed` - Speed in °/s
-    pub async fn set_rotation_speed(&mut self, id: u8, speed: f32) -> DriverResult<()> {
-        self.driver
-            .send(LssCommand::with_param(id, "WD", speed as i32))
-            .await?;
-        Ok(())
-    }
-
-    /// Set continuous rotation speed in °/s with modifier
+    /// * `id` - ID of servo you want to wait for
+    /// * `status` - Status to wait for
+    /// * `options` - Poll interval and timeout
     ///
-    /// # Arguments
+    /// # Example
     ///
-    /// * `id` - ID of servo you want to control
-    /// * `speed` - Speed in °/s
-    /// * `modifier` - Modifier applied to this motion. Look at the type for more info.
-    pub async fn set_rotation_speed_with_modifier(
+    /// ```no_run
+    /// use lss_driver::{LSSDriver, MotorStatus, WaitOptions};
+    /// async fn async_main(){
+    ///     let mut driver = LSSDriver::with_baud_rate("COM1", 115200).unwrap();
+    ///     driver.limp(5).await;
+    ///     driver.wait_for_status(5, MotorStatus::Limp, WaitOptions::default()).await;
+    /// }
+    /// ```
+    pub async fn wait_for_status(
         &mut self,
         id: u8,
-        speed: f32,
-        modifier: CommandModifier,
+        status: MotorStatus,
+        options: WaitOptions,
     ) -> DriverResult<()> {
-        self.driver
-            .send(LssCommand::with_param_modifier(
-                id,
-                "WD",
-                speed as i32,
-                modifier,
-            ))
-            .await?;
-        Ok(())
+        let deadline = tokio::time::Instant::now() + options.timeout;
+        loop {
+            let current = self.query_status(id).await?;
+            if current == status {
+                return Ok(());
+            }
+            check_fault(id, current)?;
+            next_poll(id, current, deadline, options).await?;
+        }
     }
 
-    /// Query absolute rotation speed in °/s
+    /// Wait until servo is holding within `tolerance` of its target position
     ///
-    /// # Arguments
+    /// Returns the final position in degrees.
+    /// Fails early with [LssDriverError::ServoFault] if the servo reports a fault
+    /// and with [LssDriverError::WaitTimeout] after `options.timeout`
     ///
-    /// * `id` - ID of servo you want to query
-    pub async fn query_rotation_speed(&mut self, id: u8) -> DriverResult<f32> {
-        self.driver.send(LssCommand::simple(id, "QWD")).await?;
-        let response = self.driver.receive().await?;
-        let (_, value) = response.separate("QWD")?;
-        Ok(value as f32)
-    }
-
-    /// Query status of a motor
+    /// # Arguments
     ///
-    /// View more on [wiki](https://www.robotshop.com/info/wiki/lynxmotion/view/lynxmotion-smart-servo/lss-communication-protocol/#HQueryStatus28Q29)
+    /// * `id` - ID of servo you want to wait for
+    /// * `tolerance` - Allowed distance from target position in degrees
+    /// * `options` - Poll interval and timeout
     ///
-    /// # Arguments
+    /// # Example
     ///
-    /// * `id` - ID of servo you want to query
-    pub async fn query_status(&mut self, id: u8) -> DriverResult<MotorStatus> {
-        self.driver.send(LssCommand::simple(id, "Q")).await?;
-        let response = self.driver.receive().await?;
-        let (_, value) = response.separate("Q")?;
-        MotorStatus::from_i32(value)
+    /// ```no_run
+    /// use lss_driver::{LSSDriver, WaitOptions};
+    /// async fn async_main(){
+    ///     let mut driver = LSSDriver::with_baud_rate("COM1", 115200).unwrap();
+    ///     driver.move_to_position(5, 90.0).await;
+    ///     let position = driver.wait_until_settled(5, 1.0, WaitOptions::default()).await;
+    /// }
+    /// ```
+    pub async fn wait_until_settled(
+        &mut self,
+        id: u8,
+        tolerance: f32,
+        options: WaitOptions,
+    ) -> DriverResult<f32> {
+        let deadline = tokio::time::Instant::now() + options.timeout;
+        loop {
+            let status = self.query_status(id).await?;
+            check_fault(id, status)?;
+            if status == MotorStatus::Holding {
+                let target = self.query_target_position(id).await?;
+                let position = self.query_position(id).await?;
+                if (position - target).abs() <= tolerance {
+                    return Ok(position);
+                }
+            }
+            next_poll(id, status, deadline, options).await?;
+        }
     }
 
-    /// Query safety status of a motor
+    /// Watch servos until one of them reports a fault
     ///
-    /// View more on [wiki](https://www.robotshop.com/info/wiki/lynxmotion/view/lynxmotion-smart-servo/lss-communication-protocol/#HQueryStatus28Q29)
+    /// Returns ID and status of the first servo that reports a fault,
+    /// or `None` if no servo reported a fault within `options.timeout`.
+    /// Unlike [wait_for_status](LSSDriver::wait_for_status) and
+    /// [wait_until_settled](LSSDriver::wait_until_settled), a fault is the expected outcome here
+    /// and not an error, so it is returned as `Ok(Some(..))` instead of [LssDriverError::ServoFault].
+    ///
+    /// A servo that doesn't answer is skipped and polled again on the next round.
+    /// Fails only if the same servo can't be read [MAX_FAILED_WATCH_READS] times in a row
     ///
     /// # Arguments
     ///
-    /// * `id` - ID of servo you want to query
-    pub async fn query_safety_status(&mut self, id: u8) -> DriverResult<SafeModeStatus> {
-        self.driver.send(LssCommand::simple(id, "Q1")).await?;
-        let response = self.driver.receive().await?;
-        let (_, value) = response.separate("Q")?;
-        SafeModeStatus::from_i32(value)
+    /// * `ids` - IDs of servos you want to watch
+    /// * `options` - Poll interval and how long to watch
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use lss_driver::{LSSDriver, WaitOptions};
+    /// async fn async_main(){
+    ///     let mut driver = LSSDriver::with_baud_rate("COM1", 115200).unwrap();
+    ///     if let Ok(Some((id, status))) =
+    ///         driver.wait_until_any_fault(&[1, 2, 3], WaitOptions::default()).await
+    ///     {
+    ///         println!("Servo {} reported {:?}", id, status);
+    ///         driver.limp(id).await;
+    ///     }
+    /// }
+    /// ```
+    pub async fn wait_until_any_fault(
+        &mut self,
+        ids: &[u8],
+        options: WaitOptions,
+    ) -> DriverResult<Option<(u8, MotorStatus)>> {
+        let deadline = tokio::time::Instant::now() + options.timeout;
+        let mut failed_reads = vec![0; ids.len()];
+        loop {
+            for (&id, failed) in ids.iter().zip(failed_reads.iter_mut()) {
+                match self.query_status(id).await {
+                    Ok(status) if status.is_fault() => return Ok(Some((id, status))),
+                    Ok(_) => *failed = 0,
+                    Err(err) => {
+                        *failed += 1;
+                        if *failed >= MAX_FAILED_WATCH_READS {
+                            return Err(err);
+                        }
+                    }
+                }
+            }
+            let now = tokio::time::Instant::now();
+            if now >= deadline {
+                return Ok(None);
+            }
+            tokio::time::sleep(options.poll_interval.min(deadline - now)).await;
+        }
     }
 
     /// Set motion profile enabled or disabled.
@@ -393,9 +1613,32 @@ impl LSSDriver {
     /// * `id` - ID of servo you want to control
     /// * `motion_profile` - set motion profile on/off
     pub async fn set_motion_profile(&mut self, id: u8, motion_profile: bool) -> DriverResult<()> {
-        self.driver
-            .send(LssCommand::with_param(id, "EM", motion_profile as i32))
-            .await?;
+        self.send(CommandFrame::new(
+            id,
+            Command::Set(Parameter::MotionProfile, motion_profile as i32),
+        ))
+        .await?;
+        Ok(())
+    }
+
+    /// Set motion profile enabled or disabled and save it to EEPROM
+    ///
+    /// Same as [set_motion_profile](LSSDriver::set_motion_profile) but persists after a reset or power cycle
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to control
+    /// * `motion_profile` - set motion profile on/off
+    pub async fn configure_motion_profile(
+        &mut self,
+        id: u8,
+        motion_profile: bool,
+    ) -> DriverResult<()> {
+        self.send(CommandFrame::new(
+            id,
+            Command::Configure(Parameter::MotionProfile, motion_profile as i32),
+        ))
+        .await?;
         Ok(())
     }
 
@@ -409,10 +1652,24 @@ impl LSSDriver {
     ///
     /// * `id` - ID of servo you want to query
     pub async fn query_motion_profile(&mut self, id: u8) -> DriverResult<bool> {
-        self.driver.send(LssCommand::simple(id, "QEM")).await?;
-        let response = self.driver.receive().await?;
-        let (_, value) = response.separate("QEM")?;
-        Ok(value != 0)
+        self.query_value(id, Query::Parameter(Parameter::MotionProfile), |value| {
+            Ok(value != 0)
+        })
+        .await
+    }
+
+    /// Query motion profile saved in EEPROM
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to query
+    pub async fn query_configured_motion_profile(&mut self, id: u8) -> DriverResult<bool> {
+        self.query_value(
+            id,
+            Query::ConfiguredParameter(Parameter::MotionProfile),
+            |value| Ok(value != 0),
+        )
+        .await
     }
 
     /// Set filter position count
@@ -431,13 +1688,32 @@ impl LSSDriver {
         id: u8,
         filter_position_count: u8,
     ) -> DriverResult<()> {
-        self.driver
-            .send(LssCommand::with_param(
-                id,
-                "FPC",
-                filter_position_count as i32,
-            ))
-            .await?;
+        self.send(CommandFrame::new(
+            id,
+            Command::Set(Parameter::FilterPositionCount, filter_position_count as i32),
+        ))
+        .await?;
+        Ok(())
+    }
+
+    /// Set filter position count and save it to EEPROM
+    ///
+    /// Same as [set_filter_position_count](LSSDriver::set_filter_position_count) but persists after a reset or power cycle
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to control
+    /// * `filter_position_count` - default if 5
+    pub async fn configure_filter_position_count(
+        &mut self,
+        id: u8,
+        filter_position_count: u8,
+    ) -> DriverResult<()> {
+        self.send(CommandFrame::new(
+            id,
+            Command::Configure(Parameter::FilterPositionCount, filter_position_count as i32),
+        ))
+        .await?;
         Ok(())
     }
 
@@ -452,28 +1728,67 @@ impl LSSDriver {
     ///
     /// * `id` - ID of servo you want to query
     pub async fn query_filter_position_count(&mut self, id: u8) -> DriverResult<u8> {
-        self.driver.send(LssCommand::simple(id, "QFPC")).await?;
-        let response = self.driver.receive().await?;
-        let (_, value) = response.separate("QFPC")?;
-        Ok(value as u8)
+        self.query_value(
+            id,
+            Query::Parameter(Parameter::FilterPositionCount),
+            |value| Ok(value as u8),
+        )
+        .await
+    }
+
+    /// Query filter position count saved in EEPROM
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to query
+    pub async fn query_configured_filter_position_count(&mut self, id: u8) -> DriverResult<u8> {
+        self.query_value(
+            id,
+            Query::ConfiguredParameter(Parameter::FilterPositionCount),
+            |value| Ok(value as u8),
+        )
+        .await
+    }
+
+    /// Set angular stiffness
+    ///
+    /// Read more about [Angular stiffness](https://www.robotshop.com/info/wiki/lynxmotion/view/lynxmotion-smart-servo/lss-communication-protocol/#HAngularStiffness28AS29)
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to control
+    /// * `angular_stiffness` - value for angular stiffness (-10 to 10) (recommended -4 to 4)
+    pub async fn set_angular_stiffness(
+        &mut self,
+        id: u8,
+        angular_stiffness: i32,
+    ) -> DriverResult<()> {
+        self.send(CommandFrame::new(
+            id,
+            Command::Set(Parameter::AngularStiffness, angular_stiffness),
+        ))
+        .await?;
+        Ok(())
     }
 
-    /// Set angular stiffness
+    /// Set angular stiffness and save it to EEPROM
     ///
-    /// Read more about [Angular stiffness](https://www.robotshop.com/info/wiki/lynxmotion/view/lynxmotion-smart-servo/lss-communication-protocol/#HAngularStiffness28AS29)
+    /// Same as [set_angular_stiffness](LSSDriver::set_angular_stiffness) but persists after a reset or power cycle
     ///
     /// # Arguments
     ///
     /// * `id` - ID of servo you want to control
     /// * `angular_stiffness` - value for angular stiffness (-10 to 10) (recommended -4 to 4)
-    pub async fn set_angular_stiffness(
+    pub async fn configure_angular_stiffness(
         &mut self,
         id: u8,
         angular_stiffness: i32,
     ) -> DriverResult<()> {
-        self.driver
-            .send(LssCommand::with_param(id, "AS", angular_stiffness))
-            .await?;
+        self.send(CommandFrame::new(
+            id,
+            Command::Configure(Parameter::AngularStiffness, angular_stiffness),
+        ))
+        .await?;
         Ok(())
     }
 
@@ -485,10 +1800,22 @@ impl LSSDriver {
     ///
     /// * `id` - ID of servo you want to query
     pub async fn query_angular_stiffness(&mut self, id: u8) -> DriverResult<i32> {
-        self.driver.send(LssCommand::simple(id, "QAS")).await?;
-        let response = self.driver.receive().await?;
-        let (_, value) = response.separate("QAS")?;
-        Ok(value)
+        self.query_value(id, Query::Parameter(Parameter::AngularStiffness), Ok)
+            .await
+    }
+
+    /// Query angular stiffness saved in EEPROM
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to query
+    pub async fn query_configured_angular_stiffness(&mut self, id: u8) -> DriverResult<i32> {
+        self.query_value(
+            id,
+            Query::ConfiguredParameter(Parameter::AngularStiffness),
+            Ok,
+        )
+        .await
     }
 
     /// Set angular holding stiffness
@@ -504,9 +1831,32 @@ impl LSSDriver {
         id: u8,
         angular_holding: i32,
     ) -> DriverResult<()> {
-        self.driver
-            .send(LssCommand::with_param(id, "AH", angular_holding))
-            .await?;
+        self.send(CommandFrame::new(
+            id,
+            Command::Set(Parameter::AngularHoldingStiffness, angular_holding),
+        ))
+        .await?;
+        Ok(())
+    }
+
+    /// Set angular holding stiffness and save it to EEPROM
+    ///
+    /// Same as [set_angular_holding_stiffness](LSSDriver::set_angular_holding_stiffness) but persists after a reset or power cycle
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to control
+    /// * `angular_holding` - value for angular holding stiffness (-10 to 10)
+    pub async fn configure_angular_holding_stiffness(
+        &mut self,
+        id: u8,
+        angular_holding: i32,
+    ) -> DriverResult<()> {
+        self.send(CommandFrame::new(
+            id,
+            Command::Configure(Parameter::AngularHoldingStiffness, angular_holding),
+        ))
+        .await?;
         Ok(())
     }
 
@@ -518,10 +1868,25 @@ impl LSSDriver {
     ///
     /// * `id` - ID of servo you want to control
     pub async fn query_angular_holding_stiffness(&mut self, id: u8) -> DriverResult<i32> {
-        self.driver.send(LssCommand::simple(id, "QAH")).await?;
-        let response = self.driver.receive().await?;
-        let (_, value) = response.separate("QAH")?;
-        Ok(value)
+        self.query_value(id, Query::Parameter(Parameter::AngularHoldingStiffness), Ok)
+            .await
+    }
+
+    /// Query angular holding stiffness saved in EEPROM
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to query
+    pub async fn query_configured_angular_holding_stiffness(
+        &mut self,
+        id: u8,
+    ) -> DriverResult<i32> {
+        self.query_value(
+            id,
+            Query::ConfiguredParameter(Parameter::AngularHoldingStiffness),
+            Ok,
+        )
+        .await
     }
 
     /// Set angular acceleration in degrees per second squared (°/s2)
@@ -540,9 +1905,32 @@ impl LSSDriver {
         id: u8,
         angular_acceleration: i32,
     ) -> DriverResult<()> {
-        self.driver
-            .send(LssCommand::with_param(id, "AA", angular_acceleration))
-            .await?;
+        self.send(CommandFrame::new(
+            id,
+            Command::Set(Parameter::AngularAcceleration, angular_acceleration),
+        ))
+        .await?;
+        Ok(())
+    }
+
+    /// Set angular acceleration in degrees per second squared (°/s2) and save it to EEPROM
+    ///
+    /// Same as [set_angular_acceleration](LSSDriver::set_angular_acceleration) but persists after a reset or power cycle
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to control
+    /// * `angular_acceleration` - value for angular acceleration (1 to 100, Increments 10)
+    pub async fn configure_angular_acceleration(
+        &mut self,
+        id: u8,
+        angular_acceleration: i32,
+    ) -> DriverResult<()> {
+        self.send(CommandFrame::new(
+            id,
+            Command::Configure(Parameter::AngularAcceleration, angular_acceleration),
+        ))
+        .await?;
         Ok(())
     }
 
@@ -557,10 +1945,22 @@ impl LSSDriver {
     ///
     /// * `id` - ID of servo you want to query
     pub async fn query_angular_acceleration(&mut self, id: u8) -> DriverResult<i32> {
-        self.driver.send(LssCommand::simple(id, "QAA")).await?;
-        let response = self.driver.receive().await?;
-        let (_, value) = response.separate("QAA")?;
-        Ok(value)
+        self.query_value(id, Query::Parameter(Parameter::AngularAcceleration), Ok)
+            .await
+    }
+
+    /// Query angular acceleration in degrees per second squared (°/s2) saved in EEPROM
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to query
+    pub async fn query_configured_angular_acceleration(&mut self, id: u8) -> DriverResult<i32> {
+        self.query_value(
+            id,
+            Query::ConfiguredParameter(Parameter::AngularAcceleration),
+            Ok,
+        )
+        .await
     }
 
     /// Set angular deceleration in degrees per second squared (°/s2)
@@ -579,9 +1979,32 @@ impl LSSDriver {
         id: u8,
         angular_deceleration: i32,
     ) -> DriverResult<()> {
-        self.driver
-            .send(LssCommand::with_param(id, "AD", angular_deceleration))
-            .await?;
+        self.send(CommandFrame::new(
+            id,
+            Command::Set(Parameter::AngularDeceleration, angular_deceleration),
+        ))
+        .await?;
+        Ok(())
+    }
+
+    /// Set angular deceleration in degrees per second squared (°/s2) and save it to EEPROM
+    ///
+    /// Same as [set_angular_deceleration](LSSDriver::set_angular_deceleration) but persists after a reset or power cycle
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to control
+    /// * `angular_deceleration` - value for angular deceleration (1 to 100, Increments 10)
+    pub async fn configure_angular_deceleration(
+        &mut self,
+        id: u8,
+        angular_deceleration: i32,
+    ) -> DriverResult<()> {
+        self.send(CommandFrame::new(
+            id,
+            Command::Configure(Parameter::AngularDeceleration, angular_deceleration),
+        ))
+        .await?;
         Ok(())
     }
 
@@ -596,10 +2019,22 @@ impl LSSDriver {
     ///
     /// * `id` - ID of servo you want to query
     pub async fn query_angular_deceleration(&mut self, id: u8) -> DriverResult<i32> {
-        self.driver.send(LssCommand::simple(id, "QAD")).await?;
-        let response = self.driver.receive().await?;
-        let (_, value) = response.separate("QAD")?;
-        Ok(value)
+        self.query_value(id, Query::Parameter(Parameter::AngularDeceleration), Ok)
+            .await
+    }
+
+    /// Query angular deceleration in degrees per second squared (°/s2) saved in EEPROM
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to query
+    pub async fn query_configured_angular_deceleration(&mut self, id: u8) -> DriverResult<i32> {
+        self.query_value(
+            id,
+            Query::ConfiguredParameter(Parameter::AngularDeceleration),
+            Ok,
+        )
+        .await
     }
 
     /// Set maximum motor duty
@@ -618,9 +2053,32 @@ impl LSSDriver {
         id: u8,
         maximum_motor_duty: i32,
     ) -> DriverResult<()> {
-        self.driver
-            .send(LssCommand::with_param(id, "MMD", maximum_motor_duty))
-            .await?;
+        self.send(CommandFrame::new(
+            id,
+            Command::Set(Parameter::MaximumMotorDuty, maximum_motor_duty),
+        ))
+        .await?;
+        Ok(())
+    }
+
+    /// Set maximum motor duty and save it to EEPROM
+    ///
+    /// Same as [set_maximum_motor_duty](LSSDriver::set_maximum_motor_duty) but persists after a reset or power cycle
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to control
+    /// * `maximum_motor_duty` - value for maximum motor duty (255 to 1023)
+    pub async fn configure_maximum_motor_duty(
+        &mut self,
+        id: u8,
+        maximum_motor_duty: i32,
+    ) -> DriverResult<()> {
+        self.send(CommandFrame::new(
+            id,
+            Command::Configure(Parameter::MaximumMotorDuty, maximum_motor_duty),
+        ))
+        .await?;
         Ok(())
     }
 
@@ -635,10 +2093,22 @@ impl LSSDriver {
     ///
     /// * `id` - ID of servo you want to query
     pub async fn query_maximum_motor_duty(&mut self, id: u8) -> DriverResult<i32> {
-        self.driver.send(LssCommand::simple(id, "QMMD")).await?;
-        let response = self.driver.receive().await?;
-        let (_, value) = response.separate("QMMD")?;
-        Ok(value)
+        self.query_value(id, Query::Parameter(Parameter::MaximumMotorDuty), Ok)
+            .await
+    }
+
+    /// Query maximum motor duty saved in EEPROM
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to query
+    pub async fn query_configured_maximum_motor_duty(&mut self, id: u8) -> DriverResult<i32> {
+        self.query_value(
+            id,
+            Query::ConfiguredParameter(Parameter::MaximumMotorDuty),
+            Ok,
+        )
+        .await
     }
 
     /// Set maximum speed in degrees per second
@@ -652,13 +2122,48 @@ impl LSSDriver {
     /// * `id` - ID of servo you want to control
     /// * `maximum_speed` - value for maximum speed
     pub async fn set_maximum_speed(&mut self, id: u8, maximum_speed: f32) -> DriverResult<()> {
-        self.driver
-            .send(LssCommand::with_param(
-                id,
-                "SD",
-                (maximum_speed * 10.) as i32,
-            ))
-            .await?;
+        self.send(CommandFrame::new(
+            id,
+            Command::Set(
+                Parameter::MaximumSpeed,
+                (maximum_speed * 10.0).round() as i32,
+            ),
+        ))
+        .await?;
+        Ok(())
+    }
+
+    /// Set maximum speed in degrees per second and save it to EEPROM
+    ///
+    /// Same as [set_maximum_speed](LSSDriver::set_maximum_speed) but persists after a reset or power cycle
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to control
+    /// * `maximum_speed` - value for maximum speed
+    ///
+    /// # Example
+    /// ```no_run
+    /// use lss_driver::LSSDriver;
+    ///
+    /// async fn async_main() {
+    ///     let mut driver = LSSDriver::with_baud_rate("COM1", 115200).unwrap();
+    ///     driver.configure_maximum_speed(5, 90.0).await;
+    /// }
+    /// ```
+    pub async fn configure_maximum_speed(
+        &mut self,
+        id: u8,
+        maximum_speed: f32,
+    ) -> DriverResult<()> {
+        self.send(CommandFrame::new(
+            id,
+            Command::Configure(
+                Parameter::MaximumSpeed,
+                (maximum_speed * 10.0).round() as i32,
+            ),
+        ))
+        .await?;
         Ok(())
     }
 
@@ -672,10 +2177,215 @@ impl LSSDriver {
     ///
     /// * `id` - ID of servo you want to query
     pub async fn query_maximum_speed(&mut self, id: u8) -> DriverResult<f32> {
-        self.driver.send(LssCommand::simple(id, "QSD")).await?;
-        let response = self.driver.receive().await?;
-        let (_, value) = response.separate("QSD")?;
-        Ok(value as f32 / 10.)
+        self.query_value(id, Query::Parameter(Parameter::MaximumSpeed), |value| {
+            Ok(value as f32 / 10.)
+        })
+        .await
+    }
+
+    /// Query maximum speed in degrees per second saved in EEPROM
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to query
+    pub async fn query_configured_maximum_speed(&mut self, id: u8) -> DriverResult<f32> {
+        self.query_value(
+            id,
+            Query::ConfiguredParameter(Parameter::MaximumSpeed),
+            |value| Ok(value as f32 / 10.0),
+        )
+        .await
+    }
+
+    /// Set direction of rotation for this session
+    ///
+    /// With [Gyre::CounterClockwise] everything is mirrored: [move_to_position](LSSDriver::move_to_position)
+    /// turns the other way, [query_position](LSSDriver::query_position) reports mirrored angles
+    /// and the origin offset is applied in the mirrored frame.
+    /// Mirrored joints can then use the same angles as their counterparts.
+    /// Change it while the servo is still so it doesn't jump to the mirrored target.
+    ///
+    /// Read more on the [wiki](https://www.robotshop.com/info/wiki/lynxmotion/view/lynxmotion-smart-servo/lss-communication-protocol/#HGyreDirection28G29)
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to control
+    /// * `gyre` - Direction of rotation
+    ///
+    /// # Example
+    /// ```no_run
+    /// use lss_driver::{Gyre, LSSDriver};
+    ///
+    /// async fn async_main() {
+    ///     let mut driver = LSSDriver::with_baud_rate("COM1", 115200).unwrap();
+    ///     driver.set_gyre(6, Gyre::CounterClockwise).await;
+    ///     // both legs move forward
+    ///     driver.move_to_position(5, 30.0).await;
+    ///     driver.move_to_position(6, 30.0).await;
+    /// }
+    /// ```
+    pub async fn set_gyre(&mut self, id: u8, gyre: Gyre) -> DriverResult<()> {
+        self.send(CommandFrame::new(
+            id,
+            Command::Set(Parameter::Gyre, gyre as i32),
+        ))
+        .await?;
+        Ok(())
+    }
+
+    /// Set direction of rotation and save it to EEPROM
+    ///
+    /// Same as [set_gyre](LSSDriver::set_gyre) but persists after a reset or power cycle
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to control
+    /// * `gyre` - Direction of rotation
+    pub async fn configure_gyre(&mut self, id: u8, gyre: Gyre) -> DriverResult<()> {
+        self.send(CommandFrame::new(
+            id,
+            Command::Configure(Parameter::Gyre, gyre as i32),
+        ))
+        .await?;
+        Ok(())
+    }
+
+    /// Query direction of rotation
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to query
+    pub async fn query_gyre(&mut self, id: u8) -> DriverResult<Gyre> {
+        self.query_value(id, Query::Parameter(Parameter::Gyre), Gyre::from_i32)
+            .await
+    }
+
+    /// Query direction of rotation saved in EEPROM
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to query
+    pub async fn query_configured_gyre(&mut self, id: u8) -> DriverResult<Gyre> {
+        self.query_value(
+            id,
+            Query::ConfiguredParameter(Parameter::Gyre),
+            Gyre::from_i32,
+        )
+        .await
+    }
+
+    /// Set position in degrees the servo moves to on power-up
+    ///
+    /// Saved to EEPROM. Also used after [reset](LSSDriver::reset).
+    /// Without a first position the servo stays limp on power-up
+    ///
+    /// Read more on the [wiki](https://www.robotshop.com/info/wiki/lynxmotion/view/lynxmotion-smart-servo/lss-communication-protocol/#HConfigureFirstPosition28CFD29)
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to control
+    /// * `position` - Absolute position in degrees
+    ///
+    /// # Example
+    /// ```no_run
+    /// use lss_driver::LSSDriver;
+    ///
+    /// async fn async_main() {
+    ///     let mut driver = LSSDriver::with_baud_rate("COM1", 115200).unwrap();
+    ///     driver.configure_first_position(5, 90.0).await;
+    /// }
+    /// ```
+    pub async fn configure_first_position(&mut self, id: u8, position: f32) -> DriverResult<()> {
+        self.send(CommandFrame::new(
+            id,
+            Command::ConfigureSetting(Setting::FirstPosition, (position * 10.0).round() as i32),
+        ))
+        .await?;
+        Ok(())
+    }
+
+    /// Clear first position so the servo stays limp on power-up
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to control
+    pub async fn clear_first_position(&mut self, id: u8) -> DriverResult<()> {
+        self.send(CommandFrame::new(id, Command::ClearFirstPosition))
+            .await?;
+        Ok(())
+    }
+
+    /// Query position in degrees the servo moves to on power-up
+    ///
+    /// `None` if the servo stays limp on power-up
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to query
+    pub async fn query_first_position(&mut self, id: u8) -> DriverResult<Option<f32>> {
+        self.query_parsed(
+            id,
+            Query::Setting(Setting::FirstPosition),
+            |reply| match reply {
+                Reply::Value(_, value) => Ok(Some(value as f32 / 10.0)),
+                Reply::Empty(_) => Ok(None),
+                Reply::Text(..) => Err(LssDriverError::PacketParsingError(
+                    "Expected number".to_owned(),
+                )),
+            },
+        )
+        .await
+    }
+
+    /// Save first position, LED color and motion profile to EEPROM
+    ///
+    /// Values take effect on the next power-up or [reset](LSSDriver::reset).
+    /// Works with [BROADCAST_ID] to configure the whole bus at once
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to control
+    /// * `profile` - Power-on configuration
+    ///
+    /// # Example
+    /// ```no_run
+    /// use lss_driver::{BootProfile, LSSDriver, LedColor, BROADCAST_ID};
+    ///
+    /// async fn async_main() {
+    ///     let mut driver = LSSDriver::with_baud_rate("COM1", 115200).unwrap();
+    ///     let profile = BootProfile {
+    ///         first_position: Some(0.0),
+    ///         color: LedColor::Green,
+    ///         motion_profile: true,
+    ///     };
+    ///     driver.configure_boot_profile(BROADCAST_ID, profile).await.unwrap();
+    /// }
+    /// ```
+    pub async fn configure_boot_profile(
+        &mut self,
+        id: u8,
+        profile: BootProfile,
+    ) -> DriverResult<()> {
+        match profile.first_position {
+            Some(position) => self.configure_first_position(id, position).await?,
+            None => self.clear_first_position(id).await?,
+        }
+        self.configure_color(id, profile.color).await?;
+        self.configure_motion_profile(id, profile.motion_profile)
+            .await
+    }
+
+    /// Query first position, LED color and motion profile saved in EEPROM
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to query
+    pub async fn query_boot_profile(&mut self, id: u8) -> DriverResult<BootProfile> {
+        Ok(BootProfile {
+            first_position: self.query_first_position(id).await?,
+            color: self.query_configured_color(id).await?,
+            motion_profile: self.query_configured_motion_profile(id).await?,
+        })
     }
 
     /// Disables power to motor allowing it to be back driven
@@ -684,7 +2394,7 @@ impl LSSDriver {
     ///
     /// * `id` - ID of servo you want to control
     pub async fn limp(&mut self, id: u8) -> DriverResult<()> {
-        self.driver.send(LssCommand::simple(id, "L")).await?;
+        self.send(CommandFrame::new(id, Command::Limp)).await?;
         Ok(())
     }
 
@@ -694,7 +2404,7 @@ impl LSSDriver {
     ///
     /// * `id` - ID of servo you want to control
     pub async fn halt_hold(&mut self, id: u8) -> DriverResult<()> {
-        self.driver.send(LssCommand::simple(id, "H")).await?;
+        self.send(CommandFrame::new(id, Command::HaltHold)).await?;
         Ok(())
     }
 
@@ -706,10 +2416,8 @@ impl LSSDriver {
     pub async fn query_voltage(&mut self, id: u8) -> DriverResult<f32> {
         // response message looks like *5QV11200<cr>
         // Response is in mV
-        self.driver.send(LssCommand::simple(id, "QV")).await?;
-        let response = self.driver.receive().await?;
-        let (_, value) = response.separate("QV")?;
-        Ok(value as f32 / 1000.0)
+        self.query_value(id, Query::Voltage, |value| Ok(value as f32 / 1000.0))
+            .await
     }
 
     /// Query temperature of motor in celsius
@@ -721,10 +2429,8 @@ impl LSSDriver {
         // response message looks like *5QT441<cr>
         // Response is in 10s of celsius
         // 441 would be 44.1 celsius
-        self.driver.send(LssCommand::simple(id, "QT")).await?;
-        let response = self.driver.receive().await?;
-        let (_, value) = response.separate("QT")?;
-        Ok(value as f32 / 10.0)
+        self.query_value(id, Query::Temperature, |value| Ok(value as f32 / 10.0))
+            .await
     }
 
     /// Query current of motor in Amps
@@ -735,10 +2441,161 @@ impl LSSDriver {
     pub async fn query_current(&mut self, id: u8) -> DriverResult<f32> {
         // response message looks like *5QT441<cr>
         // Response is in mA
-        self.driver.send(LssCommand::simple(id, "QC")).await?;
-        let response = self.driver.receive().await?;
-        let (_, value) = response.separate("QC")?;
-        Ok(value as f32 / 1000.0)
+        self.query_value(id, Query::Current, |value| Ok(value as f32 / 1000.0))
+            .await
+    }
+
+    /// Query status, position, speed, current, voltage and temperature at once
+    ///
+    /// All queries are sent back-to-back before reading the replies,
+    /// so the snapshot is taken in about one round trip instead of ten.
+    /// Speed is read with `QS`, `QSD2` and `QSR2` so it is reported during moves too.
+    /// Stale replies waiting on the bus are discarded first and replies are matched
+    /// in the order the queries were sent, following [ResponseValidation].
+    /// Each field has its own result. A missing or invalid reply only fails that field
+    /// and is queried again according to [RetryPolicy]. Sending errors fail the whole call.
+    /// Also updates [LSSDriver::expected_target]
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to query. Shouldn't be [BROADCAST_ID]
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use lss_driver::LSSDriver;
+    /// async fn async_main(){
+    ///     let mut driver = LSSDriver::with_baud_rate("COM1", 115200).unwrap();
+    ///     let telemetry = driver.query_telemetry(5).await.unwrap();
+    ///     if let Ok(temperature) = telemetry.temperature {
+    ///         println!("{}°C", temperature);
+    ///     }
+    /// }
+    /// ```
+    pub async fn query_telemetry(&mut self, id: u8) -> DriverResult<Telemetry> {
+        const QUERIES: [Query; 10] = [
+            Query::Status,
+            Query::Position,
+            Query::TargetPosition,
+            Query::PulsePosition,
+            Query::Speed,
+            Query::CurrentSpeed,
+            Query::CurrentSpeedRpm,
+            Query::Current,
+            Query::Voltage,
+            Query::Temperature,
+        ];
+        let commands: Vec<_> = QUERIES
+            .iter()
+            .map(|query| LssCommand::from(CommandFrame::new(id, Command::Query(*query))))
+            .collect();
+        let matches = move |index: usize, response: &LssResponse| {
+            response.is_response_to(id, QUERIES[index].echo())
+        };
+        let validation = self.settings.response_validation;
+        // one transaction so that nothing else is sent on a shared bus before all replies are read
+        let timeout = self.settings.timeout;
+        let responses = self
+            .transaction(Transaction {
+                commands: commands.clone(),
+                expected_replies: QUERIES.len(),
+                timeout,
+                drain_stale: true,
+                accept: Box::new(move |response| {
+                    validation != ResponseValidation::DiscardStale
+                        || (0..QUERIES.len()).any(|index| matches(index, response))
+                }),
+            })
+            .await
+            .map_err(|source| {
+                LssDriverError::command_failed(id, commands[0].as_str(), None, source)
+            })?;
+        // replies come back in the order the queries were sent.
+        // A reply never fills a field that comes before one already answered
+        let mut replies: Vec<Option<DriverResult<LssResponse>>> =
+            QUERIES.iter().map(|_| None).collect();
+        let mut next = 0;
+        for response in responses.responses {
+            if next == QUERIES.len() {
+                break;
+            }
+            if validation == ResponseValidation::Disabled {
+                replies[next] = Some(Ok(response));
+                next += 1;
+                continue;
+            }
+            match (next..QUERIES.len()).find(|&index| matches(index, &response)) {
+                Some(index) => {
+                    replies[index] = Some(Ok(response));
+                    next = index + 1;
+                }
+                None if validation == ResponseValidation::Strict => {
+                    replies[next] = Some(Err(LssDriverError::UnexpectedResponse {
+                        expected: format!("*{}{}", id, QUERIES[next].echo()),
+                        actual: response.as_str().to_owned(),
+                    }));
+                    next += 1;
+                }
+                None => {}
+            }
+        }
+        let mut fields = Vec::with_capacity(QUERIES.len());
+        for (index, reply) in replies.into_iter().enumerate() {
+            let mut reply = reply.unwrap_or(Err(LssDriverError::TimeoutError));
+            // the batch counts as the first attempt
+            for _ in 0..self.settings.retry_policy.retries {
+                if reply.is_ok() {
+                    break;
+                }
+                tokio::time::sleep(self.settings.retry_policy.backoff).await;
+                reply = self
+                    .query_matching_once(commands[index].clone(), id, QUERIES[index].echo(), false)
+                    .await;
+            }
+            let command = commands[index].as_str();
+            fields.push(
+                reply
+                    .map_err(|source| LssDriverError::command_failed(id, command, None, source))
+                    .and_then(|response| {
+                        response
+                            .as_str()
+                            .parse::<ReplyFrame>()
+                            .ok()
+                            .and_then(|frame| frame.reply.value())
+                            .ok_or_else(|| {
+                                LssDriverError::command_failed(
+                                    id,
+                                    command,
+                                    Some(response.as_str()),
+                                    LssDriverError::PacketParsingError(
+                                        "Expected number".to_owned(),
+                                    ),
+                                )
+                            })
+                    }),
+            );
+        }
+        let mut fields = fields.into_iter();
+        let mut field = || fields.next().unwrap();
+        let telemetry = Telemetry {
+            status: field().and_then(MotorStatus::from_i32),
+            position: field().map(|value| value as f32 / 10.0),
+            target_position: field().map(|value| value as f32 / 10.0),
+            pwm_position: field(),
+            pwm_speed: field(),
+            speed: field().map(|value| value as f32 / 10.0),
+            speed_rpm: field().map(|value| value as f32),
+            current: field().map(|value| value as f32 / 1000.0),
+            voltage: field().map(|value| value as f32 / 1000.0),
+            temperature: field().map(|value| value as f32 / 10.0),
+        };
+        match telemetry.target_position {
+            Ok(target) if id != BROADCAST_ID => {
+                self.targets.lock().unwrap().insert(id, target);
+            }
+            _ => {}
+        }
+        Ok(telemetry)
     }
 
     /// Query model string
@@ -747,10 +2604,8 @@ impl LSSDriver {
     ///
     /// * `id` - ID of servo you want to query
     pub async fn query_model(&mut self, id: u8) -> DriverResult<Model> {
-        self.driver.send(LssCommand::simple(id, "QMS")).await?;
-        let response = self.driver.receive().await?;
-        let (_, value) = response.separate_string("QMS")?;
-        Ok(Model::from_str(&value))
+        let model = self.query_text(id, Query::Model).await?;
+        Ok(Model::from_str(&model))
     }
 
     /// Query firmware version
@@ -759,10 +2614,7 @@ impl LSSDriver {
     ///
     /// * `id` - ID of servo you want to query
     pub async fn query_firmware_version(&mut self, id: u8) -> DriverResult<String> {
-        self.driver.send(LssCommand::simple(id, "QF")).await?;
-        let response = self.driver.receive().await?;
-        let (_, value) = response.separate_string("QF")?;
-        Ok(value)
+        self.query_text(id, Query::Firmware).await
     }
 
     /// Query serial number
@@ -771,36 +2623,59 @@ impl LSSDriver {
     ///
     /// * `id` - ID of servo you want to query
     pub async fn query_serial_number(&mut self, id: u8) -> DriverResult<String> {
-        self.driver.send(LssCommand::simple(id, "QN")).await?;
-        let response = self.driver.receive().await?;
-        let (_, value) = response.separate_string("QN")?;
-        Ok(value)
+        self.query_text(id, Query::SerialNumber).await
     }
 
     /// Set LED blinking mode
     ///
+    /// Saved to EEPROM
+    ///
     /// Read more on the [wiki](https://www.robotshop.com/info/wiki/lynxmotion/view/lynxmotion-smart-servo/lss-communication-protocol/#HConfigureLEDBlinking28CLB29)
     ///
     /// # Arguments
     ///
     /// * `id` - ID of servo you want to control
-    /// * `blinking_mode` - Blinking mode desired. Can be combination to make motor blink during multiple modes
+    /// * `blinking_mode` - Blinking mode desired. A single [LedBlinking], a list of them or [LedBlinkingFlags]
+    ///
+    /// # Example
+    /// ```no_run
+    /// use lss_driver::{LSSDriver, LedBlinking, LedBlinkingFlags};
+    ///
+    /// async fn async_main() {
+    ///     let mut driver = LSSDriver::with_baud_rate("COM1", 115200).unwrap();
+    ///     driver.set_led_blinking(5, LedBlinking::Limp).await;
+    ///     let flags = LedBlinkingFlags::from(LedBlinking::Limp) | LedBlinking::Holding;
+    ///     driver.set_led_blinking(5, flags).await;
+    /// }
+    /// ```
     pub async fn set_led_blinking(
         &mut self,
         id: u8,
-        blinking_mode: Vec<LedBlinking>,
+        blinking_mode: impl Into<LedBlinkingFlags>,
     ) -> DriverResult<()> {
-        let sum = blinking_mode
-            .iter()
-            .map(|item| *item as i32)
-            .sum::<i32>()
-            .min(LedBlinking::AlwaysBlink as i32);
-        self.driver
-            .send(LssCommand::with_param(id, "CLB", sum))
-            .await?;
+        let flags = blinking_mode.into();
+        self.send(CommandFrame::new(
+            id,
+            Command::ConfigureSetting(Setting::LedBlinking, flags.bits()),
+        ))
+        .await?;
         Ok(())
     }
 
+    /// Query LED blinking mode
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to query
+    pub async fn query_led_blinking(&mut self, id: u8) -> DriverResult<LedBlinkingFlags> {
+        self.query_value(
+            id,
+            Query::Setting(Setting::LedBlinking),
+            LedBlinkingFlags::from_bits,
+        )
+        .await
+    }
+
     /// Query origin offset in degrees
     ///
     /// Read more on the [wiki](https://www.robotshop.com/info/wiki/lynxmotion/view/lynxmotion-smart-servo/lss-communication-protocol/#HOriginOffset28O29)
@@ -821,10 +2696,24 @@ impl LSSDriver {
     pub async fn query_origin_offset(&mut self, id: u8) -> DriverResult<f32> {
         // response messages looks like *5QO-13
         // Response is in tenths of degrees
-        self.driver.send(LssCommand::simple(id, "QO")).await?;
-        let response = self.driver.receive().await?;
-        let (_, value) = response.separate("QO")?;
-        Ok(value as f32 / 10.0)
+        self.query_value(id, Query::Parameter(Parameter::OriginOffset), |value| {
+            Ok(value as f32 / 10.0)
+        })
+        .await
+    }
+
+    /// Query origin offset in degrees saved in EEPROM
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to query
+    pub async fn query_configured_origin_offset(&mut self, id: u8) -> DriverResult<f32> {
+        self.query_value(
+            id,
+            Query::ConfiguredParameter(Parameter::OriginOffset),
+            |value| Ok(value as f32 / 10.0),
+        )
+        .await
     }
 
     /// Query the angular range in degrees
@@ -848,15 +2737,30 @@ impl LSSDriver {
         // Response looks like *5QAR1800 where 1800 is range in 1/10 degrees
         // Contrary what the wiki says, which is *5AR1800 as example, the servo I used (HT1) returns
         // *5QAR1800
-        self.driver.send(LssCommand::simple(id, "QAR")).await?;
-
-        let response = self.driver.receive().await?;
-        let (_, value) = response.separate("QAR")?;
+        self.query_value(id, Query::Parameter(Parameter::AngularRange), |value| {
+            Ok(value as f32 / 10.0)
+        })
+        .await
+    }
 
-        Ok(value as f32 / 10.0)
+    /// Query the angular range in degrees saved in EEPROM
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to query
+    pub async fn query_configured_angular_range(&mut self, id: u8) -> DriverResult<f32> {
+        self.query_value(
+            id,
+            Query::ConfiguredParameter(Parameter::AngularRange),
+            |value| Ok(value as f32 / 10.0),
+        )
+        .await
     }
 
-    /// Set the angular range in degrees
+    /// Set the angular range in degrees and save it to EEPROM
+    ///
+    /// Same as [configure_angular_range](LSSDriver::configure_angular_range).
+    /// Use [set_angular_range_session](LSSDriver::set_angular_range_session) to change it for this session only
     ///
     /// Read more on the [wiki](https://www.robotshop.com/info/wiki/lynxmotion/view/lynxmotion-smart-servo/lss-communication-protocol/#HAngularRange28AR29)
     ///
@@ -873,11 +2777,62 @@ impl LSSDriver {
     ///     let mut driver = LSSDriver::with_baud_rate("COM1", 115200).unwrap();
     ///     driver.set_angular_range(5, 180.0).await;
     /// }
+    /// ```
     pub async fn set_angular_range(&mut self, id: u8, range: f32) -> DriverResult<()> {
-        self.driver
-            .send(LssCommand::with_param(id, "CAR", (range * 10.) as i32))
-            .await?;
+        self.configure_angular_range(id, range).await
+    }
+
+    /// Set the angular range in degrees for this session
+    ///
+    /// Reverts to the value in EEPROM after a reset or power cycle
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of the servo you want to control
+    /// * `range` - Angular range in degrees
+    ///
+    /// # Example
+    /// ```no_run
+    /// use lss_driver::LSSDriver;
+    ///
+    /// async fn async_main() {
+    ///     let mut driver = LSSDriver::with_baud_rate("COM1", 115200).unwrap();
+    ///     driver.set_angular_range_session(5, 180.0).await;
+    /// }
+    /// ```
+    pub async fn set_angular_range_session(&mut self, id: u8, range: f32) -> DriverResult<()> {
+        self.send(CommandFrame::new(
+            id,
+            Command::Set(Parameter::AngularRange, (range * 10.0).round() as i32),
+        ))
+        .await?;
+        Ok(())
+    }
 
+    /// Set the angular range in degrees and save it to EEPROM
+    ///
+    /// Same as [set_angular_range_session](LSSDriver::set_angular_range_session) but persists after a reset or power cycle
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to control
+    /// * `range` - Angular range in degrees
+    ///
+    /// # Example
+    /// ```no_run
+    /// use lss_driver::LSSDriver;
+    ///
+    /// async fn async_main() {
+    ///     let mut driver = LSSDriver::with_baud_rate("COM1", 115200).unwrap();
+    ///     driver.configure_angular_range(5, 180.0).await;
+    /// }
+    /// ```
+    pub async fn configure_angular_range(&mut self, id: u8, range: f32) -> DriverResult<()> {
+        self.send(CommandFrame::new(
+            id,
+            Command::Configure(Parameter::AngularRange, (range * 10.0).round() as i32),
+        ))
+        .await?;
         Ok(())
     }
 
@@ -903,15 +2858,13 @@ impl LSSDriver {
     /// ```
     pub async fn query_pwm_position(&mut self, id: u8) -> DriverResult<i32> {
         // Response looks like *5QP2334 where 2335 is in µs
-        self.driver.send(LssCommand::simple(id, "QP")).await?;
-
-        let response = self.driver.receive().await?;
-        let (_, value) = response.separate("QP")?;
-
-        Ok(value)
+        self.query_value(id, Query::PulsePosition, Ok).await
     }
 
-    /// Set origin offset in degrees
+    /// Set origin offset in degrees and save it to EEPROM
+    ///
+    /// Same as [configure_origin_offset](LSSDriver::configure_origin_offset).
+    /// Use [set_origin_offset_session](LSSDriver::set_origin_offset_session) to change it for this session only
     ///
     /// Read more on the [wiki](https://www.robotshop.com/info/wiki/lynxmotion/view/lynxmotion-smart-servo/lss-communication-protocol/#HOriginOffset28O29)
     ///
@@ -930,13 +2883,74 @@ impl LSSDriver {
     /// }
     /// ```
     pub async fn set_origin_offset(&mut self, id: u8, origin_offset: f32) -> DriverResult<()> {
-        self.driver
-            .send(LssCommand::with_param(
-                id,
-                "CO",
-                (origin_offset * 10.) as i32,
-            ))
-            .await?;
+        self.configure_origin_offset(id, origin_offset).await
+    }
+
+    /// Set origin offset in degrees for this session
+    ///
+    /// Reverts to the value in EEPROM after a reset or power cycle
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to control
+    /// * `origin_offset` - Offset from factory 0 in degrees
+    ///
+    /// # Example
+    /// ```no_run
+    /// use lss_driver::LSSDriver;
+    ///
+    /// async fn async_main() {
+    ///     let mut driver = LSSDriver::with_baud_rate("COM1", 115200).unwrap();
+    ///     driver.set_origin_offset_session(5, -1.3).await;
+    /// }
+    /// ```
+    pub async fn set_origin_offset_session(
+        &mut self,
+        id: u8,
+        origin_offset: f32,
+    ) -> DriverResult<()> {
+        self.send(CommandFrame::new(
+            id,
+            Command::Set(
+                Parameter::OriginOffset,
+                (origin_offset * 10.0).round() as i32,
+            ),
+        ))
+        .await?;
+        Ok(())
+    }
+
+    /// Set origin offset in degrees and save it to EEPROM
+    ///
+    /// Same as [set_origin_offset_session](LSSDriver::set_origin_offset_session) but persists after a reset or power cycle
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to control
+    /// * `origin_offset` - Offset from factory 0 in degrees
+    ///
+    /// # Example
+    /// ```no_run
+    /// use lss_driver::LSSDriver;
+    ///
+    /// async fn async_main() {
+    ///     let mut driver = LSSDriver::with_baud_rate("COM1", 115200).unwrap();
+    ///     driver.configure_origin_offset(5, -1.3).await;
+    /// }
+    /// ```
+    pub async fn configure_origin_offset(
+        &mut self,
+        id: u8,
+        origin_offset: f32,
+    ) -> DriverResult<()> {
+        self.send(CommandFrame::new(
+            id,
+            Command::Configure(
+                Parameter::OriginOffset,
+                (origin_offset * 10.0).round() as i32,
+            ),
+        ))
+        .await?;
         Ok(())
     }
 
@@ -961,8 +2975,7 @@ impl LSSDriver {
     /// }
     /// ```
     pub async fn move_to_pwm_position(&mut self, id: u8, position: i32) -> DriverResult<()> {
-        self.driver
-            .send(LssCommand::with_param(id, "P", position))
+        self.send(CommandFrame::new(id, Command::MovePulse(position)))
             .await?;
 
         Ok(())
@@ -995,9 +3008,10 @@ impl LSSDriver {
         position: i32,
         modifier: CommandModifier,
     ) -> DriverResult<()> {
-        self.driver
-            .send(LssCommand::with_param_modifier(id, "P", position, modifier))
-            .await?;
+        self.send(
+            CommandFrame::new(id, Command::MovePulse(position)).with_command_modifiers(&[modifier]),
+        )
+        .await?;
 
         Ok(())
     }
@@ -1029,22 +3043,67 @@ impl LSSDriver {
         position: i32,
         modifiers: &[CommandModifier],
     ) -> DriverResult<()> {
-        self.driver
-            .send(LssCommand::with_param_modifiers(
-                id, "P", position, modifiers,
-            ))
-            .await?;
+        self.send(
+            CommandFrame::new(id, Command::MovePulse(position)).with_command_modifiers(modifiers),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Query the current angular range as [PwmScale] for converting PWM positions
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of servo you want to query
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use lss_driver::LSSDriver;
+    /// async fn async_main(){
+    ///     let mut driver = LSSDriver::with_baud_rate("COM1", 115200).unwrap();
+    ///     let scale = driver.query_pwm_scale(5).await.unwrap();
+    ///     driver.move_to_pwm_position(5, scale.to_pwm(30.0)).await;
+    /// }
+    /// ```
+    pub async fn query_pwm_scale(&mut self, id: u8) -> DriverResult<PwmScale> {
+        Ok(PwmScale::new(self.query_angular_range(id).await?))
+    }
+}
 
+/// Fail with [LssDriverError::ServoFault] if `status` is a fault
+fn check_fault(id: u8, status: MotorStatus) -> DriverResult<()> {
+    if status.is_fault() {
+        Err(LssDriverError::ServoFault { id, status })
+    } else {
         Ok(())
     }
 }
 
+/// Sleep until the next poll or fail with [LssDriverError::WaitTimeout] if `deadline` passed
+async fn next_poll(
+    id: u8,
+    status: MotorStatus,
+    deadline: tokio::time::Instant,
+    options: WaitOptions,
+) -> DriverResult<()> {
+    let now = tokio::time::Instant::now();
+    if now >= deadline {
+        return Err(LssDriverError::WaitTimeout { id, status });
+    }
+    tokio::time::sleep(options.poll_interval.min(deadline - now)).await;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
-    use super::serial_driver::LssResponse;
     use super::*;
+    use crate::simulator::{Faults, VirtualBus, VirtualServo};
     use approx::assert_relative_eq;
     use async_trait::async_trait;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
 
     struct MockedDriver {
         expected_send: Vec<String>,
@@ -1060,7 +3119,18 @@ mod tests {
         }
 
         async fn receive(&mut self) -> DriverResult<LssResponse> {
-            Ok(LssResponse::new(self.receive.pop().unwrap()))
+            self.receive
+                .pop()
+                .map(LssResponse::new)
+                .ok_or(LssDriverError::TimeoutError)
+        }
+
+        async fn receive_timeout(&mut self, timeout: Duration) -> DriverResult<LssResponse> {
+            // replies only arrive after a command is sent so there is never anything to drain
+            if timeout.is_zero() {
+                return Err(LssDriverError::TimeoutError);
+            }
+            self.receive().await
         }
     }
 
@@ -1088,6 +3158,201 @@ mod tests {
         assert_relative_eq!(voltage, 11.2);
     }
 
+    #[tokio::test]
+    async fn test_response_from_wrong_servo_is_rejected() {
+        let mocked_framed_driver = MockedDriver {
+            expected_send: vec!["#5QD\r".to_owned()],
+            receive: vec!["*3QD132\r".to_owned()],
+        };
+        let mut driver = LSSDriver::with_driver(Box::new(mocked_framed_driver));
+        let err = driver.query_position(5).await.unwrap_err();
+        assert_eq!(err.servo_id(), Some(5));
+        match err.root_cause() {
+            LssDriverError::UnexpectedResponse { expected, actual } => {
+                assert_eq!(expected, "*5QD");
+                assert_eq!(actual, "*3QD132\r");
+            }
+            other => panic!("Unexpected error {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_response_for_wrong_command_is_rejected() {
+        let mocked_framed_driver = MockedDriver {
+            expected_send: vec!["#5Q\r".to_owned()],
+            receive: vec!["*5QD132\r".to_owned()],
+        };
+        let mut driver = LSSDriver::with_driver(Box::new(mocked_framed_driver));
+        let err = driver.query_status(5).await.unwrap_err();
+        assert!(matches!(
+            err.root_cause(),
+            LssDriverError::UnexpectedResponse { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_stale_responses_are_discarded() {
+        let mocked_framed_driver = MockedDriver {
+            expected_send: vec!["#5QD\r".to_owned()],
+            receive: vec![
+                "*5QD132\r".to_owned(),
+                "*5QDT900\r".to_owned(),
+                "*3QD100\r".to_owned(),
+            ],
+        };
+        let mut driver = LSSDriver::with_driver(Box::new(mocked_framed_driver));
+        driver.set_response_validation(ResponseValidation::DiscardStale);
+        let position = driver.query_position(5).await.unwrap();
+        assert_relative_eq!(position, 13.2);
+    }
+
+    #[tokio::test]
+    async fn test_validation_can_be_disabled() {
+        let mocked_framed_driver = MockedDriver {
+            expected_send: vec!["#5QD\r".to_owned()],
+            receive: vec!["*3QD132\r".to_owned()],
+        };
+        let mut driver = LSSDriver::with_driver(Box::new(mocked_framed_driver));
+        driver.set_response_validation(ResponseValidation::Disabled);
+        let position = driver.query_position(5).await.unwrap();
+        assert_relative_eq!(position, 13.2);
+    }
+
+    #[tokio::test]
+    async fn test_errors_carry_command_context() {
+        let mocked_framed_driver = MockedDriver {
+            expected_send: vec!["#5QD\r".to_owned()],
+            receive: vec!["*5QDabc\r".to_owned()],
+        };
+        let mut driver = LSSDriver::with_driver(Box::new(mocked_framed_driver));
+        let err = driver.query_position(5).await.unwrap_err();
+        match &err {
+            LssDriverError::CommandFailed {
+                id,
+                command,
+                response,
+                source,
+            } => {
+                assert_eq!(*id, 5);
+                assert_eq!(command, "#5QD");
+                assert_eq!(response.as_deref(), Some("*5QDabc"));
+                assert!(matches!(**source, LssDriverError::PacketParsingError(_)));
+            }
+            other => panic!("Unexpected error {:?}", other),
+        }
+        assert!(std::error::Error::source(&err).is_some());
+        assert!(err.to_string().contains("servo 5"));
+    }
+
+    /// Transport that fails every send and counts attempts
+    struct FailingSend {
+        attempts: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl FramedDriver for FailingSend {
+        async fn send(&mut self, _command: LssCommand) -> DriverResult<()> {
+            self.attempts.fetch_add(1, Ordering::SeqCst);
+            Err(LssDriverError::SendingError(std::io::Error::other(
+                "port closed",
+            )))
+        }
+
+        async fn receive(&mut self) -> DriverResult<LssResponse> {
+            Err(LssDriverError::TimeoutError)
+        }
+    }
+
+    /// Transport that answers after 50ms
+    struct SlowDriver {
+        bus: VirtualBus,
+    }
+
+    #[async_trait]
+    impl FramedDriver for SlowDriver {
+        async fn send(&mut self, command: LssCommand) -> DriverResult<()> {
+            self.bus.send(command).await
+        }
+
+        async fn receive(&mut self) -> DriverResult<LssResponse> {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            self.bus.receive().await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_queries_are_retried() {
+        let faults = Faults {
+            drop_every: Some(2),
+            ..Faults::default()
+        };
+        let bus = VirtualBus::new(vec![VirtualServo::new(5, Model::ST1)]).with_faults(faults);
+        let mut driver = LSSDriver::with_driver(Box::new(bus));
+        driver.query_position(5).await.unwrap();
+        let res = driver.query_position(5).await;
+        assert!(res.unwrap_err().is_timeout());
+        driver.set_retry_policy(RetryPolicy::new(1, Duration::ZERO));
+        for _ in 0..4 {
+            driver.query_position(5).await.unwrap();
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_writes_are_not_retried_by_default() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let mut driver = LSSDriver::with_driver(Box::new(FailingSend {
+            attempts: attempts.clone(),
+        }));
+        driver.set_retry_policy(RetryPolicy::new(2, Duration::from_millis(5)));
+        assert!(driver.limp(5).await.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+        driver.set_retry_policy(RetryPolicy::new(2, Duration::from_millis(5)).retry_writes(true));
+        assert!(driver.limp(5).await.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_call_timeout_is_restored() {
+        let bus = VirtualBus::new(vec![VirtualServo::new(5, Model::ST1)]);
+        let mut driver = LSSDriver::with_driver(Box::new(SlowDriver { bus }));
+        driver.set_timeout(Duration::from_millis(10));
+        let res = driver.query_position(5).await;
+        assert!(res.unwrap_err().is_timeout());
+        driver
+            .call_timeout(Duration::from_millis(100))
+            .query_position(5)
+            .await
+            .unwrap();
+        let res = driver.query_position(5).await;
+        assert!(res.unwrap_err().is_timeout());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_expected_target_is_tracked() {
+        let bus = VirtualBus::new(vec![
+            VirtualServo::new(1, Model::ST1).with_position(10.0),
+            VirtualServo::new(2, Model::ST1),
+        ]);
+        let mut driver = LSSDriver::with_driver(Box::new(bus.clone()));
+        assert_eq!(driver.expected_target(1), None);
+        driver.move_relative(1, 5.0).await.unwrap();
+        assert_eq!(driver.expected_target(1), None);
+        assert_eq!(driver.step_target(1, 5.0).await.unwrap(), 20.0);
+        driver.move_to_position(2, 30.0).await.unwrap();
+        driver.move_relative(2, -2.5).await.unwrap();
+        assert_eq!(driver.expected_target(2), Some(27.5));
+        driver.move_relative(BROADCAST_ID, 1.0).await.unwrap();
+        assert_eq!(driver.expected_target(1), Some(21.0));
+        assert_eq!(driver.expected_target(2), Some(28.5));
+        driver.set_color(2, LedColor::Red).await.unwrap();
+        assert_eq!(driver.expected_target(2), Some(28.5));
+        driver.limp(2).await.unwrap();
+        assert_eq!(driver.expected_target(1), Some(21.0));
+        assert_eq!(driver.expected_target(2), None);
+        driver.halt_hold(BROADCAST_ID).await.unwrap();
+        assert_eq!(driver.expected_target(1), None);
+    }
+
     macro_rules! test_command {
         ($name:ident, $expected:expr, $command:expr) => {
             #[tokio::test]
@@ -1119,37 +3384,305 @@ mod tests {
         };
     }
 
-    macro_rules! test_query_float {
-        ($name:ident, $expected:expr, $recv:expr, $command:expr, $val:expr) => {
-            #[tokio::test]
-            #[allow(clippy::redundant_closure_call)]
-            async fn $name() {
-                let mocked_framed_driver = MockedDriver {
-                    expected_send: vec![$expected.to_owned()],
-                    receive: vec![$recv.to_owned()],
-                };
-                let driver = LSSDriver::with_driver(Box::new(mocked_framed_driver));
-                let res = ($command)(driver).await;
-                assert_relative_eq!(res, $val);
-            }
+    macro_rules! test_query_float {
+        ($name:ident, $expected:expr, $recv:expr, $command:expr, $val:expr) => {
+            #[tokio::test]
+            #[allow(clippy::redundant_closure_call)]
+            async fn $name() {
+                let mocked_framed_driver = MockedDriver {
+                    expected_send: vec![$expected.to_owned()],
+                    receive: vec![$recv.to_owned()],
+                };
+                let driver = LSSDriver::with_driver(Box::new(mocked_framed_driver));
+                let res = ($command)(driver).await;
+                assert_relative_eq!(res, $val);
+            }
+        };
+    }
+
+    test_command!(
+        test_hold_command,
+        "#4H\r",
+        |mut driver: LSSDriver| async move {
+            driver.halt_hold(4_u8).await.unwrap();
+        }
+    );
+
+    test_query!(
+        test_query_id,
+        "#254QID\r",
+        "*QID5\r",
+        |mut driver: LSSDriver| async move { driver.query_id(BROADCAST_ID).await.unwrap() },
+        5
+    );
+    test_command!(
+        test_configure_rc_mode,
+        "#5CRC1\r",
+        |mut driver: LSSDriver| async move {
+            driver
+                .configure_rc_mode(5, RcMode::Position, true)
+                .await
+                .unwrap();
+        }
+    );
+
+    test_command!(
+        test_configure_serial_mode_without_confirmation,
+        "#5CRC0\r",
+        |mut driver: LSSDriver| async move {
+            driver
+                .configure_rc_mode(5, RcMode::Serial, false)
+                .await
+                .unwrap();
+        }
+    );
+
+    #[tokio::test]
+    async fn test_query_telemetry() {
+        let mocked_framed_driver = MockedDriver {
+            expected_send: vec![
+                "#5QT\r".to_owned(),
+                "#5QV\r".to_owned(),
+                "#5QC\r".to_owned(),
+                "#5QSR2\r".to_owned(),
+                "#5QSD2\r".to_owned(),
+                "#5QS\r".to_owned(),
+                "#5QP\r".to_owned(),
+                "#5QDT\r".to_owned(),
+                "#5QD\r".to_owned(),
+                "#5Q\r".to_owned(),
+            ],
+            receive: vec![
+                "*5QT441\r".to_owned(),
+                "*5QV11200\r".to_owned(),
+                "*5QC?\r".to_owned(),
+                "*5QSR5\r".to_owned(),
+                "*5QSD300\r".to_owned(),
+                "*5QS333\r".to_owned(),
+                "*5QP1750\r".to_owned(),
+                "*4QDT450\r".to_owned(),
+                "*5QD-1800\r".to_owned(),
+                "*5Q6\r".to_owned(),
+            ],
+        };
+        let mut driver = LSSDriver::with_driver(Box::new(mocked_framed_driver));
+        let telemetry = driver.query_telemetry(5).await.unwrap();
+        assert!(!telemetry.is_complete());
+        assert_eq!(telemetry.status.unwrap(), MotorStatus::Holding);
+        assert_relative_eq!(telemetry.position.unwrap(), -180.0);
+        // reply from a different servo fails the field under strict validation
+        assert!(matches!(
+            telemetry.target_position.unwrap_err().root_cause(),
+            LssDriverError::UnexpectedResponse { .. }
+        ));
+        assert_eq!(telemetry.pwm_position.unwrap(), 1750);
+        assert_eq!(telemetry.pwm_speed.unwrap(), 333);
+        assert_relative_eq!(telemetry.speed.unwrap(), 30.0);
+        assert_relative_eq!(telemetry.speed_rpm.unwrap(), 5.0);
+        assert!(telemetry.current.is_err());
+        assert_relative_eq!(telemetry.voltage.unwrap(), 11.2);
+        assert_relative_eq!(telemetry.temperature.unwrap(), 44.1);
+        assert_eq!(driver.expected_target(5), None);
+    }
+
+    #[tokio::test]
+    async fn test_query_telemetry_retries_missing_fields() {
+        let mocked_framed_driver = MockedDriver {
+            expected_send: vec![
+                "#5QDT\r".to_owned(),
+                "#5QT\r".to_owned(),
+                "#5QV\r".to_owned(),
+                "#5QC\r".to_owned(),
+                "#5QSR2\r".to_owned(),
+                "#5QSD2\r".to_owned(),
+                "#5QS\r".to_owned(),
+                "#5QP\r".to_owned(),
+                "#5QDT\r".to_owned(),
+                "#5QD\r".to_owned(),
+                "#5Q\r".to_owned(),
+            ],
+            receive: vec![
+                "*5QDT450\r".to_owned(),
+                "*5QT441\r".to_owned(),
+                "*5QV11200\r".to_owned(),
+                "*5QC140\r".to_owned(),
+                "*5QSR0\r".to_owned(),
+                "*5QSD0\r".to_owned(),
+                "*5QS0\r".to_owned(),
+                "*5QP1750\r".to_owned(),
+                // late reply to an earlier status query, QDT reply is lost
+                "*5Q3\r".to_owned(),
+                "*5QD-900\r".to_owned(),
+                "*5Q6\r".to_owned(),
+            ],
+        };
+        let mut driver = LSSDriver::with_driver(Box::new(mocked_framed_driver));
+        driver.set_response_validation(ResponseValidation::DiscardStale);
+        driver.set_retry_policy(RetryPolicy::new(1, Duration::ZERO));
+        let telemetry = driver.query_telemetry(5).await.unwrap();
+        assert!(telemetry.is_complete());
+        assert_eq!(telemetry.status.unwrap(), MotorStatus::Holding);
+        assert_relative_eq!(telemetry.position.unwrap(), -90.0);
+        assert_relative_eq!(telemetry.target_position.unwrap(), 45.0);
+        assert_eq!(driver.expected_target(5), Some(45.0));
+    }
+
+    #[tokio::test]
+    async fn test_group_move_is_single_write() {
+        let mocked_framed_driver = MockedDriver {
+            expected_send: vec![
+                "#1D900SD600\r#2D-450SD200\r#3D100SD1\r".to_owned(),
+                "#3QD\r".to_owned(),
+                "#2QD\r".to_owned(),
+                "#1QD\r".to_owned(),
+            ],
+            receive: vec![
+                "*3QD100\r".to_owned(),
+                "*2QD-150\r".to_owned(),
+                "*1QD0\r".to_owned(),
+            ],
         };
+        let mut driver = LSSDriver::with_driver(Box::new(mocked_framed_driver));
+        let targets = HashMap::from([(1, 90.0), (2, -45.0), (3, 10.0)]);
+        let duration = driver
+            .start_group_move(&targets, GroupTiming::Speed(60.0))
+            .await
+            .unwrap();
+        assert_eq!(duration, Duration::from_millis(1500));
+        assert_eq!(driver.expected_target(2), Some(-45.0));
     }
 
     test_command!(
-        test_hold_command,
-        "#4H\r",
+        test_timed_group_move,
+        "#1D900T2000\r#2D-450T2000\r",
         |mut driver: LSSDriver| async move {
-            driver.halt_hold(4_u8).await.unwrap();
+            let targets = HashMap::from([(2, -45.0), (1, 90.0)]);
+            driver
+                .start_group_move(&targets, GroupTiming::Timed(Duration::from_secs(2)))
+                .await
+                .unwrap();
         }
     );
 
+    #[tokio::test]
+    async fn test_group_move_rejects_invalid_speed() {
+        let mut driver = LSSDriver::with_driver(Box::new(MockedDriver {
+            expected_send: vec![],
+            receive: vec![],
+        }));
+        let targets = HashMap::from([(1, 90.0)]);
+        let err = driver
+            .start_group_move(&targets, GroupTiming::Speed(0.0))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            LssDriverError::InvalidArgument { name: "speed", .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_group_move_rejects_too_slow_speed() {
+        let mut driver = LSSDriver::with_driver(Box::new(MockedDriver {
+            expected_send: vec!["#1QD\r".to_owned()],
+            receive: vec!["*1QD0\r".to_owned()],
+        }));
+        let targets = HashMap::from([(1, 90.0)]);
+        let err = driver
+            .start_group_move(&targets, GroupTiming::Speed(1e-30))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            LssDriverError::InvalidArgument { name: "speed", .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_rc_mode_requires_confirmation() {
+        let mut driver = LSSDriver::with_driver(Box::new(MockedDriver {
+            expected_send: vec![],
+            receive: vec![],
+        }));
+        let err = driver
+            .configure_rc_mode(BROADCAST_ID, RcMode::Wheel, false)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, LssDriverError::NotConfirmed { command, .. } if command == "CRC2"));
+    }
+
     test_query!(
-        test_query_id,
-        "#254QID\r",
-        "*QID5\r",
-        |mut driver: LSSDriver| async move { driver.query_id(BROADCAST_ID).await.unwrap() },
-        5
+        test_query_pwm_scale,
+        "#5QAR\r",
+        "*5QAR900\r",
+        |mut driver: LSSDriver| async move { driver.query_pwm_scale(5).await.unwrap() },
+        PwmScale::new(90.0)
+    );
+
+    #[tokio::test]
+    async fn test_factory_defaults_refuse_broadcast() {
+        let mut driver = LSSDriver::with_driver(Box::new(MockedDriver {
+            expected_send: vec![],
+            receive: vec![],
+        }));
+        let err = driver
+            .restore_factory_defaults(BROADCAST_ID, false)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, LssDriverError::BroadcastNotAllowed { .. }));
+        let err = driver
+            .enter_firmware_update_mode(BROADCAST_ID)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, LssDriverError::BroadcastNotAllowed { .. }));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_factory_defaults_are_verified() {
+        let mocked_framed_driver = MockedDriver {
+            expected_send: vec![
+                "#0QB\r".to_owned(),
+                "#0QID\r".to_owned(),
+                "#5RESET\r".to_owned(),
+                "#5CONFIRM\r".to_owned(),
+                "#5DEFAULT\r".to_owned(),
+            ],
+            receive: vec!["*0QB9600\r".to_owned(), "*QID0\r".to_owned()],
+        };
+        let mut driver = LSSDriver::with_driver(Box::new(mocked_framed_driver));
+        let err = driver.restore_factory_defaults(5, false).await.unwrap_err();
+        assert!(matches!(
+            err,
+            LssDriverError::VerificationFailed {
+                setting: "baud rate",
+                ..
+            }
+        ));
+    }
+
+    test_command!(
+        test_configure_baud_rate,
+        "#5CB500000\r",
+        |mut driver: LSSDriver| async move { driver.configure_baud_rate(5, 500000).await.unwrap() }
+    );
+    test_query!(
+        test_query_baud_rate,
+        "#5QB\r",
+        "*5QB9600\r",
+        |mut driver: LSSDriver| async move { driver.query_baud_rate(5).await.unwrap() },
+        9600
     );
+
+    #[tokio::test]
+    async fn test_unsupported_baud_rate_is_not_sent() {
+        let mut driver = LSSDriver::with_driver(Box::new(MockedDriver {
+            expected_send: vec![],
+            receive: vec![],
+        }));
+        let err = driver.configure_baud_rate(5, 12345).await.unwrap_err();
+        assert!(matches!(err, LssDriverError::UnsupportedBaudRate(12345)));
+    }
+
     test_command!(
         test_set_id,
         "#1CID2\r",
@@ -1194,6 +3727,38 @@ mod tests {
         "#1D200\r",
         |mut driver: LSSDriver| async move { driver.set_target_position(1, 20.0).await.unwrap() }
     );
+    test_command!(
+        test_move_relative,
+        "#1MD-123\r",
+        |mut driver: LSSDriver| async move { driver.move_relative(1, -12.3).await.unwrap() }
+    );
+    test_command!(
+        test_move_relative_with_modifier,
+        "#1MD900T1500\r",
+        |mut driver: LSSDriver| async move {
+            driver
+                .move_relative_with_modifier(1, 90.0, CommandModifier::Timed(1500))
+                .await
+                .unwrap()
+        }
+    );
+    test_command!(
+        test_move_relative_with_modifiers,
+        "#1MD900SD200CH400\r",
+        |mut driver: LSSDriver| async move {
+            driver
+                .move_relative_with_modifiers(
+                    1,
+                    90.0,
+                    &[
+                        CommandModifier::SpeedDegrees(200),
+                        CommandModifier::CurrentHold(400),
+                    ],
+                )
+                .await
+                .unwrap()
+        }
+    );
     test_query_float!(
         test_query_current_position,
         "#5QD\r",
@@ -1232,6 +3797,67 @@ mod tests {
         |mut driver: LSSDriver| async move { driver.query_rotation_speed(5).await.unwrap() },
         90.0
     );
+    test_command!(
+        test_set_rotation_speed_degrees_rounds,
+        "#5WD-91\r",
+        |mut driver: LSSDriver| async move { driver.set_rotation_speed(5, -90.6).await.unwrap() }
+    );
+    test_command!(
+        test_set_rotation_speed_degrees_with_modifiers,
+        "#5WD90T1000CH400\r",
+        |mut driver: LSSDriver| async move {
+            driver
+                .set_rotation_speed_with_modifiers(
+                    5,
+                    90.0,
+                    &[
+                        CommandModifier::Timed(1000),
+                        CommandModifier::CurrentHold(400),
+                    ],
+                )
+                .await
+                .unwrap()
+        }
+    );
+    test_command!(
+        test_set_rotation_speed_rpm,
+        "#5WR13\r",
+        |mut driver: LSSDriver| async move { driver.set_rotation_speed_rpm(5, 12.5).await.unwrap() }
+    );
+    test_command!(
+        test_set_rotation_speed_rpm_with_modifier,
+        "#5WR-20CL500\r",
+        |mut driver: LSSDriver| async move {
+            driver
+                .set_rotation_speed_rpm_with_modifier(5, -20.0, CommandModifier::CurrentLimp(500))
+                .await
+                .unwrap()
+        }
+    );
+    test_command!(
+        test_set_rotation_speed_rpm_with_modifiers,
+        "#5WR20T2000CH300\r",
+        |mut driver: LSSDriver| async move {
+            driver
+                .set_rotation_speed_rpm_with_modifiers(
+                    5,
+                    20.0,
+                    &[
+                        CommandModifier::Timed(2000),
+                        CommandModifier::CurrentHold(300),
+                    ],
+                )
+                .await
+                .unwrap()
+        }
+    );
+    test_query_float!(
+        test_query_rotation_speed_rpm,
+        "#5QWR\r",
+        "*5QWR-15\r",
+        |mut driver: LSSDriver| async move { driver.query_rotation_speed_rpm(5).await.unwrap() },
+        -15.0
+    );
 
     // Status
     test_query!(
@@ -1283,6 +3909,13 @@ mod tests {
         |mut driver: LSSDriver| async move { driver.query_color(5).await.unwrap() },
         LedColor::Cyan
     );
+    test_query!(
+        test_query_configured_led,
+        "#5QLED1\r",
+        "*5QLED5\r",
+        |mut driver: LSSDriver| async move { driver.query_configured_color(5).await.unwrap() },
+        LedColor::Cyan
+    );
 
     // motion profile
     test_command!(
@@ -1309,6 +3942,18 @@ mod tests {
         |mut driver: LSSDriver| async move { driver.query_motion_profile(5).await.unwrap() },
         false
     );
+    test_command!(
+        test_configure_motion_profile,
+        "#5CEM1\r",
+        |mut driver: LSSDriver| async move { driver.configure_motion_profile(5, true).await.unwrap() }
+    );
+    test_query!(
+        test_query_configured_motion_profile,
+        "#5QEM1\r",
+        "*5QEM0\r",
+        |mut driver: LSSDriver| async move { driver.query_configured_motion_profile(5).await.unwrap() },
+        false
+    );
 
     test_command!(
         test_set_filter_position_count,
@@ -1322,6 +3967,25 @@ mod tests {
         |mut driver: LSSDriver| async move { driver.query_filter_position_count(5).await.unwrap() },
         10
     );
+    test_command!(
+        test_configure_filter_position_count,
+        "#5CFPC10\r",
+        |mut driver: LSSDriver| async move {
+            driver.configure_filter_position_count(5, 10).await.unwrap()
+        }
+    );
+    test_query!(
+        test_query_configured_filter_position_count,
+        "#5QFPC1\r",
+        "*5QFPC4\r",
+        |mut driver: LSSDriver| async move {
+            driver
+                .query_configured_filter_position_count(5)
+                .await
+                .unwrap()
+        },
+        4
+    );
 
     test_command!(
         test_set_angular_stiffness,
@@ -1335,6 +3999,20 @@ mod tests {
         |mut driver: LSSDriver| async move { driver.query_angular_stiffness(5).await.unwrap() },
         -2
     );
+    test_command!(
+        test_configure_angular_stiffness,
+        "#5CAS-2\r",
+        |mut driver: LSSDriver| async move { driver.configure_angular_stiffness(5, -2).await.unwrap() }
+    );
+    test_query!(
+        test_query_configured_angular_stiffness,
+        "#5QAS1\r",
+        "*5QAS-2\r",
+        |mut driver: LSSDriver| async move {
+            driver.query_configured_angular_stiffness(5).await.unwrap()
+        },
+        -2
+    );
 
     test_command!(
         test_set_angular_holding_stiffness,
@@ -1348,6 +4026,28 @@ mod tests {
         |mut driver: LSSDriver| async move { driver.query_angular_holding_stiffness(5).await.unwrap() },
         3
     );
+    test_command!(
+        test_configure_angular_holding_stiffness,
+        "#5CAH3\r",
+        |mut driver: LSSDriver| async move {
+            driver
+                .configure_angular_holding_stiffness(5, 3)
+                .await
+                .unwrap()
+        }
+    );
+    test_query!(
+        test_query_configured_angular_holding_stiffness,
+        "#5QAH1\r",
+        "*5QAH3\r",
+        |mut driver: LSSDriver| async move {
+            driver
+                .query_configured_angular_holding_stiffness(5)
+                .await
+                .unwrap()
+        },
+        3
+    );
 
     test_command!(
         test_set_angular_acceleration,
@@ -1361,6 +4061,25 @@ mod tests {
         |mut driver: LSSDriver| async move { driver.query_angular_acceleration(5).await.unwrap() },
         30
     );
+    test_command!(
+        test_configure_angular_acceleration,
+        "#5CAA30\r",
+        |mut driver: LSSDriver| async move {
+            driver.configure_angular_acceleration(5, 30).await.unwrap()
+        }
+    );
+    test_query!(
+        test_query_configured_angular_acceleration,
+        "#5QAA1\r",
+        "*5QAA30\r",
+        |mut driver: LSSDriver| async move {
+            driver
+                .query_configured_angular_acceleration(5)
+                .await
+                .unwrap()
+        },
+        30
+    );
 
     test_command!(
         test_set_angular_deceleration,
@@ -1374,6 +4093,25 @@ mod tests {
         |mut driver: LSSDriver| async move { driver.query_angular_deceleration(5).await.unwrap() },
         30
     );
+    test_command!(
+        test_configure_angular_deceleration,
+        "#5CAD30\r",
+        |mut driver: LSSDriver| async move {
+            driver.configure_angular_deceleration(5, 30).await.unwrap()
+        }
+    );
+    test_query!(
+        test_query_configured_angular_deceleration,
+        "#5QAD1\r",
+        "*5QAD30\r",
+        |mut driver: LSSDriver| async move {
+            driver
+                .query_configured_angular_deceleration(5)
+                .await
+                .unwrap()
+        },
+        30
+    );
 
     test_command!(
         test_maximum_motor_duty,
@@ -1387,6 +4125,22 @@ mod tests {
         |mut driver: LSSDriver| async move { driver.query_maximum_motor_duty(5).await.unwrap() },
         512
     );
+    test_command!(
+        test_configure_maximum_motor_duty,
+        "#5CMMD512\r",
+        |mut driver: LSSDriver| async move {
+            driver.configure_maximum_motor_duty(5, 512).await.unwrap()
+        }
+    );
+    test_query!(
+        test_query_configured_maximum_motor_duty,
+        "#5QMMD1\r",
+        "*5QMMD512\r",
+        |mut driver: LSSDriver| async move {
+            driver.query_configured_maximum_motor_duty(5).await.unwrap()
+        },
+        512
+    );
 
     test_command!(
         test_maximum_speed,
@@ -1400,6 +4154,68 @@ mod tests {
         |mut driver: LSSDriver| async move { driver.query_maximum_speed(5).await.unwrap() },
         180.
     );
+    test_command!(
+        test_configure_maximum_speed,
+        "#5CSD1234\r",
+        |mut driver: LSSDriver| async move { driver.configure_maximum_speed(5, 123.4).await.unwrap() }
+    );
+    test_query_float!(
+        test_query_configured_maximum_speed,
+        "#5QSD1\r",
+        "*5QSD900\r",
+        |mut driver: LSSDriver| async move { driver.query_configured_maximum_speed(5).await.unwrap() },
+        90.
+    );
+
+    test_command!(
+        test_set_gyre,
+        "#5G-1\r",
+        |mut driver: LSSDriver| async move { driver.set_gyre(5, Gyre::CounterClockwise).await.unwrap() }
+    );
+    test_command!(
+        test_configure_gyre,
+        "#5CG1\r",
+        |mut driver: LSSDriver| async move { driver.configure_gyre(5, Gyre::Clockwise).await.unwrap() }
+    );
+    test_query!(
+        test_query_gyre,
+        "#5QG\r",
+        "*5QG-1\r",
+        |mut driver: LSSDriver| async move { driver.query_gyre(5).await.unwrap() },
+        Gyre::CounterClockwise
+    );
+    test_query!(
+        test_query_configured_gyre,
+        "#5QG1\r",
+        "*5QG1\r",
+        |mut driver: LSSDriver| async move { driver.query_configured_gyre(5).await.unwrap() },
+        Gyre::Clockwise
+    );
+
+    test_command!(
+        test_configure_first_position,
+        "#5CFD-900\r",
+        |mut driver: LSSDriver| async move { driver.configure_first_position(5, -90.0).await.unwrap() }
+    );
+    test_command!(
+        test_clear_first_position,
+        "#5CFD\r",
+        |mut driver: LSSDriver| async move { driver.clear_first_position(5).await.unwrap() }
+    );
+    test_query!(
+        test_query_first_position,
+        "#5QFD\r",
+        "*5QFD900\r",
+        |mut driver: LSSDriver| async move { driver.query_first_position(5).await.unwrap() },
+        Some(90.0)
+    );
+    test_query!(
+        test_query_first_position_disabled,
+        "#5QFD\r",
+        "*5QFD\r",
+        |mut driver: LSSDriver| async move { driver.query_first_position(5).await.unwrap() },
+        None
+    );
 
     // test telemetry queries
     test_query_float!(
@@ -1511,6 +4327,35 @@ mod tests {
         }
     );
 
+    test_command!(
+        test_blinking_mode_duplicates,
+        "#5CLB2\r",
+        |mut driver: LSSDriver| async move {
+            driver
+                .set_led_blinking(5, vec![LedBlinking::Holding, LedBlinking::Holding])
+                .await
+                .unwrap()
+        }
+    );
+    test_query!(
+        test_query_led_blinking,
+        "#5QLB\r",
+        "*5QLB12\r",
+        |mut driver: LSSDriver| async move { driver.query_led_blinking(5).await.unwrap() },
+        LedBlinkingFlags::from(LedBlinking::Accelerating) | LedBlinking::Decelerating
+    );
+
+    #[tokio::test]
+    async fn test_led_blinking_round_trips_through_servo() {
+        let bus = VirtualBus::new(vec![VirtualServo::new(5, Model::ST1)]);
+        let mut driver = LSSDriver::with_driver(Box::new(bus));
+        for bits in 0..=LedBlinking::AlwaysBlink as i32 {
+            let flags = LedBlinkingFlags::from_bits(bits).unwrap();
+            driver.set_led_blinking(5, flags).await.unwrap();
+            assert_eq!(driver.query_led_blinking(5).await.unwrap(), flags);
+        }
+    }
+
     test_command!(
         test_reset,
         "#254RESET\r",
@@ -1530,6 +4375,23 @@ mod tests {
         "#5CO-24\r",
         |mut driver: LSSDriver| async move { driver.set_origin_offset(5, -2.4).await.unwrap() }
     );
+    test_command!(
+        test_set_origin_offset_session,
+        "#5O-24\r",
+        |mut driver: LSSDriver| async move { driver.set_origin_offset_session(5, -2.4).await.unwrap() }
+    );
+    test_command!(
+        test_configure_origin_offset,
+        "#5CO-24\r",
+        |mut driver: LSSDriver| async move { driver.configure_origin_offset(5, -2.4).await.unwrap() }
+    );
+    test_query_float!(
+        test_query_configured_origin_offset,
+        "#5QO1\r",
+        "*5QO-13\r",
+        |mut driver: LSSDriver| async move { driver.query_configured_origin_offset(5).await.unwrap() },
+        -1.3
+    );
 
     test_query_float!(
         test_query_angular_range,
@@ -1544,6 +4406,23 @@ mod tests {
         "#5CAR1800\r",
         |mut driver: LSSDriver| async move { driver.set_angular_range(5, 180.0).await.unwrap() }
     );
+    test_command!(
+        test_set_angular_range_session,
+        "#5AR1800\r",
+        |mut driver: LSSDriver| async move { driver.set_angular_range_session(5, 180.0).await.unwrap() }
+    );
+    test_command!(
+        test_configure_angular_range,
+        "#5CAR1800\r",
+        |mut driver: LSSDriver| async move { driver.configure_angular_range(5, 180.0).await.unwrap() }
+    );
+    test_query_float!(
+        test_query_configured_angular_range,
+        "#5QAR1\r",
+        "*5QAR900\r",
+        |mut driver: LSSDriver| async move { driver.query_configured_angular_range(5).await.unwrap() },
+        90.0
+    );
 
     test_query!(
         test_query_pwm_position,
@@ -1,19 +1,290 @@
-use std::{str, time::Duration};
+use std::{io, ops, str, time::Duration};
 use thiserror::Error;
 
 /// Driver errors
 #[derive(Error, Debug)]
 pub enum LssDriverError {
-    #[error("Failed to parse data")]
+    #[error("Failed to parse data: {0}")]
     /// Error triggered if we fail parsing incoming packet into a data structure
     PacketParsingError(String),
     #[error("Operation timed out")]
     /// Error triggered for reading timeout
     TimeoutError,
-    #[error("Failed to open serial port")]
-    FailedOpeningSerialPort,
-    #[error("Failed to open serial port")]
-    SendingError,
+    #[error("Failed to open serial port {port}: {source}")]
+    /// Error triggered if the serial port can't be opened
+    FailedOpeningSerialPort {
+        /// Port that was opened. e.g. COM1
+        port: String,
+        source: tokio_serial::Error,
+    },
+    #[error("Failed to send command")]
+    /// Error triggered if writing to the transport fails
+    SendingError(#[source] io::Error),
+    #[error("Failed to read response")]
+    /// Error triggered if reading from the transport fails
+    ReceivingError(#[source] io::Error),
+    #[error("Failed to connect")]
+    /// Error triggered if a network transport fails to connect
+    FailedConnecting(#[source] io::Error),
+    #[error("Connection lost")]
+    /// Error triggered if a network transport loses connection
+    ///
+    /// The transport will try to reconnect on the next command
+    ConnectionLost(#[source] io::Error),
+    #[error("Bus task stopped")]
+    /// Error triggered if the background task of a [SharedLSSDriver](crate::SharedLSSDriver) is gone
+    BusStopped,
+    #[error("Expected response starting with {expected:?} but got {actual:?}")]
+    /// Error triggered if a response came from a different servo or for a different command
+    UnexpectedResponse {
+        /// Start of the expected response. e.g. `*5QD`
+        expected: String,
+        /// Response frame that was received
+        actual: String,
+    },
+    #[error("Baud rate {0} is not supported by LSS servos")]
+    /// Error triggered when configuring a baud rate that isn't in [SUPPORTED_BAUD_RATES](crate::SUPPORTED_BAUD_RATES)
+    UnsupportedBaudRate(u32),
+    #[error("Refusing to send {command:?} to all servos")]
+    /// Error triggered if a command that isn't safe to broadcast is sent to [BROADCAST_ID](crate::BROADCAST_ID)
+    BroadcastNotAllowed {
+        /// Command that was refused. e.g. `DEFAULT`
+        command: String,
+    },
+    #[error("Refusing to send {command:?} without confirmation: {reason}")]
+    /// Error triggered if a command that can make servos unreachable wasn't confirmed
+    NotConfirmed {
+        /// Command that was refused. e.g. `CRC1`
+        command: String,
+        /// Why the command needs confirmation
+        reason: &'static str,
+    },
+    #[error("Expected {setting} of servo {id} to be {expected} but it is {actual}")]
+    /// Error triggered if a servo doesn't report the value it should have after a command
+    VerificationFailed {
+        /// ID of the servo that was queried
+        id: u8,
+        /// Setting that was checked. e.g. `baud rate`
+        setting: &'static str,
+        expected: String,
+        actual: String,
+    },
+    #[error("Invalid {name}: {reason}")]
+    /// Error triggered if an argument is out of range before anything is sent
+    InvalidArgument {
+        /// Name of the argument. e.g. `speed`
+        name: &'static str,
+        reason: String,
+    },
+    #[error("Timed out waiting for servo {id}. Last status was {status:?}")]
+    /// Error triggered if a servo doesn't reach the expected state in time
+    WaitTimeout {
+        /// ID of the servo that was waited for
+        id: u8,
+        /// Last status the servo reported
+        status: MotorStatus,
+    },
+    #[error("Servo {id} reported {status:?}")]
+    /// Error triggered if a servo reports a fault while waiting for it. See [MotorStatus::is_fault]
+    ServoFault {
+        /// ID of the servo with the fault
+        id: u8,
+        /// Status the servo reported
+        status: MotorStatus,
+    },
+    #[error("No servo answered on {port} at any supported baud rate")]
+    /// Error triggered if baud rate detection didn't find any servo
+    BaudRateNotDetected {
+        /// Port that was probed. e.g. COM1
+        port: String,
+    },
+    #[error("Command {command:?} to servo {id} failed{}: {source}", describe_response(.response))]
+    /// Context for an error that happened while executing a command
+    ///
+    /// Use [LssDriverError::root_cause] to get the underlying error
+    CommandFailed {
+        /// ID of the servo the command was sent to
+        id: u8,
+        /// Command that was sent. e.g. `#5QD`
+        command: String,
+        /// Response frame if one was received
+        response: Option<String>,
+        source: Box<LssDriverError>,
+    },
+}
+
+fn describe_response(response: &Option<String>) -> String {
+    match response {
+        Some(response) => format!(" with response {:?}", response),
+        None => String::new(),
+    }
+}
+
+impl LssDriverError {
+    /// Wrap error with the command that caused it
+    ///
+    /// Errors that already have context are returned unchanged
+    pub(crate) fn command_failed(
+        id: u8,
+        command: &str,
+        response: Option<&str>,
+        source: LssDriverError,
+    ) -> LssDriverError {
+        match source {
+            LssDriverError::CommandFailed { .. } => source,
+            source => LssDriverError::CommandFailed {
+                id,
+                command: command.trim_end_matches('\r').to_owned(),
+                response: response.map(|response| response.trim_end_matches('\r').to_owned()),
+                source: Box::new(source),
+            },
+        }
+    }
+
+    /// Underlying error without the command context
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use lss_driver::{LSSDriver, LssDriverError};
+    /// async fn async_main(){
+    ///     let mut driver = LSSDriver::new("COM1").unwrap();
+    ///     match driver.query_position(5).await {
+    ///         Err(err) if matches!(err.root_cause(), LssDriverError::TimeoutError) => {
+    ///             println!("Servo 5 is not on the bus");
+    ///         }
+    ///         _ => (),
+    ///     }
+    /// }
+    /// ```
+    pub fn root_cause(&self) -> &LssDriverError {
+        match self {
+            LssDriverError::CommandFailed { source, .. } => source.root_cause(),
+            other => other,
+        }
+    }
+
+    /// ID of the servo the failed command was sent to if known
+    pub fn servo_id(&self) -> Option<u8> {
+        match self {
+            LssDriverError::CommandFailed { id, .. } => Some(*id),
+            _ => None,
+        }
+    }
+
+    /// Whether the servo didn't respond in time
+    pub fn is_timeout(&self) -> bool {
+        matches!(self.root_cause(), LssDriverError::TimeoutError)
+    }
+}
+
+/// How strictly responses are matched against the query that was sent
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum ResponseValidation {
+    /// Accept any response
+    Disabled,
+    /// Fail with [LssDriverError::UnexpectedResponse] if the response came from
+    /// a different servo or for a different command
+    #[default]
+    Strict,
+    /// Discard responses that don't match and keep reading until the right one arrives
+    /// or the read times out
+    ///
+    /// Useful for recovering from late responses to queries that already timed out
+    DiscardStale,
+}
+
+/// When failed commands are retried
+///
+/// Queries don't change the state of the servo so they are always safe to retry.
+/// Write commands are only retried if `retry_writes` is set.
+/// A write that timed out might still have reached the servo.
+///
+/// # Example
+///
+/// ```
+/// use lss_driver::RetryPolicy;
+/// use std::time::Duration;
+/// let policy = RetryPolicy::new(3, Duration::from_millis(5));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub struct RetryPolicy {
+    /// How many times a failed command is retried. 0 disables retries
+    pub retries: u32,
+    /// Delay before each retry
+    pub backoff: Duration,
+    /// Also retry write commands
+    pub retry_writes: bool,
+}
+
+impl RetryPolicy {
+    /// Retry queries `retries` times waiting `backoff` before each retry
+    ///
+    /// # Arguments
+    ///
+    /// * `retries` - How many times a failed query is retried
+    /// * `backoff` - Delay before each retry
+    pub fn new(retries: u32, backoff: Duration) -> RetryPolicy {
+        RetryPolicy {
+            retries,
+            backoff,
+            retry_writes: false,
+        }
+    }
+
+    /// Never retry
+    pub fn none() -> RetryPolicy {
+        RetryPolicy::default()
+    }
+
+    /// Set whether write commands are retried too
+    ///
+    /// # Arguments
+    ///
+    /// * `retry_writes` - Retry write commands
+    pub fn retry_writes(mut self, retry_writes: bool) -> RetryPolicy {
+        self.retry_writes = retry_writes;
+        self
+    }
+}
+
+/// How often and how long [wait_for_status](crate::LSSDriver::wait_for_status) and
+/// similar methods poll a servo
+///
+/// Defaults to polling every 20ms for up to 10s
+///
+/// # Example
+///
+/// ```
+/// use lss_driver::WaitOptions;
+/// use std::time::Duration;
+/// let options = WaitOptions::new(Duration::from_millis(50), Duration::from_secs(3));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct WaitOptions {
+    /// Delay between queries
+    pub poll_interval: Duration,
+    /// How long to wait in total
+    pub timeout: Duration,
+}
+
+impl WaitOptions {
+    /// # Arguments
+    ///
+    /// * `poll_interval` - Delay between queries
+    /// * `timeout` - How long to wait in total
+    pub fn new(poll_interval: Duration, timeout: Duration) -> WaitOptions {
+        WaitOptions {
+            poll_interval,
+            timeout,
+        }
+    }
+}
+
+impl Default for WaitOptions {
+    fn default() -> Self {
+        WaitOptions::new(Duration::from_millis(20), Duration::from_secs(10))
+    }
 }
 
 /// Colors for the LED on the servo
@@ -49,6 +320,31 @@ impl LedColor {
     }
 }
 
+/// Direction of rotation of a servo
+///
+/// Positions, targets and speeds are all mirrored for [Gyre::CounterClockwise] servos.
+/// Useful for mirrored joints that should be driven with the same angles
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Gyre {
+    /// Positive angles turn clockwise. Factory default
+    Clockwise = 1,
+    /// Positive angles turn counterclockwise
+    CounterClockwise = -1,
+}
+
+impl Gyre {
+    pub(crate) fn from_i32(number: i32) -> Result<Gyre, LssDriverError> {
+        match number {
+            1 => Ok(Gyre::Clockwise),
+            -1 => Ok(Gyre::CounterClockwise),
+            value => Err(LssDriverError::PacketParsingError(format!(
+                "Failed parsing Gyre from {}",
+                value
+            ))),
+        }
+    }
+}
+
 /// Status of the motor as responded to status query
 /// If status is safe mode you can use `query_safety_status` to see more details
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -68,6 +364,18 @@ pub enum MotorStatus {
 }
 
 impl MotorStatus {
+    /// Whether the servo can't continue moving without help.
+    /// `Stuck`, `Blocked`, `OutsideLimits` or `SafeMode`
+    pub fn is_fault(&self) -> bool {
+        matches!(
+            self,
+            MotorStatus::Stuck
+                | MotorStatus::Blocked
+                | MotorStatus::OutsideLimits
+                | MotorStatus::SafeMode
+        )
+    }
+
     pub(crate) fn from_i32(number: i32) -> Result<MotorStatus, LssDriverError> {
         match number {
             0 => Ok(MotorStatus::Unknown),
@@ -144,8 +452,142 @@ impl Model {
     }
 }
 
+/// How a servo is controlled
+///
+/// [Read more](https://www.robotshop.com/info/wiki/lynxmotion/view/lynxmotion-smart-servo/lss-communication-protocol/#HConfigureRCMode28CRC29)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RcMode {
+    /// Serial commands. Factory default
+    Serial = 0,
+    /// Hobby RC position control with PWM pulses
+    Position = 1,
+    /// Hobby RC continuous rotation with PWM pulses
+    Wheel = 2,
+}
+
+/// Conversion between PWM positions in µs and degrees
+///
+/// 1500µs is the center and 500µs to 2500µs cover the angular range.
+/// Degrees are relative to the origin offset the same as [move_to_position](crate::LSSDriver::move_to_position)
+///
+/// # Example
+///
+/// ```
+/// use lss_driver::PwmScale;
+/// let scale = PwmScale::new(180.0);
+/// assert_eq!(scale.to_pwm(45.0), 2000);
+/// assert_eq!(scale.to_degrees(1000), -45.0);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PwmScale {
+    /// Angular range in degrees. Factory default is 180
+    pub angular_range: f32,
+}
+
+impl PwmScale {
+    /// Center of the range in µs
+    pub const CENTER: i32 = 1500;
+    /// Shortest pulse in µs
+    pub const MIN: i32 = 500;
+    /// Longest pulse in µs
+    pub const MAX: i32 = 2500;
+
+    pub fn new(angular_range: f32) -> PwmScale {
+        PwmScale { angular_range }
+    }
+
+    /// Convert PWM position in µs to degrees
+    pub fn to_degrees(&self, pwm: i32) -> f32 {
+        let half_range = (Self::MAX - Self::CENTER) as f32;
+        (pwm - Self::CENTER) as f32 / half_range * self.angular_range / 2.0
+    }
+
+    /// Convert degrees to PWM position in µs
+    ///
+    /// Clamped to 500µs to 2500µs
+    pub fn to_pwm(&self, degrees: f32) -> i32 {
+        let half_range = (Self::MAX - Self::CENTER) as f32;
+        let pwm = Self::CENTER as f32 + degrees / (self.angular_range / 2.0) * half_range;
+        (pwm.round() as i32).clamp(Self::MIN, Self::MAX)
+    }
+}
+
+impl Default for PwmScale {
+    fn default() -> Self {
+        PwmScale::new(180.0)
+    }
+}
+
+/// How [move_group](crate::LSSDriver::move_group) makes servos arrive at the same time
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum GroupTiming {
+    /// Every servo uses the timed (T) modifier with this duration.
+    /// Only works with motion profile enabled
+    Timed(Duration),
+    /// Servo with the longest travel moves at this speed in °/s.
+    /// The others get a lower speed (SD modifier) in proportion to their travel
+    Speed(f32),
+}
+
+/// Power-on configuration of a servo saved in EEPROM
+///
+/// Written with [configure_boot_profile](crate::LSSDriver::configure_boot_profile)
+/// so every servo on a bus comes back the same way after a power cycle or brown-out
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BootProfile {
+    /// Position in degrees to move to on power-up. `None` stays limp
+    pub first_position: Option<f32>,
+    /// LED color
+    pub color: LedColor,
+    /// Whether motion profile is enabled
+    pub motion_profile: bool,
+}
+
+/// Snapshot of servo state read with [query_telemetry](crate::LSSDriver::query_telemetry)
+///
+/// Every field is queried separately so each one can fail on its own
+#[derive(Debug)]
+pub struct Telemetry {
+    /// Motor status
+    pub status: Result<MotorStatus, LssDriverError>,
+    /// Current position in degrees
+    pub position: Result<f32, LssDriverError>,
+    /// Target position in degrees
+    pub target_position: Result<f32, LssDriverError>,
+    /// Current position in µs
+    pub pwm_position: Result<i32, LssDriverError>,
+    /// Current speed in µs/s
+    pub pwm_speed: Result<i32, LssDriverError>,
+    /// Current speed in °/s
+    pub speed: Result<f32, LssDriverError>,
+    /// Current speed in RPM
+    pub speed_rpm: Result<f32, LssDriverError>,
+    /// Current in Amps
+    pub current: Result<f32, LssDriverError>,
+    /// Voltage in volts
+    pub voltage: Result<f32, LssDriverError>,
+    /// Temperature in celsius
+    pub temperature: Result<f32, LssDriverError>,
+}
+
+impl Telemetry {
+    /// Whether every field was read successfully
+    pub fn is_complete(&self) -> bool {
+        self.status.is_ok()
+            && self.position.is_ok()
+            && self.target_position.is_ok()
+            && self.pwm_position.is_ok()
+            && self.pwm_speed.is_ok()
+            && self.speed.is_ok()
+            && self.speed_rpm.is_ok()
+            && self.current.is_ok()
+            && self.voltage.is_ok()
+            && self.temperature.is_ok()
+    }
+}
+
 /// Which status should trigger LED blinking
-/// Can be combined in a list
+/// Can be combined into [LedBlinkingFlags]
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum LedBlinking {
     NoBlinking = 0,
@@ -158,6 +600,140 @@ pub enum LedBlinking {
     AlwaysBlink = 63,
 }
 
+impl LedBlinking {
+    /// Flags that have their own bit
+    const SINGLE: [LedBlinking; 6] = [
+        LedBlinking::Limp,
+        LedBlinking::Holding,
+        LedBlinking::Accelerating,
+        LedBlinking::Decelerating,
+        LedBlinking::Free,
+        LedBlinking::Travelling,
+    ];
+}
+
+/// Set of statuses that trigger LED blinking
+///
+/// Stored as the bitmask used by `CLB` and `QLB`
+///
+/// # Example
+///
+/// ```
+/// use lss_driver::{LedBlinking, LedBlinkingFlags};
+/// let flags = LedBlinkingFlags::from(LedBlinking::Limp) | LedBlinking::Holding;
+/// assert!(flags.contains(LedBlinking::Holding));
+/// assert_eq!(flags.bits(), 3);
+/// assert_eq!(flags.iter().collect::<Vec<_>>(), vec![LedBlinking::Limp, LedBlinking::Holding]);
+/// ```
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct LedBlinkingFlags(u8);
+
+impl LedBlinkingFlags {
+    /// No blinking
+    pub fn empty() -> LedBlinkingFlags {
+        LedBlinkingFlags(0)
+    }
+
+    /// Blink in every status
+    pub fn all() -> LedBlinkingFlags {
+        LedBlinkingFlags(LedBlinking::AlwaysBlink as u8)
+    }
+
+    /// Parse bitmask as responded to `QLB`
+    pub fn from_bits(bits: i32) -> Result<LedBlinkingFlags, LssDriverError> {
+        match u8::try_from(bits) {
+            Ok(bits) if bits <= LedBlinking::AlwaysBlink as u8 => Ok(LedBlinkingFlags(bits)),
+            _ => Err(LssDriverError::PacketParsingError(format!(
+                "Failed parsing LedBlinkingFlags from {}",
+                bits
+            ))),
+        }
+    }
+
+    /// Bitmask as sent with `CLB`
+    pub fn bits(&self) -> i32 {
+        self.0 as i32
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Whether all bits of `flag` are set
+    ///
+    /// [LedBlinking::NoBlinking] is only contained in an empty set
+    pub fn contains(&self, flag: LedBlinking) -> bool {
+        match flag {
+            LedBlinking::NoBlinking => self.is_empty(),
+            flag => self.0 & flag as u8 == flag as u8,
+        }
+    }
+
+    pub fn insert(&mut self, flag: LedBlinking) {
+        self.0 |= flag as u8;
+    }
+
+    pub fn remove(&mut self, flag: LedBlinking) {
+        self.0 &= !(flag as u8);
+    }
+
+    pub fn union(self, other: LedBlinkingFlags) -> LedBlinkingFlags {
+        LedBlinkingFlags(self.0 | other.0)
+    }
+
+    /// Flags in the set, excluding [LedBlinking::NoBlinking] and [LedBlinking::AlwaysBlink]
+    pub fn iter(&self) -> impl Iterator<Item = LedBlinking> {
+        let flags = *self;
+        LedBlinking::SINGLE
+            .into_iter()
+            .filter(move |flag| flags.contains(*flag))
+    }
+}
+
+impl From<LedBlinking> for LedBlinkingFlags {
+    fn from(flag: LedBlinking) -> Self {
+        LedBlinkingFlags(flag as u8)
+    }
+}
+
+impl From<Vec<LedBlinking>> for LedBlinkingFlags {
+    fn from(flags: Vec<LedBlinking>) -> Self {
+        flags.into_iter().collect()
+    }
+}
+
+impl From<&[LedBlinking]> for LedBlinkingFlags {
+    fn from(flags: &[LedBlinking]) -> Self {
+        flags.iter().copied().collect()
+    }
+}
+
+impl FromIterator<LedBlinking> for LedBlinkingFlags {
+    fn from_iter<T: IntoIterator<Item = LedBlinking>>(iter: T) -> Self {
+        let mut flags = LedBlinkingFlags::empty();
+        for flag in iter {
+            flags.insert(flag);
+        }
+        flags
+    }
+}
+
+impl ops::BitOr for LedBlinkingFlags {
+    type Output = LedBlinkingFlags;
+
+    fn bitor(self, other: LedBlinkingFlags) -> LedBlinkingFlags {
+        self.union(other)
+    }
+}
+
+impl ops::BitOr<LedBlinking> for LedBlinkingFlags {
+    type Output = LedBlinkingFlags;
+
+    fn bitor(self, flag: LedBlinking) -> LedBlinkingFlags {
+        self.union(flag.into())
+    }
+}
+
 /// Modifiers used for some commands
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CommandModifier {
@@ -249,6 +825,56 @@ mod tests {
         }
     }
 
+    #[test]
+    fn gyre_parses() {
+        assert_eq!(Gyre::from_i32(1).unwrap(), Gyre::Clockwise);
+        assert_eq!(Gyre::from_i32(-1).unwrap(), Gyre::CounterClockwise);
+        assert!(Gyre::from_i32(0).is_err());
+    }
+
+    #[test]
+    fn led_blinking_flags_round_trip() {
+        for bits in 0..=LedBlinking::AlwaysBlink as i32 {
+            let flags = LedBlinkingFlags::from_bits(bits).unwrap();
+            assert_eq!(flags.bits(), bits);
+            let collected: LedBlinkingFlags = flags.iter().collect();
+            assert_eq!(collected, flags);
+            assert_eq!(flags.contains(LedBlinking::NoBlinking), bits == 0);
+            assert_eq!(flags.contains(LedBlinking::AlwaysBlink), bits == 63);
+        }
+        assert!(LedBlinkingFlags::from_bits(64).is_err());
+        assert!(LedBlinkingFlags::from_bits(-1).is_err());
+    }
+
+    #[test]
+    fn led_blinking_duplicates_are_ignored() {
+        let flags = LedBlinkingFlags::from(vec![
+            LedBlinking::Holding,
+            LedBlinking::Holding,
+            LedBlinking::AlwaysBlink,
+        ]);
+        assert_eq!(flags, LedBlinkingFlags::all());
+        let mut flags = LedBlinkingFlags::from(LedBlinking::Free) | LedBlinking::Free;
+        assert_eq!(flags.bits(), 16);
+        flags.remove(LedBlinking::Free);
+        assert!(flags.is_empty());
+    }
+
+    #[test]
+    fn pwm_scale_converts() {
+        let scale = PwmScale::new(90.0);
+        assert_eq!(scale.to_pwm(0.0), 1500);
+        assert_eq!(scale.to_pwm(45.0), 2500);
+        assert_eq!(scale.to_pwm(-22.5), 1000);
+        assert_eq!(scale.to_pwm(100.0), 2500);
+        assert_eq!(scale.to_pwm(-100.0), 500);
+        assert_eq!(scale.to_degrees(2000), 22.5);
+        assert_eq!(scale.to_degrees(500), -45.0);
+        for pwm in PwmScale::MIN..=PwmScale::MAX {
+            assert_eq!(scale.to_pwm(scale.to_degrees(pwm)), pwm);
+        }
+    }
+
     #[test]
     fn motor_status_parse_fails() {
         let status = MotorStatus::from_i32(42);
@@ -0,0 +1,432 @@
+use crate::message_types::{LssDriverError, MotorStatus};
+use crate::LSSDriver;
+use futures::stream::{self, BoxStream, Stream};
+use std::ops::DerefMut;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::time::{Duration, Instant};
+
+type DriverResult<T> = Result<T, LssDriverError>;
+
+/// Value that [TelemetryPoller] can poll
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TelemetryField {
+    /// [LSSDriver::query_status]
+    Status,
+    /// [LSSDriver::query_position]
+    Position,
+    /// [LSSDriver::query_target_position]
+    TargetPosition,
+    /// [LSSDriver::query_pwm_position]
+    PwmPosition,
+    /// [LSSDriver::query_rotation_speed]
+    Speed,
+    /// [LSSDriver::query_current]
+    Current,
+    /// [LSSDriver::query_voltage]
+    Voltage,
+    /// [LSSDriver::query_temperature]
+    Temperature,
+}
+
+/// Polled value in the same units as the matching query
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TelemetryValue {
+    Status(MotorStatus),
+    /// Degrees
+    Position(f32),
+    /// Degrees
+    TargetPosition(f32),
+    /// µs
+    PwmPosition(i32),
+    /// °/s
+    Speed(f32),
+    /// Amps
+    Current(f32),
+    /// Volts
+    Voltage(f32),
+    /// Celsius
+    Temperature(f32),
+}
+
+impl TelemetryValue {
+    /// Field this value belongs to
+    pub fn field(&self) -> TelemetryField {
+        match self {
+            TelemetryValue::Status(_) => TelemetryField::Status,
+            TelemetryValue::Position(_) => TelemetryField::Position,
+            TelemetryValue::TargetPosition(_) => TelemetryField::TargetPosition,
+            TelemetryValue::PwmPosition(_) => TelemetryField::PwmPosition,
+            TelemetryValue::Speed(_) => TelemetryField::Speed,
+            TelemetryValue::Current(_) => TelemetryField::Current,
+            TelemetryValue::Voltage(_) => TelemetryField::Voltage,
+            TelemetryValue::Temperature(_) => TelemetryField::Temperature,
+        }
+    }
+}
+
+/// Single value read by [TelemetryPoller]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TelemetrySample {
+    /// ID of the servo
+    pub id: u8,
+    /// When the reply was received
+    pub timestamp: Instant,
+    pub value: TelemetryValue,
+}
+
+/// Counters of a [TelemetryStream]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PollerStats {
+    /// Samples returned by the stream
+    pub samples: u64,
+    /// Queries that failed. e.g. timeout or invalid reply
+    pub dropped: u64,
+    /// Queries sent a whole period or more after they were due.
+    /// Happens when the bus is busy or the stream isn't read fast enough
+    pub late: u64,
+}
+
+/// Polls registered servo values at their own rates
+///
+/// Queries are only sent while the [TelemetryStream] is read.
+/// The query that has been due the longest always goes first so every field gets its turn
+/// even when the bus can't keep up with all rates.
+/// Late queries are rescheduled from the time they were sent instead of catching up.
+///
+/// Use a [SharedLSSDriver](crate::SharedLSSDriver) to keep moving servos from other tasks
+/// while polling.
+///
+/// # Example
+///
+/// ```no_run
+/// use futures::StreamExt;
+/// use lss_driver::{LSSDriver, SharedLSSDriver, TelemetryField, TelemetryPoller};
+/// use std::time::Duration;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let driver = SharedLSSDriver::new(LSSDriver::new("COM1").unwrap());
+///     let mut samples = TelemetryPoller::new(driver.clone())
+///         .poll(5, TelemetryField::Position, Duration::from_millis(20))
+///         .poll(5, TelemetryField::Temperature, Duration::from_secs(1))
+///         .start();
+///     while let Some(sample) = samples.next().await {
+///         println!("{:?}", sample);
+///     }
+/// }
+/// ```
+pub struct TelemetryPoller<D> {
+    driver: D,
+    entries: Vec<Entry>,
+}
+
+struct Entry {
+    id: u8,
+    field: TelemetryField,
+    period: Duration,
+    due: Instant,
+}
+
+impl<'a, D: DerefMut<Target = LSSDriver> + Send + 'a> TelemetryPoller<D> {
+    /// Create poller without any registered values
+    ///
+    /// # Arguments
+    ///
+    /// * `driver` - Driver to use. e.g. `&mut LSSDriver` or [SharedLSSDriver](crate::SharedLSSDriver)
+    pub fn new(driver: D) -> TelemetryPoller<D> {
+        TelemetryPoller {
+            driver,
+            entries: vec![],
+        }
+    }
+
+    /// Poll `field` of servo `id` every `period`
+    ///
+    /// Registering the same field twice replaces the period
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of the servo
+    /// * `field` - Value to poll
+    /// * `period` - Time between samples. Zero polls as fast as the bus allows
+    ///   and is never counted as late
+    pub fn poll(mut self, id: u8, field: TelemetryField, period: Duration) -> TelemetryPoller<D> {
+        self.entries
+            .retain(|entry| entry.id != id || entry.field != field);
+        self.entries.push(Entry {
+            id,
+            field,
+            period,
+            due: Instant::now(),
+        });
+        self
+    }
+
+    /// Start polling
+    ///
+    /// The stream ends if there is nothing to poll or the bus task of a
+    /// [SharedLSSDriver](crate::SharedLSSDriver) is gone.
+    /// Other errors, including [LssDriverError::ConnectionLost], are counted as dropped
+    pub fn start(self) -> TelemetryStream<'a> {
+        let stats = Arc::new(Mutex::new(PollerStats::default()));
+        let state = PollerState {
+            driver: self.driver,
+            entries: self.entries,
+            stats: stats.clone(),
+        };
+        TelemetryStream {
+            samples: Box::pin(stream::unfold(state, |mut state| async move {
+                let sample = state.next_sample().await?;
+                Some((sample, state))
+            })),
+            stats,
+        }
+    }
+}
+
+struct PollerState<D> {
+    driver: D,
+    entries: Vec<Entry>,
+    stats: Arc<Mutex<PollerStats>>,
+}
+
+impl<D: DerefMut<Target = LSSDriver>> PollerState<D> {
+    /// Poll values until one succeeds. `None` if polling should stop
+    async fn next_sample(&mut self) -> Option<TelemetrySample> {
+        loop {
+            // earliest due first. Order of registration breaks ties
+            let entry = self.entries.iter_mut().min_by_key(|entry| entry.due)?;
+            tokio::time::sleep_until(entry.due).await;
+            let sent_at = Instant::now();
+            let late = !entry.period.is_zero() && sent_at.duration_since(entry.due) >= entry.period;
+            entry.due = if late {
+                sent_at + entry.period
+            } else {
+                entry.due + entry.period
+            };
+            let (id, field) = (entry.id, entry.field);
+            let result = query(&mut self.driver, id, field).await;
+            let mut stats = self.stats.lock().unwrap();
+            if late {
+                stats.late += 1;
+            }
+            match result {
+                Ok(value) => {
+                    stats.samples += 1;
+                    return Some(TelemetrySample {
+                        id,
+                        timestamp: Instant::now(),
+                        value,
+                    });
+                }
+                // transports reconnect after a lost connection so only a stopped bus task ends polling
+                Err(error) if matches!(error.root_cause(), LssDriverError::BusStopped) => {
+                    return None
+                }
+                Err(_) => stats.dropped += 1,
+            }
+        }
+    }
+}
+
+async fn query(
+    driver: &mut LSSDriver,
+    id: u8,
+    field: TelemetryField,
+) -> DriverResult<TelemetryValue> {
+    let value = match field {
+        TelemetryField::Status => TelemetryValue::Status(driver.query_status(id).await?),
+        TelemetryField::Position => TelemetryValue::Position(driver.query_position(id).await?),
+        TelemetryField::TargetPosition => {
+            TelemetryValue::TargetPosition(driver.query_target_position(id).await?)
+        }
+        TelemetryField::PwmPosition => {
+            TelemetryValue::PwmPosition(driver.query_pwm_position(id).await?)
+        }
+        TelemetryField::Speed => TelemetryValue::Speed(driver.query_rotation_speed(id).await?),
+        TelemetryField::Current => TelemetryValue::Current(driver.query_current(id).await?),
+        TelemetryField::Voltage => TelemetryValue::Voltage(driver.query_voltage(id).await?),
+        TelemetryField::Temperature => {
+            TelemetryValue::Temperature(driver.query_temperature(id).await?)
+        }
+    };
+    Ok(value)
+}
+
+/// Stream of [TelemetrySample]s created by [TelemetryPoller::start]
+pub struct TelemetryStream<'a> {
+    samples: BoxStream<'a, TelemetrySample>,
+    stats: Arc<Mutex<PollerStats>>,
+}
+
+impl TelemetryStream<'_> {
+    /// Counters since polling started
+    pub fn stats(&self) -> PollerStats {
+        *self.stats.lock().unwrap()
+    }
+}
+
+impl Stream for TelemetryStream<'_> {
+    type Item = TelemetrySample;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.samples.as_mut().poll_next(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulator::{Faults, VirtualBus, VirtualServo};
+    use crate::transport::{FramedDriver, LssCommand, LssResponse};
+    use crate::{Model, SharedLSSDriver};
+    use approx::assert_relative_eq;
+    use async_trait::async_trait;
+    use futures::StreamExt;
+    use std::io;
+
+    #[tokio::test(start_paused = true)]
+    async fn fields_are_polled_at_their_rates() {
+        let bus = VirtualBus::new(vec![
+            VirtualServo::new(1, Model::ST1).with_position(10.0),
+            VirtualServo::new(2, Model::ST1),
+        ]);
+        bus.servo(2, |servo| servo.set_temperature(40.0));
+        let mut driver = LSSDriver::with_driver(Box::new(bus));
+        let started = Instant::now();
+        let samples: Vec<_> = TelemetryPoller::new(&mut driver)
+            .poll(1, TelemetryField::Position, Duration::from_millis(100))
+            .poll(2, TelemetryField::Temperature, Duration::from_millis(250))
+            .start()
+            .take_while(|sample| {
+                futures::future::ready(sample.timestamp < started + Duration::from_secs(1))
+            })
+            .collect()
+            .await;
+        let positions: Vec<_> = samples.iter().filter(|sample| sample.id == 1).collect();
+        let temperatures: Vec<_> = samples.iter().filter(|sample| sample.id == 2).collect();
+        assert_eq!(positions.len(), 10);
+        assert_eq!(temperatures.len(), 4);
+        assert_eq!(positions[0].value, TelemetryValue::Position(10.0));
+        assert_eq!(temperatures[0].value, TelemetryValue::Temperature(40.0));
+        assert_eq!(
+            positions[3].timestamp - positions[2].timestamp,
+            Duration::from_millis(100)
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn dropped_and_late_samples_are_counted() {
+        let bus = VirtualBus::new(vec![VirtualServo::new(1, Model::ST1)]).with_faults(Faults {
+            drop_every: Some(2),
+            ..Faults::default()
+        });
+        let mut driver = LSSDriver::with_driver(Box::new(bus));
+        let mut stream = TelemetryPoller::new(&mut driver)
+            .poll(1, TelemetryField::Status, Duration::from_millis(50))
+            .start();
+        for _ in 0..3 {
+            stream.next().await.unwrap();
+        }
+        assert_eq!(
+            stream.stats(),
+            PollerStats {
+                samples: 3,
+                dropped: 2,
+                late: 0
+            }
+        );
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        stream.next().await.unwrap();
+        assert_eq!(stream.stats().late, 1);
+    }
+
+    /// Loses the connection once and then forwards to the bus
+    struct FlakyConnection {
+        bus: VirtualBus,
+        lost: bool,
+    }
+
+    #[async_trait]
+    impl FramedDriver for FlakyConnection {
+        async fn send(&mut self, command: LssCommand) -> DriverResult<()> {
+            if !self.lost {
+                self.lost = true;
+                return Err(LssDriverError::ConnectionLost(io::Error::new(
+                    io::ErrorKind::ConnectionReset,
+                    "Reset by bridge",
+                )));
+            }
+            self.bus.send(command).await
+        }
+
+        async fn receive(&mut self) -> DriverResult<LssResponse> {
+            self.bus.receive().await
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn lost_connection_is_dropped_and_polling_goes_on() {
+        let bus = VirtualBus::new(vec![VirtualServo::new(1, Model::ST1)]);
+        let driver = SharedLSSDriver::new(LSSDriver::with_driver(Box::new(FlakyConnection {
+            bus,
+            lost: false,
+        })));
+        let mut stream = TelemetryPoller::new(driver)
+            .poll(1, TelemetryField::Status, Duration::from_millis(50))
+            .start();
+        stream.next().await.unwrap();
+        assert_eq!(stream.stats().dropped, 1);
+        assert_eq!(stream.stats().samples, 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn zero_period_is_never_late() {
+        let bus = VirtualBus::new(vec![VirtualServo::new(1, Model::ST1)]);
+        let mut driver = LSSDriver::with_driver(Box::new(bus));
+        let mut stream = TelemetryPoller::new(&mut driver)
+            .poll(1, TelemetryField::Status, Duration::ZERO)
+            .start();
+        for _ in 0..3 {
+            stream.next().await.unwrap();
+        }
+        assert_eq!(stream.stats().late, 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn nothing_to_poll_ends_stream() {
+        let bus = VirtualBus::new(vec![]);
+        let mut driver = LSSDriver::with_driver(Box::new(bus));
+        let mut stream = TelemetryPoller::new(&mut driver).start();
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn polling_coexists_with_motion() {
+        let bus = VirtualBus::new(vec![VirtualServo::new(1, Model::ST1)]);
+        let driver = SharedLSSDriver::new(LSSDriver::with_driver(Box::new(bus)));
+        let mut stream = TelemetryPoller::new(driver.clone())
+            .poll(1, TelemetryField::TargetPosition, Duration::from_millis(5))
+            .start();
+        let mut motion = driver.clone();
+        let mover = tokio::spawn(async move {
+            for position in [10.0, 20.0, 30.0] {
+                motion.move_to_position(1, position).await.unwrap();
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        });
+        let mut last = 0.0;
+        while let Some(sample) = stream.next().await {
+            if let TelemetryValue::TargetPosition(target) = sample.value {
+                last = target;
+            }
+            if mover.is_finished() && last == 30.0 {
+                break;
+            }
+        }
+        assert_relative_eq!(last, 30.0);
+        assert_eq!(stream.stats().dropped, 0);
+    }
+}
@@ -0,0 +1,990 @@
+//! Typed model of the LSS wire protocol
+//!
+//! [CommandFrame] and [ReplyFrame] serialize to and parse from the exact frames sent on the bus.
+//! Values are kept in wire units. e.g. positions are in tenths of degrees.
+//!
+//! Protocol is described on the [wiki](https://www.robotshop.com/info/wiki/lynxmotion/view/lynxmotion-smart-servo/lss-communication-protocol/)
+//!
+//! # Example
+//!
+//! ```
+//! use lss_driver::protocol::{Command, CommandFrame, Modifier, Query, Reply, ReplyFrame};
+//!
+//! let frame = CommandFrame::new(5, Command::Move(1800)).with_modifier(Modifier::Timed(1000));
+//! assert_eq!(frame.to_string(), "#5D1800T1000\r");
+//! assert_eq!("#5D1800T1000\r".parse::<CommandFrame>().unwrap(), frame);
+//!
+//! let reply: ReplyFrame = "*5QD1800\r".parse().unwrap();
+//! assert_eq!(reply, ReplyFrame::new(5, Reply::Value(Query::Position, 1800)));
+//! ```
+
+use crate::message_types::{CommandModifier, LssDriverError};
+use std::fmt;
+use std::str::FromStr;
+
+type DriverResult<T> = Result<T, LssDriverError>;
+
+/// Value that can be changed for the session, saved to EEPROM or queried
+///
+/// The session command is the mnemonic (e.g. `O`),
+/// the configuration command has a `C` prefix (e.g. `CO`)
+/// and the query has a `Q` prefix (e.g. `QO`).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Parameter {
+    /// `O` in tenths of degrees
+    OriginOffset,
+    /// `AR` in tenths of degrees
+    AngularRange,
+    /// `AS`
+    AngularStiffness,
+    /// `AH`
+    AngularHoldingStiffness,
+    /// `AA` in tenths of °/s²
+    AngularAcceleration,
+    /// `AD` in tenths of °/s²
+    AngularDeceleration,
+    /// `EM` 0 or 1
+    MotionProfile,
+    /// `FPC`
+    FilterPositionCount,
+    /// `MMD`
+    MaximumMotorDuty,
+    /// `SD` in tenths of °/s
+    MaximumSpeed,
+    /// `SR` in RPM
+    MaximumSpeedRpm,
+    /// `LED` color
+    LedColor,
+    /// `G` 1 for clockwise, -1 for counterclockwise
+    Gyre,
+}
+
+impl Parameter {
+    /// All parameters
+    pub const ALL: [Parameter; 13] = [
+        Parameter::OriginOffset,
+        Parameter::AngularRange,
+        Parameter::AngularStiffness,
+        Parameter::AngularHoldingStiffness,
+        Parameter::AngularAcceleration,
+        Parameter::AngularDeceleration,
+        Parameter::MotionProfile,
+        Parameter::FilterPositionCount,
+        Parameter::MaximumMotorDuty,
+        Parameter::MaximumSpeed,
+        Parameter::MaximumSpeedRpm,
+        Parameter::LedColor,
+        Parameter::Gyre,
+    ];
+
+    /// Session command. e.g. `O`
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Parameter::OriginOffset => "O",
+            Parameter::AngularRange => "AR",
+            Parameter::AngularStiffness => "AS",
+            Parameter::AngularHoldingStiffness => "AH",
+            Parameter::AngularAcceleration => "AA",
+            Parameter::AngularDeceleration => "AD",
+            Parameter::MotionProfile => "EM",
+            Parameter::FilterPositionCount => "FPC",
+            Parameter::MaximumMotorDuty => "MMD",
+            Parameter::MaximumSpeed => "SD",
+            Parameter::MaximumSpeedRpm => "SR",
+            Parameter::LedColor => "LED",
+            Parameter::Gyre => "G",
+        }
+    }
+
+    /// Configuration command. e.g. `CO`
+    pub fn config_mnemonic(&self) -> &'static str {
+        match self {
+            Parameter::OriginOffset => "CO",
+            Parameter::AngularRange => "CAR",
+            Parameter::AngularStiffness => "CAS",
+            Parameter::AngularHoldingStiffness => "CAH",
+            Parameter::AngularAcceleration => "CAA",
+            Parameter::AngularDeceleration => "CAD",
+            Parameter::MotionProfile => "CEM",
+            Parameter::FilterPositionCount => "CFPC",
+            Parameter::MaximumMotorDuty => "CMMD",
+            Parameter::MaximumSpeed => "CSD",
+            Parameter::MaximumSpeedRpm => "CSR",
+            Parameter::LedColor => "CLED",
+            Parameter::Gyre => "CG",
+        }
+    }
+
+    /// Query command. e.g. `QO`
+    pub fn query_mnemonic(&self) -> &'static str {
+        match self {
+            Parameter::OriginOffset => "QO",
+            Parameter::AngularRange => "QAR",
+            Parameter::AngularStiffness => "QAS",
+            Parameter::AngularHoldingStiffness => "QAH",
+            Parameter::AngularAcceleration => "QAA",
+            Parameter::AngularDeceleration => "QAD",
+            Parameter::MotionProfile => "QEM",
+            Parameter::FilterPositionCount => "QFPC",
+            Parameter::MaximumMotorDuty => "QMMD",
+            Parameter::MaximumSpeed => "QSD",
+            Parameter::MaximumSpeedRpm => "QSR",
+            Parameter::LedColor => "QLED",
+            Parameter::Gyre => "QG",
+        }
+    }
+
+    /// Query for the value saved in EEPROM. e.g. `QO1`
+    pub fn configured_query_mnemonic(&self) -> &'static str {
+        match self {
+            Parameter::OriginOffset => "QO1",
+            Parameter::AngularRange => "QAR1",
+            Parameter::AngularStiffness => "QAS1",
+            Parameter::AngularHoldingStiffness => "QAH1",
+            Parameter::AngularAcceleration => "QAA1",
+            Parameter::AngularDeceleration => "QAD1",
+            Parameter::MotionProfile => "QEM1",
+            Parameter::FilterPositionCount => "QFPC1",
+            Parameter::MaximumMotorDuty => "QMMD1",
+            Parameter::MaximumSpeed => "QSD1",
+            Parameter::MaximumSpeedRpm => "QSR1",
+            Parameter::LedColor => "QLED1",
+            Parameter::Gyre => "QG1",
+        }
+    }
+}
+
+/// Value that can only be saved to EEPROM and queried
+///
+/// There is no session command.
+/// The configuration command has a `C` prefix (e.g. `CID`) and the query a `Q` prefix (e.g. `QID`).
+/// RC mode can't be queried so it is configured with [Command::ConfigureRcMode] instead.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Setting {
+    /// `ID`
+    Id,
+    /// `B` baud rate
+    BaudRate,
+    /// `FD` first position in tenths of degrees
+    FirstPosition,
+    /// `LB` LED blinking flags
+    LedBlinking,
+}
+
+impl Setting {
+    /// All settings
+    pub const ALL: [Setting; 4] = [
+        Setting::Id,
+        Setting::BaudRate,
+        Setting::FirstPosition,
+        Setting::LedBlinking,
+    ];
+
+    /// Configuration command. e.g. `CID`
+    pub fn config_mnemonic(&self) -> &'static str {
+        match self {
+            Setting::Id => "CID",
+            Setting::BaudRate => "CB",
+            Setting::FirstPosition => "CFD",
+            Setting::LedBlinking => "CLB",
+        }
+    }
+
+    /// Query command. e.g. `QID`
+    pub fn query_mnemonic(&self) -> &'static str {
+        match self {
+            Setting::Id => "QID",
+            Setting::BaudRate => "QB",
+            Setting::FirstPosition => "QFD",
+            Setting::LedBlinking => "QLB",
+        }
+    }
+}
+
+/// Query command
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Query {
+    /// `Q` motor status
+    Status,
+    /// `Q1` safe mode status. Answered with the `Q` echo
+    SafetyStatus,
+    /// `QD` position in tenths of degrees
+    Position,
+    /// `QDT` target position in tenths of degrees
+    TargetPosition,
+    /// `QP` position in µs
+    PulsePosition,
+    /// `QWD` wheel speed in °/s
+    WheelSpeed,
+    /// `QWR` wheel speed in RPM
+    WheelSpeedRpm,
+    /// `QS` current speed in µs/s
+    Speed,
+    /// `QSD2` current speed in tenths of °/s. Answered with the `QSD` echo
+    CurrentSpeed,
+    /// `QSR2` current speed in RPM. Answered with the `QSR` echo
+    CurrentSpeedRpm,
+    /// `QV` voltage in mV
+    Voltage,
+    /// `QT` temperature in tenths of °C
+    Temperature,
+    /// `QC` current in mA
+    Current,
+    /// `QMS` model string
+    Model,
+    /// `QN` serial number
+    SerialNumber,
+    /// `QF` firmware version
+    Firmware,
+    /// Value of a [Parameter] used in this session. e.g. `QO`
+    Parameter(Parameter),
+    /// Value of a [Parameter] saved in EEPROM. e.g. `QO1`. Answered with the `QO` echo
+    ConfiguredParameter(Parameter),
+    /// Value of a [Setting]. e.g. `QID`
+    Setting(Setting),
+}
+
+impl Query {
+    /// All queries
+    pub fn all() -> impl Iterator<Item = Query> {
+        [
+            Query::Status,
+            Query::SafetyStatus,
+            Query::Position,
+            Query::TargetPosition,
+            Query::PulsePosition,
+            Query::WheelSpeed,
+            Query::WheelSpeedRpm,
+            Query::Speed,
+            Query::CurrentSpeed,
+            Query::CurrentSpeedRpm,
+            Query::Voltage,
+            Query::Temperature,
+            Query::Current,
+            Query::Model,
+            Query::SerialNumber,
+            Query::Firmware,
+        ]
+        .into_iter()
+        .chain(Parameter::ALL.into_iter().map(Query::Parameter))
+        .chain(Parameter::ALL.into_iter().map(Query::ConfiguredParameter))
+        .chain(Setting::ALL.into_iter().map(Query::Setting))
+    }
+
+    /// Command as sent. e.g. `QD`
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Query::Status => "Q",
+            Query::SafetyStatus => "Q1",
+            Query::Position => "QD",
+            Query::TargetPosition => "QDT",
+            Query::PulsePosition => "QP",
+            Query::WheelSpeed => "QWD",
+            Query::WheelSpeedRpm => "QWR",
+            Query::Speed => "QS",
+            Query::CurrentSpeed => "QSD2",
+            Query::CurrentSpeedRpm => "QSR2",
+            Query::Voltage => "QV",
+            Query::Temperature => "QT",
+            Query::Current => "QC",
+            Query::Model => "QMS",
+            Query::SerialNumber => "QN",
+            Query::Firmware => "QF",
+            Query::Parameter(parameter) => parameter.query_mnemonic(),
+            Query::ConfiguredParameter(parameter) => parameter.configured_query_mnemonic(),
+            Query::Setting(setting) => setting.query_mnemonic(),
+        }
+    }
+
+    /// Command echoed in the reply. Same as [Query::mnemonic] without the trailing number
+    /// of `Q1`, `QSD2`, `QSR2` and `QO1`
+    pub fn echo(&self) -> &'static str {
+        match self {
+            Query::SafetyStatus => "Q",
+            Query::CurrentSpeed => Parameter::MaximumSpeed.query_mnemonic(),
+            Query::CurrentSpeedRpm => Parameter::MaximumSpeedRpm.query_mnemonic(),
+            Query::ConfiguredParameter(parameter) => parameter.query_mnemonic(),
+            other => other.mnemonic(),
+        }
+    }
+
+    /// Whether replies to this query are parsed as a different query with the same echo
+    fn shares_echo(&self) -> bool {
+        matches!(
+            self,
+            Query::SafetyStatus
+                | Query::CurrentSpeed
+                | Query::CurrentSpeedRpm
+                | Query::ConfiguredParameter(_)
+        )
+    }
+
+    /// Whether the reply is text instead of a number
+    pub fn is_text(&self) -> bool {
+        matches!(self, Query::Model | Query::SerialNumber | Query::Firmware)
+    }
+
+    /// Whether the reply can come without a value. e.g. `QFD` when no first position is set
+    pub fn can_be_empty(&self) -> bool {
+        matches!(self, Query::Setting(Setting::FirstPosition))
+    }
+}
+
+/// Command without the servo ID
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    /// `L`
+    Limp,
+    /// `H`
+    HaltHold,
+    /// `RESET` reverts session values to values in EEPROM
+    Reset,
+    /// `DEFAULT` restores factory defaults after `CONFIRM`
+    Default,
+    /// `UPDATE` enters firmware update mode after `CONFIRM`
+    Update,
+    /// `CONFIRM`
+    Confirm,
+    /// `D` absolute position in tenths of degrees
+    Move(i32),
+    /// `MD` relative move in tenths of degrees
+    MoveRelative(i32),
+    /// `P` absolute position in µs
+    MovePulse(i32),
+    /// `WD` wheel mode in °/s
+    WheelSpeed(i32),
+    /// `WR` wheel mode in RPM
+    WheelSpeedRpm(i32),
+    /// Change parameter for the session. e.g. `O`
+    Set(Parameter, i32),
+    /// Change parameter and save it to EEPROM. e.g. `CO`
+    Configure(Parameter, i32),
+    /// Save setting to EEPROM. e.g. `CID`
+    ConfigureSetting(Setting, i32),
+    /// `CRC` RC mode. Takes effect after reset
+    ConfigureRcMode(i32),
+    /// `CFD` without a value. Servo stays limp on power-up
+    ClearFirstPosition,
+    /// Query. e.g. `QD`
+    Query(Query),
+}
+
+/// Modifier appended to motion commands
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Modifier {
+    /// `T` duration of the move in ms
+    Timed(i32),
+    /// `S` speed in µs/s
+    Speed(i32),
+    /// `SD` speed in tenths of °/s
+    SpeedDegrees(i32),
+    /// `CH` current in mA that halts and holds
+    CurrentHold(i32),
+    /// `CL` current in mA that limps
+    CurrentLimp(i32),
+    /// Modifier not modelled by this library
+    Other(String, i32),
+}
+
+impl Modifier {
+    fn from_mnemonic(mnemonic: &str, value: i32) -> Modifier {
+        match mnemonic {
+            "T" => Modifier::Timed(value),
+            "S" => Modifier::Speed(value),
+            "SD" => Modifier::SpeedDegrees(value),
+            "CH" => Modifier::CurrentHold(value),
+            "CL" => Modifier::CurrentLimp(value),
+            other => Modifier::Other(other.to_owned(), value),
+        }
+    }
+
+    /// Convert modifier used by [LSSDriver](crate::LSSDriver)
+    ///
+    /// `None` for [CommandModifier::None].
+    /// Values that don't fit into `i32` are saturated to `i32::MAX`
+    pub fn from_command_modifier(modifier: &CommandModifier) -> Option<Modifier> {
+        let saturate = |value: u128| i32::try_from(value).unwrap_or(i32::MAX);
+        match *modifier {
+            CommandModifier::Speed(speed) => Some(Modifier::Speed(saturate(speed.into()))),
+            CommandModifier::SpeedDegrees(speed) => {
+                Some(Modifier::SpeedDegrees(saturate(speed.into())))
+            }
+            CommandModifier::Timed(time) => Some(Modifier::Timed(saturate(time.into()))),
+            CommandModifier::TimedDuration(time) => {
+                Some(Modifier::Timed(saturate(time.as_millis())))
+            }
+            CommandModifier::CurrentHold(current) => {
+                Some(Modifier::CurrentHold(saturate(current.into())))
+            }
+            CommandModifier::CurrentLimp(current) => {
+                Some(Modifier::CurrentLimp(saturate(current.into())))
+            }
+            CommandModifier::None => None,
+            CommandModifier::Custom(text, value) => Some(Modifier::Other(text.to_owned(), value)),
+        }
+    }
+}
+
+impl fmt::Display for Modifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Modifier::Timed(value) => write!(f, "T{}", value),
+            Modifier::Speed(value) => write!(f, "S{}", value),
+            Modifier::SpeedDegrees(value) => write!(f, "SD{}", value),
+            Modifier::CurrentHold(value) => write!(f, "CH{}", value),
+            Modifier::CurrentLimp(value) => write!(f, "CL{}", value),
+            Modifier::Other(mnemonic, value) => write!(f, "{}{}", mnemonic, value),
+        }
+    }
+}
+
+/// Command addressed to a servo. e.g. `#5D1800T1000\r`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommandFrame {
+    /// ID of the servo. [BROADCAST_ID](crate::BROADCAST_ID) for all servos
+    pub id: u8,
+    pub command: Command,
+    pub modifiers: Vec<Modifier>,
+}
+
+impl CommandFrame {
+    /// Command without modifiers
+    pub fn new(id: u8, command: Command) -> CommandFrame {
+        CommandFrame {
+            id,
+            command,
+            modifiers: vec![],
+        }
+    }
+
+    /// Append a modifier
+    pub fn with_modifier(mut self, modifier: Modifier) -> CommandFrame {
+        self.modifiers.push(modifier);
+        self
+    }
+
+    /// Append modifiers used by [LSSDriver](crate::LSSDriver)
+    pub fn with_command_modifiers(mut self, modifiers: &[CommandModifier]) -> CommandFrame {
+        self.modifiers
+            .extend(modifiers.iter().filter_map(Modifier::from_command_modifier));
+        self
+    }
+}
+
+impl fmt::Display for CommandFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.id)?;
+        match self.command {
+            Command::Limp => write!(f, "L")?,
+            Command::HaltHold => write!(f, "H")?,
+            Command::Reset => write!(f, "RESET")?,
+            Command::Default => write!(f, "DEFAULT")?,
+            Command::Update => write!(f, "UPDATE")?,
+            Command::Confirm => write!(f, "CONFIRM")?,
+            Command::Move(value) => write!(f, "D{}", value)?,
+            Command::MoveRelative(value) => write!(f, "MD{}", value)?,
+            Command::MovePulse(value) => write!(f, "P{}", value)?,
+            Command::WheelSpeed(value) => write!(f, "WD{}", value)?,
+            Command::WheelSpeedRpm(value) => write!(f, "WR{}", value)?,
+            Command::Set(parameter, value) => write!(f, "{}{}", parameter.mnemonic(), value)?,
+            Command::Configure(parameter, value) => {
+                write!(f, "{}{}", parameter.config_mnemonic(), value)?
+            }
+            Command::ConfigureSetting(setting, value) => {
+                write!(f, "{}{}", setting.config_mnemonic(), value)?
+            }
+            Command::ConfigureRcMode(value) => write!(f, "CRC{}", value)?,
+            Command::ClearFirstPosition => {
+                write!(f, "{}", Setting::FirstPosition.config_mnemonic())?
+            }
+            Command::Query(query) => write!(f, "{}", query.mnemonic())?,
+        }
+        for modifier in &self.modifiers {
+            write!(f, "{}", modifier)?;
+        }
+        write!(f, "\r")
+    }
+}
+
+impl FromStr for CommandFrame {
+    type Err = LssDriverError;
+
+    /// Parse a command frame. Terminator is optional
+    fn from_str(frame: &str) -> DriverResult<CommandFrame> {
+        let error = || LssDriverError::PacketParsingError(format!("Invalid command {:?}", frame));
+        let mut rest = frame
+            .strip_suffix('\r')
+            .unwrap_or(frame)
+            .strip_prefix('#')
+            .ok_or_else(error)?;
+        let id = take_number(&mut rest)
+            .and_then(|id| u8::try_from(id).ok())
+            .ok_or_else(error)?;
+        let mnemonic = take_mnemonic(&mut rest);
+        let value = take_number(&mut rest);
+        let command = parse_command(mnemonic, value).ok_or_else(error)?;
+        let mut modifiers = vec![];
+        while !rest.is_empty() {
+            let mnemonic = take_mnemonic(&mut rest);
+            let value = take_number(&mut rest).ok_or_else(error)?;
+            if mnemonic.is_empty() {
+                return Err(error());
+            }
+            modifiers.push(Modifier::from_mnemonic(mnemonic, value));
+        }
+        Ok(CommandFrame {
+            id,
+            command,
+            modifiers,
+        })
+    }
+}
+
+fn parse_command(mnemonic: &str, value: Option<i32>) -> Option<Command> {
+    let command = match (mnemonic, value) {
+        ("L", None) => Command::Limp,
+        ("H", None) => Command::HaltHold,
+        ("RESET", None) => Command::Reset,
+        ("DEFAULT", None) => Command::Default,
+        ("UPDATE", None) => Command::Update,
+        ("CONFIRM", None) => Command::Confirm,
+        ("D", Some(value)) => Command::Move(value),
+        ("MD", Some(value)) => Command::MoveRelative(value),
+        ("P", Some(value)) => Command::MovePulse(value),
+        ("WD", Some(value)) => Command::WheelSpeed(value),
+        ("WR", Some(value)) => Command::WheelSpeedRpm(value),
+        ("Q", Some(1)) => Command::Query(Query::SafetyStatus),
+        ("QSD", Some(2)) => Command::Query(Query::CurrentSpeed),
+        ("QSR", Some(2)) => Command::Query(Query::CurrentSpeedRpm),
+        ("CRC", Some(value)) => Command::ConfigureRcMode(value),
+        ("CFD", None) => Command::ClearFirstPosition,
+        (mnemonic, None) => Command::Query(
+            Query::all()
+                .filter(|query| !query.shares_echo())
+                .find(|query| query.mnemonic() == mnemonic)?,
+        ),
+        (mnemonic, Some(1)) if mnemonic.starts_with('Q') => {
+            Command::Query(Query::ConfiguredParameter(
+                Parameter::ALL
+                    .into_iter()
+                    .find(|parameter| parameter.query_mnemonic() == mnemonic)?,
+            ))
+        }
+        (mnemonic, Some(value)) => {
+            if let Some(parameter) = Parameter::ALL
+                .into_iter()
+                .find(|parameter| parameter.config_mnemonic() == mnemonic)
+            {
+                Command::Configure(parameter, value)
+            } else if let Some(setting) = Setting::ALL
+                .into_iter()
+                .find(|setting| setting.config_mnemonic() == mnemonic)
+            {
+                Command::ConfigureSetting(setting, value)
+            } else {
+                let parameter = Parameter::ALL
+                    .into_iter()
+                    .find(|parameter| parameter.mnemonic() == mnemonic)?;
+                Command::Set(parameter, value)
+            }
+        }
+    };
+    Some(command)
+}
+
+/// Value of a reply
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Reply {
+    /// Numeric reply. e.g. `QD1800`
+    Value(Query, i32),
+    /// Text reply. e.g. `QMSLSS-ST1`
+    Text(Query, String),
+    /// Reply without a value. e.g. `QFD` when no first position is set
+    Empty(Query),
+}
+
+impl Reply {
+    /// Query this is a reply to
+    ///
+    /// `Q1` replies echo `Q` so they are parsed as [Query::Status].
+    /// Replies to [Query::ConfiguredParameter], [Query::CurrentSpeed] and [Query::CurrentSpeedRpm]
+    /// are parsed as [Query::Parameter] the same way
+    pub fn query(&self) -> Query {
+        match self {
+            Reply::Value(query, _) | Reply::Text(query, _) | Reply::Empty(query) => *query,
+        }
+    }
+
+    /// Numeric value if this is a numeric reply
+    pub fn value(&self) -> Option<i32> {
+        match self {
+            Reply::Value(_, value) => Some(*value),
+            Reply::Text(..) | Reply::Empty(_) => None,
+        }
+    }
+
+    /// Text if this is a text reply
+    pub fn text(&self) -> Option<&str> {
+        match self {
+            Reply::Text(_, text) => Some(text),
+            Reply::Value(..) | Reply::Empty(_) => None,
+        }
+    }
+}
+
+/// Reply from a servo. e.g. `*5QD1800\r`
+///
+/// Replies to `QID` don't have an ID prefix (`*QID5\r`). The ID is taken from the value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReplyFrame {
+    /// ID of the servo that replied
+    pub id: u8,
+    pub reply: Reply,
+}
+
+impl ReplyFrame {
+    pub fn new(id: u8, reply: Reply) -> ReplyFrame {
+        ReplyFrame { id, reply }
+    }
+}
+
+impl fmt::Display for ReplyFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.reply {
+            Reply::Value(Query::Setting(Setting::Id), value) => write!(f, "*QID{}", value)?,
+            Reply::Value(query, value) => write!(f, "*{}{}{}", self.id, query.echo(), value)?,
+            Reply::Text(query, text) => write!(f, "*{}{}{}", self.id, query.echo(), text)?,
+            Reply::Empty(query) => write!(f, "*{}{}", self.id, query.echo())?,
+        }
+        write!(f, "\r")
+    }
+}
+
+impl FromStr for ReplyFrame {
+    type Err = LssDriverError;
+
+    /// Parse a reply frame. Terminator is optional
+    fn from_str(frame: &str) -> DriverResult<ReplyFrame> {
+        let error = || LssDriverError::PacketParsingError(format!("Invalid reply {:?}", frame));
+        let mut rest = frame
+            .strip_suffix('\r')
+            .unwrap_or(frame)
+            .strip_prefix('*')
+            .ok_or_else(error)?;
+        let id = take_number(&mut rest);
+        // longest echo first so that QDT isn't parsed as QD
+        let query = Query::all()
+            .filter(|query| !query.shares_echo())
+            .filter(|query| rest.starts_with(query.echo()))
+            .max_by_key(|query| query.echo().len())
+            .ok_or_else(error)?;
+        rest = &rest[query.echo().len()..];
+        if query.is_text() {
+            let id = id.and_then(|id| u8::try_from(id).ok()).ok_or_else(error)?;
+            return Ok(ReplyFrame::new(id, Reply::Text(query, rest.to_owned())));
+        }
+        if rest.is_empty() && query.can_be_empty() {
+            let id = id.and_then(|id| u8::try_from(id).ok()).ok_or_else(error)?;
+            return Ok(ReplyFrame::new(id, Reply::Empty(query)));
+        }
+        let value = take_number(&mut rest).ok_or_else(error)?;
+        if !rest.is_empty() {
+            return Err(error());
+        }
+        let id = match (id, query) {
+            (None, Query::Setting(Setting::Id)) => value,
+            (Some(id), _) => id,
+            (None, _) => return Err(error()),
+        };
+        let id = u8::try_from(id).map_err(|_| error())?;
+        Ok(ReplyFrame::new(id, Reply::Value(query, value)))
+    }
+}
+
+fn take_mnemonic<'a>(text: &mut &'a str) -> &'a str {
+    let end = text
+        .find(|c: char| !c.is_ascii_uppercase())
+        .unwrap_or(text.len());
+    let (mnemonic, rest) = text.split_at(end);
+    *text = rest;
+    mnemonic
+}
+
+fn take_number(text: &mut &str) -> Option<i32> {
+    let sign = usize::from(text.starts_with('-'));
+    let end = text[sign..]
+        .find(|c: char| !c.is_ascii_digit())
+        .map(|end| end + sign)
+        .unwrap_or(text.len());
+    let value = text[..end].parse().ok()?;
+    *text = &text[end..];
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BROADCAST_ID;
+
+    fn all_commands() -> Vec<Command> {
+        let mut commands = vec![
+            Command::Limp,
+            Command::HaltHold,
+            Command::Reset,
+            Command::Default,
+            Command::Update,
+            Command::Confirm,
+            Command::Move(-1800),
+            Command::MoveRelative(300),
+            Command::MovePulse(1500),
+            Command::WheelSpeed(-45),
+            Command::WheelSpeedRpm(10),
+            Command::ConfigureRcMode(1),
+            Command::ClearFirstPosition,
+        ];
+        for parameter in Parameter::ALL {
+            commands.push(Command::Set(parameter, -12));
+            commands.push(Command::Configure(parameter, 34));
+        }
+        for setting in Setting::ALL {
+            commands.push(Command::ConfigureSetting(setting, 34));
+        }
+        commands.extend(Query::all().map(Command::Query));
+        commands
+    }
+
+    #[test]
+    fn commands_round_trip() {
+        for command in all_commands() {
+            let frame = CommandFrame::new(5, command);
+            let text = frame.to_string();
+            assert_eq!(text.parse::<CommandFrame>().unwrap(), frame, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn modifiers_round_trip() {
+        let frame = CommandFrame::new(BROADCAST_ID, Command::Move(-1800))
+            .with_modifier(Modifier::Timed(1000))
+            .with_modifier(Modifier::Speed(500))
+            .with_modifier(Modifier::SpeedDegrees(20))
+            .with_modifier(Modifier::CurrentHold(400))
+            .with_modifier(Modifier::CurrentLimp(600))
+            .with_modifier(Modifier::Other("X".to_owned(), 7));
+        let text = frame.to_string();
+        assert_eq!(text, "#254D-1800T1000S500SD20CH400CL600X7\r");
+        assert_eq!(text.parse::<CommandFrame>().unwrap(), frame);
+    }
+
+    #[test]
+    fn commands_serialize_to_wire_format() {
+        let params = vec![
+            (CommandFrame::new(5, Command::Limp), "#5L\r"),
+            (
+                CommandFrame::new(5, Command::Query(Query::SafetyStatus)),
+                "#5Q1\r",
+            ),
+            (
+                CommandFrame::new(5, Command::ConfigureSetting(Setting::LedBlinking, 3)),
+                "#5CLB3\r",
+            ),
+            (
+                CommandFrame::new(5, Command::ConfigureRcMode(2)),
+                "#5CRC2\r",
+            ),
+            (CommandFrame::new(5, Command::Query(Query::Speed)), "#5QS\r"),
+            (
+                CommandFrame::new(5, Command::Query(Query::CurrentSpeed)),
+                "#5QSD2\r",
+            ),
+            (
+                CommandFrame::new(5, Command::Query(Query::CurrentSpeedRpm)),
+                "#5QSR2\r",
+            ),
+            (CommandFrame::new(5, Command::ClearFirstPosition), "#5CFD\r"),
+            (
+                CommandFrame::new(5, Command::Set(Parameter::FilterPositionCount, 10)),
+                "#5FPC10\r",
+            ),
+            (
+                CommandFrame::new(254, Command::Query(Query::Setting(Setting::Id))),
+                "#254QID\r",
+            ),
+            (
+                CommandFrame::new(
+                    5,
+                    Command::Query(Query::ConfiguredParameter(Parameter::MaximumSpeed)),
+                ),
+                "#5QSD1\r",
+            ),
+        ];
+        for (frame, text) in params {
+            assert_eq!(frame.to_string(), text);
+        }
+    }
+
+    #[test]
+    fn command_parses() {
+        let frame: CommandFrame = "#5D-1800T1000SD20\r".parse().unwrap();
+        assert_eq!(
+            frame,
+            CommandFrame::new(5, Command::Move(-1800))
+                .with_modifier(Modifier::Timed(1000))
+                .with_modifier(Modifier::SpeedDegrees(20))
+        );
+        let frame: CommandFrame = "#254QID".parse().unwrap();
+        assert_eq!(
+            frame,
+            CommandFrame::new(254, Command::Query(Query::Setting(Setting::Id)))
+        );
+    }
+
+    #[test]
+    fn invalid_commands_fail() {
+        for frame in [
+            "5D1800\r",
+            "#D1800\r",
+            "#300L\r",
+            "#5\r",
+            "#5D\r",
+            "#5L10\r",
+            "#5ID3\r",
+            "#5QRC\r",
+            "#5QID1\r",
+            "#5RC1\r",
+            "#5QSD3\r",
+            "#5QD1\r",
+            "#5ZZ\r",
+            "#5D10T\r",
+            "#5D10t5\r",
+        ] {
+            assert!(frame.parse::<CommandFrame>().is_err(), "{:?}", frame);
+        }
+    }
+
+    #[test]
+    fn command_modifiers_convert() {
+        let frame = CommandFrame::new(5, Command::Move(900)).with_command_modifiers(&[
+            CommandModifier::TimedDuration(std::time::Duration::from_secs(2)),
+            CommandModifier::None,
+            CommandModifier::Custom("E", 1),
+        ]);
+        assert_eq!(frame.to_string(), "#5D900T2000E1\r");
+    }
+
+    #[test]
+    fn oversized_command_modifiers_saturate() {
+        assert_eq!(
+            Modifier::from_command_modifier(&CommandModifier::TimedDuration(
+                std::time::Duration::from_secs(u64::MAX)
+            )),
+            Some(Modifier::Timed(i32::MAX))
+        );
+        assert_eq!(
+            Modifier::from_command_modifier(&CommandModifier::Speed(u32::MAX)),
+            Some(Modifier::Speed(i32::MAX))
+        );
+    }
+
+    #[test]
+    fn replies_round_trip() {
+        for query in Query::all().filter(|query| !query.shares_echo()) {
+            let reply = if query.is_text() {
+                Reply::Text(query, "LSS-ST1".to_owned())
+            } else if query == Query::Setting(Setting::Id) {
+                Reply::Value(query, 5)
+            } else {
+                Reply::Value(query, -25)
+            };
+            let frame = ReplyFrame::new(5, reply);
+            let text = frame.to_string();
+            assert_eq!(text.parse::<ReplyFrame>().unwrap(), frame, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn replies_parse() {
+        let params = vec![
+            (
+                "*5QD1800\r",
+                ReplyFrame::new(5, Reply::Value(Query::Position, 1800)),
+            ),
+            (
+                "*5QDT900\r",
+                ReplyFrame::new(5, Reply::Value(Query::TargetPosition, 900)),
+            ),
+            ("*5Q6\r", ReplyFrame::new(5, Reply::Value(Query::Status, 6))),
+            (
+                "*QID12\r",
+                ReplyFrame::new(12, Reply::Value(Query::Setting(Setting::Id), 12)),
+            ),
+            (
+                "*5QFD\r",
+                ReplyFrame::new(5, Reply::Empty(Query::Setting(Setting::FirstPosition))),
+            ),
+            (
+                "*5QFD-900\r",
+                ReplyFrame::new(
+                    5,
+                    Reply::Value(Query::Setting(Setting::FirstPosition), -900),
+                ),
+            ),
+            (
+                "*5QMSLSS-HS1\r",
+                ReplyFrame::new(5, Reply::Text(Query::Model, "LSS-HS1".to_owned())),
+            ),
+        ];
+        for (text, frame) in params {
+            assert_eq!(text.parse::<ReplyFrame>().unwrap(), frame);
+        }
+    }
+
+    #[test]
+    fn safety_status_reply_echoes_status() {
+        let frame = ReplyFrame::new(5, Reply::Value(Query::SafetyStatus, 3));
+        assert_eq!(frame.to_string(), "*5Q3\r");
+    }
+
+    #[test]
+    fn configured_parameter_reply_echoes_parameter() {
+        let frame = ReplyFrame::new(
+            5,
+            Reply::Value(Query::ConfiguredParameter(Parameter::OriginOffset), -13),
+        );
+        assert_eq!(frame.to_string(), "*5QO-13\r");
+        assert_eq!(
+            Query::ConfiguredParameter(Parameter::OriginOffset).echo(),
+            Query::Parameter(Parameter::OriginOffset).echo()
+        );
+    }
+
+    #[test]
+    fn speed_replies_parse() {
+        let reply: ReplyFrame = "*5QS300\r".parse().unwrap();
+        assert_eq!(reply, ReplyFrame::new(5, Reply::Value(Query::Speed, 300)));
+        let reply: ReplyFrame = "*5QSD-450\r".parse().unwrap();
+        assert_eq!(
+            reply,
+            ReplyFrame::new(
+                5,
+                Reply::Value(Query::Parameter(Parameter::MaximumSpeed), -450)
+            )
+        );
+        let frame = ReplyFrame::new(5, Reply::Value(Query::CurrentSpeedRpm, 12));
+        assert_eq!(frame.to_string(), "*5QSR12\r");
+    }
+
+    #[test]
+    fn invalid_replies_fail() {
+        for frame in [
+            "5QD10\r",
+            "*5QD\r",
+            "*5QD1x\r",
+            "*5ZZ1\r",
+            "*QD10\r",
+            "*5QD18?\r",
+        ] {
+            assert!(frame.parse::<ReplyFrame>().is_err(), "{:?}", frame);
+        }
+    }
+}
@@ -0,0 +1,301 @@
+use crate::message_types::*;
+use crate::LSSDriver;
+use std::ops::DerefMut;
+
+type DriverResult<T> = Result<T, LssDriverError>;
+
+/// Generate methods that call [LSSDriver] with the ID of the handle
+macro_rules! forward {
+    ($(fn $name:ident(&mut self $(, $arg:ident: $ty:ty)*) -> $ret:ty;)*) => {
+        $(
+            #[doc = concat!("Same as [LSSDriver::", stringify!($name), "] for this servo")]
+            pub async fn $name(&mut self $(, $arg: $ty)*) -> DriverResult<$ret> {
+                self.driver.$name(self.id $(, $arg)*).await
+            }
+        )*
+    };
+}
+
+/// Handle to a single servo
+///
+/// Created with [LSSDriver::servo] or [SharedLSSDriver::servo](crate::SharedLSSDriver::servo).
+/// Has the same methods as [LSSDriver] without the `id` argument.
+/// Methods that change the ID, like [LSSDriver::set_id] and [LSSDriver::restore_factory_defaults],
+/// are only available on the driver.
+///
+/// Model, firmware version and serial number are cached after the first successful query.
+///
+/// # Example
+///
+/// ```no_run
+/// use lss_driver::{LSSDriver, SharedLSSDriver};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let mut driver = LSSDriver::new("COM1").unwrap();
+///     let mut servo = driver.servo(5);
+///     servo.move_to_position(90.0).await.unwrap();
+///     println!("{:?}", servo.query_model().await.unwrap());
+///
+///     let driver = SharedLSSDriver::new(driver);
+///     let mut shoulder = driver.servo(1);
+///     let mut elbow = driver.servo(2);
+///     tokio::spawn(async move { shoulder.move_to_position(45.0).await });
+///     elbow.move_to_position(-45.0).await.unwrap();
+/// }
+/// ```
+pub struct Servo<D> {
+    driver: D,
+    id: u8,
+    model: Option<Model>,
+    firmware_version: Option<String>,
+    serial_number: Option<String>,
+}
+
+impl<D: DerefMut<Target = LSSDriver>> Servo<D> {
+    /// Create handle to servo `id` on `driver`
+    ///
+    /// # Arguments
+    ///
+    /// * `driver` - Driver to use. e.g. `&mut LSSDriver` or [SharedLSSDriver](crate::SharedLSSDriver)
+    /// * `id` - ID of the servo
+    pub fn new(driver: D, id: u8) -> Servo<D> {
+        Servo {
+            driver,
+            id,
+            model: None,
+            firmware_version: None,
+            serial_number: None,
+        }
+    }
+
+    /// ID of the servo
+    pub fn id(&self) -> u8 {
+        self.id
+    }
+
+    /// Driver used by this handle. Useful for methods that aren't bound to a single servo
+    pub fn driver(&mut self) -> &mut LSSDriver {
+        &mut self.driver
+    }
+
+    /// Give back the driver
+    pub fn into_driver(self) -> D {
+        self.driver
+    }
+
+    /// Forget cached model, firmware version and serial number
+    pub fn clear_cache(&mut self) {
+        self.model = None;
+        self.firmware_version = None;
+        self.serial_number = None;
+    }
+
+    /// Same as [LSSDriver::expected_target] for this servo
+    pub fn expected_target(&self) -> Option<f32> {
+        self.driver.expected_target(self.id)
+    }
+
+    /// Same as [LSSDriver::query_model] but only queried once
+    pub async fn query_model(&mut self) -> DriverResult<Model> {
+        if let Some(model) = &self.model {
+            return Ok(model.clone());
+        }
+        let model = self.driver.query_model(self.id).await?;
+        self.model = Some(model.clone());
+        Ok(model)
+    }
+
+    /// Same as [LSSDriver::query_firmware_version] but only queried once
+    pub async fn query_firmware_version(&mut self) -> DriverResult<String> {
+        if let Some(firmware_version) = &self.firmware_version {
+            return Ok(firmware_version.clone());
+        }
+        let firmware_version = self.driver.query_firmware_version(self.id).await?;
+        self.firmware_version = Some(firmware_version.clone());
+        Ok(firmware_version)
+    }
+
+    /// Same as [LSSDriver::query_serial_number] but only queried once
+    pub async fn query_serial_number(&mut self) -> DriverResult<String> {
+        if let Some(serial_number) = &self.serial_number {
+            return Ok(serial_number.clone());
+        }
+        let serial_number = self.driver.query_serial_number(self.id).await?;
+        self.serial_number = Some(serial_number.clone());
+        Ok(serial_number)
+    }
+
+    forward! {
+        fn reset(&mut self) -> ();
+        fn configure_rc_mode(&mut self, mode: RcMode, confirm: bool) -> ();
+        fn configure_baud_rate(&mut self, baud_rate: u32) -> ();
+        fn query_baud_rate(&mut self) -> u32;
+        fn set_color(&mut self, color: LedColor) -> ();
+        fn configure_color(&mut self, color: LedColor) -> ();
+        fn query_color(&mut self) -> LedColor;
+        fn query_configured_color(&mut self) -> LedColor;
+        fn move_to_position(&mut self, position: f32) -> ();
+        fn move_to_position_with_modifier(&mut self, position: f32, modifier: CommandModifier) -> ();
+        fn move_to_position_with_modifiers(&mut self, position: f32, modifiers: &[CommandModifier]) -> ();
+        fn set_target_position(&mut self, position: f32) -> ();
+        fn move_relative(&mut self, delta: f32) -> ();
+        fn move_relative_with_modifier(&mut self, delta: f32, modifier: CommandModifier) -> ();
+        fn move_relative_with_modifiers(&mut self, delta: f32, modifiers: &[CommandModifier]) -> ();
+        fn step_target(&mut self, delta: f32) -> f32;
+        fn query_position(&mut self) -> f32;
+        fn query_target_position(&mut self) -> f32;
+        fn set_rotation_speed(&mut self, speed: f32) -> ();
+        fn set_rotation_speed_with_modifier(&mut self, speed: f32, modifier: CommandModifier) -> ();
+        fn set_rotation_speed_with_modifiers(&mut self, speed: f32, modifiers: &[CommandModifier]) -> ();
+        fn query_rotation_speed(&mut self) -> f32;
+        fn set_rotation_speed_rpm(&mut self, rpm: f32) -> ();
+        fn set_rotation_speed_rpm_with_modifier(&mut self, rpm: f32, modifier: CommandModifier) -> ();
+        fn set_rotation_speed_rpm_with_modifiers(&mut self, rpm: f32, modifiers: &[CommandModifier]) -> ();
+        fn query_rotation_speed_rpm(&mut self) -> f32;
+        fn query_status(&mut self) -> MotorStatus;
+        fn wait_for_status(&mut self, status: MotorStatus, options: WaitOptions) -> ();
+        fn wait_until_settled(&mut self, tolerance: f32, options: WaitOptions) -> f32;
+        fn query_safety_status(&mut self) -> SafeModeStatus;
+        fn set_motion_profile(&mut self, motion_profile: bool) -> ();
+        fn configure_motion_profile(&mut self, motion_profile: bool) -> ();
+        fn query_motion_profile(&mut self) -> bool;
+        fn query_configured_motion_profile(&mut self) -> bool;
+        fn set_filter_position_count(&mut self, filter_position_count: u8) -> ();
+        fn configure_filter_position_count(&mut self, filter_position_count: u8) -> ();
+        fn query_filter_position_count(&mut self) -> u8;
+        fn query_configured_filter_position_count(&mut self) -> u8;
+        fn set_angular_stiffness(&mut self, angular_stiffness: i32) -> ();
+        fn configure_angular_stiffness(&mut self, angular_stiffness: i32) -> ();
+        fn query_angular_stiffness(&mut self) -> i32;
+        fn query_configured_angular_stiffness(&mut self) -> i32;
+        fn set_angular_holding_stiffness(&mut self, angular_holding: i32) -> ();
+        fn configure_angular_holding_stiffness(&mut self, angular_holding: i32) -> ();
+        fn query_angular_holding_stiffness(&mut self) -> i32;
+        fn query_configured_angular_holding_stiffness(&mut self) -> i32;
+        fn set_angular_acceleration(&mut self, angular_acceleration: i32) -> ();
+        fn configure_angular_acceleration(&mut self, angular_acceleration: i32) -> ();
+        fn query_angular_acceleration(&mut self) -> i32;
+        fn query_configured_angular_acceleration(&mut self) -> i32;
+        fn set_angular_deceleration(&mut self, angular_deceleration: i32) -> ();
+        fn configure_angular_deceleration(&mut self, angular_deceleration: i32) -> ();
+        fn query_angular_deceleration(&mut self) -> i32;
+        fn query_configured_angular_deceleration(&mut self) -> i32;
+        fn set_maximum_motor_duty(&mut self, maximum_motor_duty: i32) -> ();
+        fn configure_maximum_motor_duty(&mut self, maximum_motor_duty: i32) -> ();
+        fn query_maximum_motor_duty(&mut self) -> i32;
+        fn query_configured_maximum_motor_duty(&mut self) -> i32;
+        fn set_maximum_speed(&mut self, maximum_speed: f32) -> ();
+        fn configure_maximum_speed(&mut self, maximum_speed: f32) -> ();
+        fn query_maximum_speed(&mut self) -> f32;
+        fn query_configured_maximum_speed(&mut self) -> f32;
+        fn set_gyre(&mut self, gyre: Gyre) -> ();
+        fn configure_gyre(&mut self, gyre: Gyre) -> ();
+        fn query_gyre(&mut self) -> Gyre;
+        fn query_configured_gyre(&mut self) -> Gyre;
+        fn configure_first_position(&mut self, position: f32) -> ();
+        fn clear_first_position(&mut self) -> ();
+        fn query_first_position(&mut self) -> Option<f32>;
+        fn configure_boot_profile(&mut self, profile: BootProfile) -> ();
+        fn query_boot_profile(&mut self) -> BootProfile;
+        fn limp(&mut self) -> ();
+        fn halt_hold(&mut self) -> ();
+        fn query_voltage(&mut self) -> f32;
+        fn query_temperature(&mut self) -> f32;
+        fn query_current(&mut self) -> f32;
+        fn query_telemetry(&mut self) -> Telemetry;
+        fn set_led_blinking(&mut self, blinking_mode: impl Into<LedBlinkingFlags>) -> ();
+        fn query_led_blinking(&mut self) -> LedBlinkingFlags;
+        fn set_origin_offset(&mut self, origin_offset: f32) -> ();
+        fn set_origin_offset_session(&mut self, origin_offset: f32) -> ();
+        fn configure_origin_offset(&mut self, origin_offset: f32) -> ();
+        fn query_origin_offset(&mut self) -> f32;
+        fn query_configured_origin_offset(&mut self) -> f32;
+        fn set_angular_range(&mut self, range: f32) -> ();
+        fn set_angular_range_session(&mut self, range: f32) -> ();
+        fn configure_angular_range(&mut self, range: f32) -> ();
+        fn query_angular_range(&mut self) -> f32;
+        fn query_configured_angular_range(&mut self) -> f32;
+        fn query_pwm_position(&mut self) -> i32;
+        fn query_pwm_scale(&mut self) -> PwmScale;
+        fn move_to_pwm_position(&mut self, position: i32) -> ();
+        fn move_to_pwm_position_with_modifier(&mut self, position: i32, modifier: CommandModifier) -> ();
+        fn move_to_pwm_position_with_modifiers(&mut self, position: i32, modifiers: &[CommandModifier]) -> ();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulator::{VirtualBus, VirtualServo};
+    use crate::{LSSDriverBuilder, SharedLSSDriver};
+    use approx::assert_relative_eq;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn handle_uses_its_id() {
+        let bus = VirtualBus::new(vec![
+            VirtualServo::new(1, Model::ST1),
+            VirtualServo::new(2, Model::ST1),
+        ]);
+        let mut driver = LSSDriver::with_driver(Box::new(bus.clone()));
+        let mut servo = driver.servo(2);
+        assert_eq!(servo.id(), 2);
+        servo.set_color(LedColor::Blue).await.unwrap();
+        servo.move_to_position(30.0).await.unwrap();
+        assert_eq!(servo.expected_target(), Some(30.0));
+        assert_eq!(bus.servo(2, |servo| servo.color()), Some(LedColor::Blue));
+        assert_eq!(bus.servo(1, |servo| servo.color()), Some(LedColor::Off));
+        assert_eq!(driver.expected_target(1), None);
+    }
+
+    #[tokio::test]
+    async fn static_info_is_cached() {
+        let bus = VirtualBus::new(vec![VirtualServo::new(5, Model::HT1)
+            .with_firmware_version("370")
+            .with_serial_number("12345678")]);
+        let sent = Arc::new(AtomicUsize::new(0));
+        let counter = sent.clone();
+        let mut driver = LSSDriverBuilder::with_transport(Box::new(bus))
+            .on_command(move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            })
+            .build()
+            .unwrap();
+        let mut servo = driver.servo(5);
+        for _ in 0..3 {
+            assert_eq!(servo.query_model().await.unwrap(), Model::HT1);
+            assert_eq!(servo.query_firmware_version().await.unwrap(), "370");
+            assert_eq!(servo.query_serial_number().await.unwrap(), "12345678");
+        }
+        assert_eq!(sent.load(Ordering::SeqCst), 3);
+        servo.clear_cache();
+        servo.query_model().await.unwrap();
+        assert_eq!(sent.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn shared_handles_work_concurrently() {
+        let bus = VirtualBus::new(vec![
+            VirtualServo::new(1, Model::ST1).with_position(10.0),
+            VirtualServo::new(2, Model::ST1).with_position(20.0),
+        ]);
+        let driver = SharedLSSDriver::new(LSSDriver::with_driver(Box::new(bus)));
+        let tasks: Vec<_> = [1, 2]
+            .into_iter()
+            .map(|id| {
+                let mut servo = driver.servo(id);
+                tokio::spawn(async move {
+                    for _ in 0..10 {
+                        let position = servo.query_position().await.unwrap();
+                        assert_relative_eq!(position, servo.id() as f32 * 10.0);
+                    }
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+    }
+}
@@ -0,0 +1,363 @@
+use crate::message_types::LssDriverError;
+use crate::transport::{FramedDriver, LssCommand, LssResponse, Transaction, TransactionResponses};
+use crate::{LSSDriver, Servo};
+use async_trait::async_trait;
+use std::ops::{Deref, DerefMut};
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::Duration;
+
+type DriverResult<T> = Result<T, LssDriverError>;
+
+/// Number of requests that can wait for the bus before callers have to wait
+const REQUEST_QUEUE_SIZE: usize = 64;
+
+enum BusRequest {
+    Send {
+        command: LssCommand,
+        respond_to: oneshot::Sender<DriverResult<()>>,
+    },
+    Receive {
+        timeout: Option<Duration>,
+        respond_to: oneshot::Sender<DriverResult<LssResponse>>,
+    },
+    Query {
+        command: LssCommand,
+        timeout: Option<Duration>,
+        respond_to: oneshot::Sender<DriverResult<LssResponse>>,
+    },
+    Transaction {
+        transaction: Transaction,
+        respond_to: oneshot::Sender<DriverResult<TransactionResponses>>,
+    },
+}
+
+/// Owns the transport and executes requests one at a time
+///
+/// Requests whose caller went away before they reached the bus are skipped.
+/// Once a query or transaction is sent its responses are always read, even if the caller is gone,
+/// so that they can't be mistaken for responses to the next query.
+async fn run_bus(
+    mut driver: Box<dyn FramedDriver + Send + Sync>,
+    mut requests: mpsc::Receiver<BusRequest>,
+) {
+    while let Some(request) = requests.recv().await {
+        match request {
+            BusRequest::Send {
+                command,
+                respond_to,
+            } => {
+                if respond_to.is_closed() {
+                    continue;
+                }
+                let _ = respond_to.send(driver.send(command).await);
+            }
+            BusRequest::Receive {
+                timeout,
+                respond_to,
+            } => {
+                if respond_to.is_closed() {
+                    continue;
+                }
+                let response = match timeout {
+                    Some(timeout) => driver.receive_timeout(timeout).await,
+                    None => driver.receive().await,
+                };
+                let _ = respond_to.send(response);
+            }
+            BusRequest::Query {
+                command,
+                timeout,
+                respond_to,
+            } => {
+                if respond_to.is_closed() {
+                    continue;
+                }
+                let response = match timeout {
+                    Some(timeout) => driver.query_timeout(command, timeout).await,
+                    None => driver.query(command).await,
+                };
+                let _ = respond_to.send(response);
+            }
+            BusRequest::Transaction {
+                transaction,
+                respond_to,
+            } => {
+                if respond_to.is_closed() {
+                    continue;
+                }
+                let _ = respond_to.send(driver.transaction(transaction).await);
+            }
+        }
+    }
+}
+
+/// [FramedDriver] that forwards everything to the bus task
+#[derive(Clone)]
+struct BusClient {
+    requests: mpsc::Sender<BusRequest>,
+}
+
+impl BusClient {
+    async fn request<T>(
+        &self,
+        request: impl FnOnce(oneshot::Sender<DriverResult<T>>) -> BusRequest,
+    ) -> DriverResult<T> {
+        let (respond_to, response) = oneshot::channel();
+        self.requests
+            .send(request(respond_to))
+            .await
+            .map_err(|_| LssDriverError::BusStopped)?;
+        response.await.map_err(|_| LssDriverError::BusStopped)?
+    }
+}
+
+#[async_trait]
+impl FramedDriver for BusClient {
+    async fn send(&mut self, command: LssCommand) -> DriverResult<()> {
+        self.request(|respond_to| BusRequest::Send {
+            command,
+            respond_to,
+        })
+        .await
+    }
+
+    async fn receive(&mut self) -> DriverResult<LssResponse> {
+        self.request(|respond_to| BusRequest::Receive {
+            timeout: None,
+            respond_to,
+        })
+        .await
+    }
+
+    async fn receive_timeout(&mut self, timeout: Duration) -> DriverResult<LssResponse> {
+        self.request(|respond_to| BusRequest::Receive {
+            timeout: Some(timeout),
+            respond_to,
+        })
+        .await
+    }
+
+    async fn query(&mut self, command: LssCommand) -> DriverResult<LssResponse> {
+        self.request(|respond_to| BusRequest::Query {
+            command,
+            timeout: None,
+            respond_to,
+        })
+        .await
+    }
+
+    async fn query_timeout(
+        &mut self,
+        command: LssCommand,
+        timeout: Duration,
+    ) -> DriverResult<LssResponse> {
+        self.request(|respond_to| BusRequest::Query {
+            command,
+            timeout: Some(timeout),
+            respond_to,
+        })
+        .await
+    }
+
+    async fn transaction(
+        &mut self,
+        transaction: Transaction,
+    ) -> DriverResult<TransactionResponses> {
+        self.request(|respond_to| BusRequest::Transaction {
+            transaction,
+            respond_to,
+        })
+        .await
+    }
+}
+
+/// Cloneable handle to a [LSSDriver] shared between tasks
+///
+/// The transport is moved into a background task that executes requests from all handles
+/// one at a time. Each query is paired with its response before anything else is sent.
+/// Calls that exchange several frames, such as [LSSDriver::query_telemetry] or queries with
+/// [ResponseValidation::DiscardStale](crate::ResponseValidation::DiscardStale),
+/// run as a single [Transaction] so other handles can't interleave with them.
+///
+/// Dereferences to [LSSDriver] so all of its methods are available.
+/// Each clone has its own [LSSDriver] front end, so clone the handle for every task.
+///
+/// Dropping a future before its command reached the bus cancels the command.
+/// If the command was already sent, the response is still read and discarded by the bus task
+/// so no stale responses are left behind.
+///
+/// The background task stops when all handles are dropped.
+///
+/// # Example
+///
+/// ```no_run
+/// use lss_driver::{LSSDriver, SharedLSSDriver};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let driver = SharedLSSDriver::new(LSSDriver::new("COM1").unwrap());
+///     let mut telemetry = driver.clone();
+///     tokio::spawn(async move {
+///         let voltage = telemetry.query_voltage(5).await.unwrap();
+///     });
+///     let mut motion = driver.clone();
+///     motion.move_to_position(5, 90.0).await.unwrap();
+/// }
+/// ```
+pub struct SharedLSSDriver {
+    driver: LSSDriver,
+    client: BusClient,
+}
+
+impl SharedLSSDriver {
+    /// Move driver into a background task
+    ///
+    /// Has to be called from within a tokio runtime
+    pub fn new(driver: LSSDriver) -> SharedLSSDriver {
+        let (requests, receiver) = mpsc::channel(REQUEST_QUEUE_SIZE);
+        let client = BusClient { requests };
+        let (driver, transport) = driver.replace_driver(Box::new(client.clone()));
+        tokio::spawn(run_bus(transport, receiver));
+        SharedLSSDriver { driver, client }
+    }
+
+    /// Handle to servo `id` with its own clone of the driver
+    ///
+    /// Handles can be moved to other tasks the same way as clones
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of the servo
+    pub fn servo(&self, id: u8) -> Servo<SharedLSSDriver> {
+        Servo::new(self.clone(), id)
+    }
+}
+
+impl Clone for SharedLSSDriver {
+    fn clone(&self) -> Self {
+        SharedLSSDriver {
+            driver: self.driver.clone_with_driver(Box::new(self.client.clone())),
+            client: self.client.clone(),
+        }
+    }
+}
+
+impl Deref for SharedLSSDriver {
+    type Target = LSSDriver;
+
+    fn deref(&self) -> &Self::Target {
+        &self.driver
+    }
+}
+
+impl DerefMut for SharedLSSDriver {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.driver
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulator::{VirtualBus, VirtualServo};
+    use crate::Model;
+    use approx::assert_relative_eq;
+    use tokio::time::{sleep, timeout, Duration};
+
+    /// Transport that takes a while to answer
+    struct SlowBus {
+        bus: VirtualBus,
+    }
+
+    #[async_trait]
+    impl FramedDriver for SlowBus {
+        async fn send(&mut self, command: LssCommand) -> DriverResult<()> {
+            self.bus.send(command).await
+        }
+
+        async fn receive(&mut self) -> DriverResult<LssResponse> {
+            sleep(Duration::from_millis(50)).await;
+            self.bus.receive().await
+        }
+    }
+
+    fn assert_send_sync_clone<T: Send + Sync + Clone>() {}
+
+    #[test]
+    fn shared_driver_is_send_sync_clone() {
+        assert_send_sync_clone::<SharedLSSDriver>();
+    }
+
+    #[tokio::test]
+    async fn concurrent_queries_get_their_own_responses() {
+        let servos = (1..=5)
+            .map(|id| VirtualServo::new(id, Model::ST1).with_position(id as f32 * 10.0))
+            .collect();
+        let bus = VirtualBus::new(servos);
+        let driver = SharedLSSDriver::new(LSSDriver::with_driver(Box::new(bus)));
+        let tasks: Vec<_> = (1..=5)
+            .map(|id| {
+                let mut driver = driver.clone();
+                tokio::spawn(async move {
+                    for _ in 0..20 {
+                        let position = driver.query_position(id).await.unwrap();
+                        assert_relative_eq!(position, id as f32 * 10.0);
+                    }
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn cancelled_query_leaves_no_stale_response() {
+        let bus = VirtualBus::new(vec![
+            VirtualServo::new(1, Model::ST1).with_position(10.0),
+            VirtualServo::new(2, Model::ST1).with_position(20.0),
+        ]);
+        let driver = SharedLSSDriver::new(LSSDriver::with_driver(Box::new(SlowBus { bus })));
+        let mut first = driver.clone();
+        let cancelled = timeout(Duration::from_millis(10), first.query_position(1)).await;
+        assert!(cancelled.is_err());
+        let mut second = driver.clone();
+        assert_relative_eq!(second.query_position(2).await.unwrap(), 20.0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn telemetry_is_not_interleaved_with_other_queries() {
+        let bus = VirtualBus::new(vec![
+            VirtualServo::new(1, Model::ST1).with_position(10.0),
+            VirtualServo::new(2, Model::ST1).with_position(20.0),
+        ]);
+        let driver = SharedLSSDriver::new(LSSDriver::with_driver(Box::new(SlowBus { bus })));
+        let mut telemetry = driver.clone();
+        let telemetry_task = tokio::spawn(async move {
+            for _ in 0..5 {
+                let snapshot = telemetry.query_telemetry(1).await.unwrap();
+                assert!(snapshot.is_complete(), "{:?}", snapshot);
+                assert_relative_eq!(snapshot.position.unwrap(), 10.0);
+            }
+        });
+        let mut position = driver.clone();
+        let position_task = tokio::spawn(async move {
+            for _ in 0..20 {
+                assert_relative_eq!(position.query_position(2).await.unwrap(), 20.0);
+            }
+        });
+        telemetry_task.await.unwrap();
+        position_task.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn commands_reach_bus() {
+        let bus = VirtualBus::new(vec![VirtualServo::new(1, Model::ST1)]);
+        let mut driver = SharedLSSDriver::new(LSSDriver::with_driver(Box::new(bus.clone())));
+        driver.set_color(1, crate::LedColor::Red).await.unwrap();
+        assert_eq!(
+            bus.servo(1, |servo| servo.color()),
+            Some(crate::LedColor::Red)
+        );
+    }
+}
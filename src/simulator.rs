@@ -0,0 +1,1253 @@
+//! Simulated LSS bus
+//!
+//! [VirtualBus] implements [FramedDriver] and answers commands the same way real servos would.
+//! It can be used to run code that depends on [LSSDriver](crate::LSSDriver) without hardware.
+//!
+//! # Example
+//!
+//! ```
+//! use lss_driver::simulator::{VirtualBus, VirtualServo};
+//! use lss_driver::{LSSDriver, Model};
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let bus = VirtualBus::new(vec![VirtualServo::new(5, Model::ST1)]);
+//!     let mut driver = LSSDriver::with_driver(Box::new(bus.clone()));
+//!     driver.move_to_position(5, 90.0).await.unwrap();
+//!     let target = driver.query_target_position(5).await.unwrap();
+//!     assert_eq!(target, 90.0);
+//! }
+//! ```
+
+use crate::message_types::{LedColor, LssDriverError, Model, MotorStatus, RcMode, SafeModeStatus};
+use crate::protocol::{
+    Command, CommandFrame, Modifier, Parameter, Query, Reply, ReplyFrame, Setting,
+};
+use crate::transport::{FramedDriver, LssCommand, LssResponse};
+use crate::BROADCAST_ID;
+use async_trait::async_trait;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tokio::time::Instant;
+
+type DriverResult<T> = Result<T, LssDriverError>;
+
+/// Default physical speed limit of a virtual servo in °/s
+pub const DEFAULT_SPEED_LIMIT: f32 = 360.0;
+
+/// Values that exist both as session values and as values stored in EEPROM
+#[derive(Clone, Debug, PartialEq)]
+struct Settings {
+    color: LedColor,
+    motion_profile: bool,
+    /// °/s2
+    angular_acceleration: i32,
+    /// °/s2
+    angular_deceleration: i32,
+    angular_stiffness: i32,
+    angular_holding_stiffness: i32,
+    filter_position_count: i32,
+    maximum_motor_duty: i32,
+    /// °/s
+    maximum_speed: f32,
+    /// degrees
+    origin_offset: f32,
+    /// degrees
+    angular_range: f32,
+    led_blinking: i32,
+    /// 1 clockwise, -1 counterclockwise
+    gyre: i32,
+    baud_rate: u32,
+}
+
+impl Settings {
+    /// Value of `parameter` in wire units
+    fn get(&self, parameter: Parameter) -> i32 {
+        let tenths = |value: f32| (value * 10.0).round() as i32;
+        match parameter {
+            Parameter::OriginOffset => tenths(self.origin_offset),
+            Parameter::AngularRange => tenths(self.angular_range),
+            Parameter::AngularStiffness => self.angular_stiffness,
+            Parameter::AngularHoldingStiffness => self.angular_holding_stiffness,
+            Parameter::AngularAcceleration => self.angular_acceleration,
+            Parameter::AngularDeceleration => self.angular_deceleration,
+            Parameter::MotionProfile => self.motion_profile as i32,
+            Parameter::FilterPositionCount => self.filter_position_count,
+            Parameter::MaximumMotorDuty => self.maximum_motor_duty,
+            Parameter::MaximumSpeed => tenths(self.maximum_speed),
+            Parameter::MaximumSpeedRpm => (self.maximum_speed / 6.0).round() as i32,
+            Parameter::LedColor => self.color as i32,
+            Parameter::Gyre => self.gyre,
+        }
+    }
+
+    /// Set `parameter` from a value in wire units
+    fn set(&mut self, parameter: Parameter, value: i32) {
+        let from_tenths = |value: i32| value as f32 / 10.0;
+        match parameter {
+            Parameter::OriginOffset => self.origin_offset = from_tenths(value),
+            Parameter::AngularRange => self.angular_range = from_tenths(value),
+            Parameter::AngularStiffness => self.angular_stiffness = value,
+            Parameter::AngularHoldingStiffness => self.angular_holding_stiffness = value,
+            Parameter::AngularAcceleration => self.angular_acceleration = value,
+            Parameter::AngularDeceleration => self.angular_deceleration = value,
+            Parameter::MotionProfile => self.motion_profile = value != 0,
+            Parameter::FilterPositionCount => self.filter_position_count = value,
+            Parameter::MaximumMotorDuty => self.maximum_motor_duty = value,
+            Parameter::MaximumSpeed => self.maximum_speed = from_tenths(value),
+            Parameter::MaximumSpeedRpm => self.maximum_speed = value as f32 * 6.0,
+            Parameter::LedColor => {
+                if let Ok(color) = LedColor::from_i32(value) {
+                    self.color = color;
+                }
+            }
+            Parameter::Gyre => self.gyre = value,
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            color: LedColor::Off,
+            motion_profile: true,
+            angular_acceleration: 100,
+            angular_deceleration: 100,
+            angular_stiffness: 0,
+            angular_holding_stiffness: 4,
+            filter_position_count: 5,
+            maximum_motor_duty: 1023,
+            maximum_speed: 180.0,
+            origin_offset: 0.0,
+            angular_range: 180.0,
+            led_blinking: 0,
+            gyre: 1,
+            baud_rate: 115200,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Motion {
+    /// Not moving
+    Idle,
+    /// Moving from `start` to `target` at `speed` °/s
+    Position {
+        start: f32,
+        started_at: Instant,
+        speed: f32,
+    },
+    /// Continuous rotation at `speed` °/s
+    Wheel {
+        start: f32,
+        started_at: Instant,
+        speed: f32,
+    },
+}
+
+/// State of a single simulated servo
+///
+/// Positions are stored relative to the factory origin.
+/// Values reported to the bus are shifted by the origin offset the same way real servos do.
+/// Gyre is only stored and reported. Unlike real servos, moves and positions aren't mirrored.
+#[derive(Clone, Debug)]
+pub struct VirtualServo {
+    id: u8,
+    configured_id: u8,
+    model: Model,
+    firmware_version: String,
+    serial_number: String,
+    speed_limit: f32,
+    session: Settings,
+    eeprom: Settings,
+    /// In tenths of degrees. `None` stays limp on power-up
+    first_position: Option<i32>,
+    /// `DEFAULT` or `UPDATE` waiting for `CONFIRM`
+    pending_confirmation: Option<Command>,
+    rc_mode: RcMode,
+    /// Takes effect after reset
+    configured_rc_mode: RcMode,
+    status: MotorStatus,
+    safe_mode_status: SafeModeStatus,
+    position: f32,
+    target: f32,
+    motion: Motion,
+    voltage: f32,
+    temperature: f32,
+    current: f32,
+    commands_received: u32,
+}
+
+impl VirtualServo {
+    /// Create a limp servo at position 0 with factory settings
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of the servo on the bus
+    /// * `model` - Model reported by QMS
+    pub fn new(id: u8, model: Model) -> VirtualServo {
+        VirtualServo {
+            id,
+            configured_id: id,
+            model,
+            firmware_version: String::from("368"),
+            serial_number: format!("{:08}", id),
+            speed_limit: DEFAULT_SPEED_LIMIT,
+            session: Settings::default(),
+            eeprom: Settings::default(),
+            first_position: None,
+            pending_confirmation: None,
+            rc_mode: RcMode::Serial,
+            configured_rc_mode: RcMode::Serial,
+            status: MotorStatus::Limp,
+            safe_mode_status: SafeModeStatus::NoLimits,
+            position: 0.0,
+            target: 0.0,
+            motion: Motion::Idle,
+            voltage: 12.0,
+            temperature: 35.0,
+            current: 0.0,
+            commands_received: 0,
+        }
+    }
+
+    /// Set the physical speed limit in °/s
+    ///
+    /// Moves never go faster than this regardless of configured speed or modifiers
+    pub fn with_speed_limit(mut self, speed_limit: f32) -> VirtualServo {
+        self.speed_limit = speed_limit;
+        self
+    }
+
+    /// Start the servo at a given position in degrees
+    pub fn with_position(mut self, position: f32) -> VirtualServo {
+        self.position = position;
+        self.target = position;
+        self
+    }
+
+    /// Set firmware version reported by QF
+    pub fn with_firmware_version(mut self, firmware_version: &str) -> VirtualServo {
+        self.firmware_version = firmware_version.to_owned();
+        self
+    }
+
+    /// Set serial number reported by QN
+    pub fn with_serial_number(mut self, serial_number: &str) -> VirtualServo {
+        self.serial_number = serial_number.to_owned();
+        self
+    }
+
+    /// ID the servo currently answers to
+    pub fn id(&self) -> u8 {
+        self.id
+    }
+
+    /// Model reported by QMS
+    pub fn model(&self) -> &Model {
+        &self.model
+    }
+
+    /// Status reported by Q
+    pub fn status(&self) -> MotorStatus {
+        self.status
+    }
+
+    /// Active control mode. Serial commands are ignored outside of [RcMode::Serial]
+    pub fn rc_mode(&self) -> RcMode {
+        self.rc_mode
+    }
+
+    /// Safe mode status reported by Q1
+    pub fn safe_mode_status(&self) -> SafeModeStatus {
+        self.safe_mode_status
+    }
+
+    /// Current position in degrees as reported by QD
+    pub fn position(&self) -> f32 {
+        self.position - self.session.origin_offset
+    }
+
+    /// Target position in degrees as reported by QDT
+    pub fn target(&self) -> f32 {
+        self.target - self.session.origin_offset
+    }
+
+    /// Current LED color
+    pub fn color(&self) -> LedColor {
+        self.session.color
+    }
+
+    /// Session value of the motion profile
+    pub fn motion_profile(&self) -> bool {
+        self.session.motion_profile
+    }
+
+    /// Session value of the origin offset in degrees
+    pub fn origin_offset(&self) -> f32 {
+        self.session.origin_offset
+    }
+
+    /// Origin offset stored in EEPROM in degrees
+    pub fn configured_origin_offset(&self) -> f32 {
+        self.eeprom.origin_offset
+    }
+
+    /// Position in degrees the servo moves to on power-up
+    pub fn first_position(&self) -> Option<f32> {
+        self.first_position.map(|position| position as f32 / 10.0)
+    }
+
+    /// Session value of the angular range in degrees
+    pub fn angular_range(&self) -> f32 {
+        self.session.angular_range
+    }
+
+    /// Angular range stored in EEPROM in degrees
+    pub fn configured_angular_range(&self) -> f32 {
+        self.eeprom.angular_range
+    }
+
+    /// Set voltage in volts reported by QV
+    pub fn set_voltage(&mut self, voltage: f32) {
+        self.voltage = voltage;
+    }
+
+    /// Set temperature in celsius reported by QT
+    pub fn set_temperature(&mut self, temperature: f32) {
+        self.temperature = temperature;
+    }
+
+    /// Set current in Amps reported by QC
+    pub fn set_current(&mut self, current: f32) {
+        self.current = current;
+    }
+
+    /// Put the servo in safe mode the same way an overload would
+    ///
+    /// The servo goes limp and reports [MotorStatus::SafeMode] until reset
+    pub fn trip_safe_mode(&mut self, reason: SafeModeStatus) {
+        self.stop_motion(Instant::now());
+        self.status = MotorStatus::SafeMode;
+        self.safe_mode_status = reason;
+    }
+
+    /// Advance simulated motion to `now`
+    fn update(&mut self, now: Instant) {
+        match self.motion {
+            Motion::Idle => (),
+            Motion::Position {
+                start,
+                started_at,
+                speed,
+            } => {
+                let travelled = speed * now.duration_since(started_at).as_secs_f32();
+                let distance = self.target - start;
+                if travelled >= distance.abs() {
+                    self.position = self.target;
+                    self.motion = Motion::Idle;
+                    self.status = MotorStatus::Holding;
+                } else {
+                    self.position = start + travelled * distance.signum();
+                }
+            }
+            Motion::Wheel {
+                start,
+                started_at,
+                speed,
+            } => {
+                self.position = start + speed * now.duration_since(started_at).as_secs_f32();
+                self.target = self.position;
+            }
+        }
+    }
+
+    fn stop_motion(&mut self, now: Instant) {
+        self.update(now);
+        self.motion = Motion::Idle;
+        self.target = self.position;
+    }
+
+    fn can_move(&self) -> bool {
+        self.status != MotorStatus::SafeMode
+    }
+
+    /// Start moving towards a position given in degrees from factory origin
+    fn start_move(&mut self, target: f32, modifiers: &[Modifier], now: Instant) {
+        if !self.can_move() {
+            return;
+        }
+        self.stop_motion(now);
+        let distance = (target - self.position).abs();
+        let mut speed = self.session.maximum_speed;
+        for modifier in modifiers {
+            match *modifier {
+                Modifier::SpeedDegrees(value) => speed = value as f32 / 10.0,
+                Modifier::Speed(value) => {
+                    speed = value as f32 * self.session.angular_range / 2000.0
+                }
+                Modifier::Timed(value) if value > 0 => speed = distance / (value as f32 / 1000.0),
+                _ => (),
+            }
+        }
+        if !self.session.motion_profile {
+            speed = self.speed_limit;
+        }
+        self.target = target;
+        self.motion = Motion::Position {
+            start: self.position,
+            started_at: now,
+            speed: speed.min(self.speed_limit).max(f32::EPSILON),
+        };
+        self.status = MotorStatus::Traveling;
+        self.update(now);
+    }
+
+    fn start_rotation(&mut self, speed: f32, now: Instant) {
+        if !self.can_move() {
+            return;
+        }
+        self.stop_motion(now);
+        if speed == 0.0 {
+            self.status = MotorStatus::Holding;
+            return;
+        }
+        self.motion = Motion::Wheel {
+            start: self.position,
+            started_at: now,
+            speed: speed.clamp(-self.speed_limit, self.speed_limit),
+        };
+        self.status = MotorStatus::Traveling;
+    }
+
+    /// Signed speed in °/s of any motion
+    fn speed(&self) -> f32 {
+        match self.motion {
+            Motion::Idle => 0.0,
+            Motion::Position { speed, .. } => speed * (self.target - self.position).signum(),
+            Motion::Wheel { speed, .. } => speed,
+        }
+    }
+
+    fn wheel_speed(&self) -> f32 {
+        match self.motion {
+            Motion::Wheel { speed, .. } => speed,
+            _ => 0.0,
+        }
+    }
+
+    fn pwm_position(&self) -> i32 {
+        let half_range = self.session.angular_range / 2.0;
+        let position = self.position();
+        if position < -half_range {
+            -500
+        } else if position > half_range {
+            2500
+        } else {
+            (1500.0 + position / half_range * 1000.0).round() as i32
+        }
+    }
+
+    /// Revert session to values stored in EEPROM
+    fn reset(&mut self, now: Instant) {
+        self.stop_motion(now);
+        self.id = self.configured_id;
+        self.rc_mode = self.configured_rc_mode;
+        self.session = self.eeprom.clone();
+        self.status = MotorStatus::Limp;
+        self.safe_mode_status = SafeModeStatus::NoLimits;
+        if let Some(first_position) = self.first_position {
+            let target = first_position as f32 / 10.0 + self.session.origin_offset;
+            self.start_move(target, &[], now);
+        }
+    }
+
+    /// Replace EEPROM with factory values. Takes effect after reset
+    fn restore_factory_defaults(&mut self) {
+        self.configured_id = crate::FACTORY_ID;
+        self.eeprom = Settings::default();
+        self.first_position = None;
+        self.configured_rc_mode = RcMode::Serial;
+    }
+
+    /// Apply a single command and return the reply if the command is a query
+    fn handle(&mut self, frame: &CommandFrame, now: Instant) -> Option<ReplyFrame> {
+        self.update(now);
+        if self.rc_mode != RcMode::Serial {
+            return None;
+        }
+        let from_tenths = |value: i32| value as f32 / 10.0;
+        // CONFIRM only applies to the command right before it
+        let pending_confirmation = self.pending_confirmation.take();
+        match frame.command {
+            Command::Reset => self.reset(now),
+            Command::Limp => {
+                self.stop_motion(now);
+                if self.can_move() {
+                    self.status = MotorStatus::Limp;
+                }
+            }
+            Command::HaltHold => {
+                self.stop_motion(now);
+                if self.can_move() {
+                    self.status = MotorStatus::Holding;
+                }
+            }
+            Command::Default | Command::Update => self.pending_confirmation = Some(frame.command),
+            Command::Confirm => {
+                // firmware updates aren't simulated
+                if pending_confirmation == Some(Command::Default) {
+                    self.restore_factory_defaults();
+                }
+            }
+            Command::Move(position) => {
+                let target = from_tenths(position) + self.session.origin_offset;
+                self.start_move(target, &frame.modifiers, now);
+            }
+            Command::MoveRelative(delta) => {
+                let target = self.position + from_tenths(delta);
+                self.start_move(target, &frame.modifiers, now);
+            }
+            Command::MovePulse(pwm) => {
+                let half_range = self.session.angular_range / 2.0;
+                let position = (pwm - 1500) as f32 / 1000.0 * half_range;
+                let target = position + self.session.origin_offset;
+                self.start_move(target, &frame.modifiers, now);
+            }
+            Command::WheelSpeed(speed) => self.start_rotation(speed as f32, now),
+            Command::WheelSpeedRpm(rpm) => self.start_rotation(rpm as f32 * 6.0, now),
+            Command::Set(parameter, value) => {
+                self.session.set(parameter, value);
+            }
+            // configuration (EEPROM) values also change the session
+            Command::Configure(parameter, value) => {
+                self.session.set(parameter, value);
+                self.eeprom.set(parameter, value);
+            }
+            Command::ConfigureSetting(Setting::Id, value) => {
+                // takes effect after reset
+                if let Ok(new_id) = u8::try_from(value) {
+                    self.configured_id = new_id;
+                }
+            }
+            Command::ConfigureSetting(Setting::FirstPosition, value) => {
+                self.first_position = Some(value);
+            }
+            Command::ClearFirstPosition => self.first_position = None,
+            Command::ConfigureSetting(Setting::LedBlinking, value) => {
+                self.session.led_blinking = value;
+                self.eeprom.led_blinking = value;
+            }
+            Command::ConfigureSetting(Setting::BaudRate, value) => {
+                if let Ok(baud_rate) = u32::try_from(value) {
+                    self.session.baud_rate = baud_rate;
+                    self.eeprom.baud_rate = baud_rate;
+                }
+            }
+            Command::ConfigureRcMode(value) => {
+                self.configured_rc_mode = match value {
+                    1 => RcMode::Position,
+                    2 => RcMode::Wheel,
+                    _ => RcMode::Serial,
+                };
+            }
+            Command::Query(query) => {
+                return self
+                    .answer(query)
+                    .map(|reply| ReplyFrame::new(self.id, reply))
+            }
+        }
+        None
+    }
+
+    fn answer(&self, query: Query) -> Option<Reply> {
+        let tenths = |value: f32| (value * 10.0).round() as i32;
+        let value = match query {
+            Query::Status => self.status as i32,
+            Query::SafetyStatus => self.safe_mode_status as i32,
+            Query::Position => tenths(self.position()),
+            Query::TargetPosition => tenths(self.target()),
+            Query::PulsePosition => self.pwm_position(),
+            Query::WheelSpeed => self.wheel_speed().round() as i32,
+            Query::WheelSpeedRpm => (self.wheel_speed() / 6.0).round() as i32,
+            Query::Speed => (self.speed() * 2000.0 / self.session.angular_range).round() as i32,
+            Query::CurrentSpeed => tenths(self.speed()),
+            Query::CurrentSpeedRpm => (self.speed() / 6.0).round() as i32,
+            Query::Voltage => (self.voltage * 1000.0).round() as i32,
+            Query::Temperature => tenths(self.temperature),
+            Query::Current => (self.current * 1000.0).round() as i32,
+            Query::Model => {
+                let model = match &self.model {
+                    Model::ST1 => "LSS-ST1",
+                    Model::HS1 => "LSS-HS1",
+                    Model::HT1 => "LSS-HT1",
+                    Model::Other(other) => other,
+                };
+                return Some(Reply::Text(query, model.to_owned()));
+            }
+            Query::Firmware => return Some(Reply::Text(query, self.firmware_version.clone())),
+            Query::SerialNumber => return Some(Reply::Text(query, self.serial_number.clone())),
+            Query::Setting(Setting::Id) => self.id as i32,
+            Query::Setting(Setting::FirstPosition) => match self.first_position {
+                Some(first_position) => first_position,
+                None => return Some(Reply::Empty(query)),
+            },
+            Query::Setting(Setting::LedBlinking) => self.session.led_blinking,
+            Query::Setting(Setting::BaudRate) => self.session.baud_rate as i32,
+            Query::Parameter(parameter) => self.session.get(parameter),
+            Query::ConfiguredParameter(parameter) => self.eeprom.get(parameter),
+        };
+        Some(Reply::Value(query, value))
+    }
+}
+
+/// Faults injected into responses of a [VirtualBus]
+///
+/// Counters are shared by the whole bus so faults are deterministic
+#[derive(Clone, Debug, PartialEq)]
+pub struct Faults {
+    /// Drop every n-th response. Driver will see a timeout
+    pub drop_every: Option<u32>,
+    /// Corrupt every n-th response. Driver will fail to parse it
+    pub garble_every: Option<u32>,
+    /// Trip safe mode on a servo after it receives this many commands
+    pub safe_mode_after: Option<u32>,
+    /// Reason reported by Q1 when safe mode is tripped
+    pub safe_mode_reason: SafeModeStatus,
+}
+
+impl Default for Faults {
+    fn default() -> Self {
+        Faults {
+            drop_every: None,
+            garble_every: None,
+            safe_mode_after: None,
+            safe_mode_reason: SafeModeStatus::CurrentLimit,
+        }
+    }
+}
+
+fn every(n: Option<u32>, count: u32) -> bool {
+    n.map(|n| n > 0 && count.is_multiple_of(n)).unwrap_or(false)
+}
+
+/// Replace last character before the terminator so the frame no longer parses
+fn garble(response: &str) -> String {
+    let mut garbled = response.trim_end_matches('\r').to_owned();
+    garbled.pop();
+    garbled.push_str("?\r");
+    garbled
+}
+
+struct BusState {
+    servos: Vec<VirtualServo>,
+    faults: Faults,
+    response_count: u32,
+}
+
+/// Simulated bus of [VirtualServo]s
+///
+/// Cloning the bus creates a new connection to the same set of servos.
+/// Each clone has its own queue of pending responses so one clone can be given to the
+/// driver while another is used to inspect or change servo state.
+///
+/// As on a real bus, commands for IDs that aren't present are ignored
+/// and `receive` returns [LssDriverError::TimeoutError] when there is no response.
+pub struct VirtualBus {
+    state: Arc<Mutex<BusState>>,
+    responses: VecDeque<LssResponse>,
+}
+
+impl Clone for VirtualBus {
+    fn clone(&self) -> Self {
+        VirtualBus {
+            state: self.state.clone(),
+            responses: VecDeque::new(),
+        }
+    }
+}
+
+impl VirtualBus {
+    /// Create a bus with the given servos
+    pub fn new(servos: Vec<VirtualServo>) -> VirtualBus {
+        VirtualBus {
+            state: Arc::new(Mutex::new(BusState {
+                servos,
+                faults: Faults::default(),
+                response_count: 0,
+            })),
+            responses: VecDeque::new(),
+        }
+    }
+
+    /// Inject faults into the bus
+    pub fn with_faults(self, faults: Faults) -> VirtualBus {
+        self.set_faults(faults);
+        self
+    }
+
+    /// Change faults injected into the bus
+    pub fn set_faults(&self, faults: Faults) {
+        self.state.lock().unwrap().faults = faults;
+    }
+
+    /// Add a servo to the bus
+    pub fn add_servo(&self, servo: VirtualServo) {
+        self.state.lock().unwrap().servos.push(servo);
+    }
+
+    /// Inspect or modify servo with a given ID
+    ///
+    /// Returns `None` if there is no such servo on the bus
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lss_driver::simulator::{VirtualBus, VirtualServo};
+    /// use lss_driver::Model;
+    ///
+    /// let bus = VirtualBus::new(vec![VirtualServo::new(5, Model::ST1)]);
+    /// bus.servo(5, |servo| servo.set_temperature(70.0));
+    /// ```
+    pub fn servo<T>(&self, id: u8, f: impl FnOnce(&mut VirtualServo) -> T) -> Option<T> {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        let servo = state.servos.iter_mut().find(|servo| servo.id == id)?;
+        servo.update(now);
+        Some(f(servo))
+    }
+
+    /// Process a single command frame and return all responses
+    ///
+    /// The frame should include the `#` prefix. Terminator is optional.
+    pub fn process(&self, frame: &str) -> Vec<String> {
+        let now = Instant::now();
+        let command: CommandFrame = match frame.parse() {
+            Ok(command) => command,
+            Err(_) => return vec![],
+        };
+        let mut state = self.state.lock().unwrap();
+        let BusState {
+            servos,
+            faults,
+            response_count,
+        } = &mut *state;
+        servos.sort_by_key(|servo| servo.id);
+        let mut responses = vec![];
+        for servo in servos
+            .iter_mut()
+            .filter(|servo| command.id == BROADCAST_ID || servo.id == command.id)
+        {
+            servo.commands_received += 1;
+            if faults.safe_mode_after == Some(servo.commands_received) {
+                servo.trip_safe_mode(faults.safe_mode_reason);
+            }
+            if let Some(response) = servo.handle(&command, now).map(|reply| reply.to_string()) {
+                *response_count += 1;
+                if every(faults.drop_every, *response_count) {
+                    continue;
+                }
+                if every(faults.garble_every, *response_count) {
+                    responses.push(garble(&response));
+                } else {
+                    responses.push(response);
+                }
+            }
+        }
+        responses
+    }
+}
+
+#[async_trait]
+impl FramedDriver for VirtualBus {
+    async fn send(&mut self, command: LssCommand) -> DriverResult<()> {
+        // a single write can contain several commands
+        for frame in command.as_str().split_inclusive('\r') {
+            let responses = self.process(frame);
+            self.responses
+                .extend(responses.into_iter().map(LssResponse::new));
+        }
+        Ok(())
+    }
+
+    async fn receive(&mut self) -> DriverResult<LssResponse> {
+        self.responses
+            .pop_front()
+            .ok_or(LssDriverError::TimeoutError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BootProfile, GroupTiming, LSSDriver, WaitOptions};
+    use approx::assert_relative_eq;
+    use std::collections::HashMap;
+    use tokio::time::Duration;
+
+    fn driver_with_bus(servos: Vec<VirtualServo>) -> (LSSDriver, VirtualBus) {
+        let bus = VirtualBus::new(servos);
+        (LSSDriver::with_driver(Box::new(bus.clone())), bus)
+    }
+
+    #[test]
+    fn queries_are_answered_in_wire_format() {
+        let bus = VirtualBus::new(vec![VirtualServo::new(5, Model::ST1).with_position(180.0)]);
+        assert_eq!(bus.process("#5QD\r"), vec!["*5QD1800\r".to_owned()]);
+        assert_eq!(bus.process("#5QMS\r"), vec!["*5QMSLSS-ST1\r".to_owned()]);
+        assert_eq!(bus.process("#254QID\r"), vec!["*QID5\r".to_owned()]);
+        assert!(bus.process("#4QD\r").is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rpm_is_simulated_and_gyre_is_stored() {
+        let bus = VirtualBus::new(vec![VirtualServo::new(5, Model::ST1)]);
+        assert!(bus.process("#5WR10\r").is_empty());
+        assert_eq!(bus.process("#5QWR\r"), vec!["*5QWR10\r".to_owned()]);
+        assert_eq!(bus.process("#5QWD\r"), vec!["*5QWD60\r".to_owned()]);
+        bus.process("#5CG-1\r");
+        assert_eq!(bus.process("#5QG\r"), vec!["*5QG-1\r".to_owned()]);
+        bus.process("#5RESET\r");
+        assert_eq!(bus.process("#5QG\r"), vec!["*5QG-1\r".to_owned()]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn speed_of_moves_is_simulated() {
+        let bus = VirtualBus::new(vec![VirtualServo::new(5, Model::ST1)]);
+        assert_eq!(bus.process("#5QSD2\r"), vec!["*5QSD0\r".to_owned()]);
+        bus.process("#5D-900SD600\r");
+        assert_eq!(bus.process("#5QSD2\r"), vec!["*5QSD-600\r".to_owned()]);
+        assert_eq!(bus.process("#5QSR2\r"), vec!["*5QSR-10\r".to_owned()]);
+        assert_eq!(bus.process("#5QS\r"), vec!["*5QS-667\r".to_owned()]);
+        assert_eq!(bus.process("#5QWD\r"), vec!["*5QWD0\r".to_owned()]);
+    }
+
+    #[tokio::test]
+    async fn unknown_id_times_out() {
+        let (mut driver, _) = driver_with_bus(vec![VirtualServo::new(5, Model::ST1)]);
+        let res = driver.query_position(4).await;
+        assert!(res.unwrap_err().is_timeout());
+    }
+
+    #[tokio::test]
+    async fn driver_queries_simulated_servo() {
+        let (mut driver, _) = driver_with_bus(vec![VirtualServo::new(5, Model::HT1)]);
+        assert_eq!(driver.query_id(BROADCAST_ID).await.unwrap(), 5);
+        assert_eq!(driver.query_model(5).await.unwrap(), Model::HT1);
+        assert_eq!(driver.query_status(5).await.unwrap(), MotorStatus::Limp);
+        assert_relative_eq!(driver.query_voltage(5).await.unwrap(), 12.0);
+        driver.set_color(5, LedColor::Red).await.unwrap();
+        assert_eq!(driver.query_color(5).await.unwrap(), LedColor::Red);
+        driver.set_angular_stiffness(5, -2).await.unwrap();
+        assert_eq!(driver.query_angular_stiffness(5).await.unwrap(), -2);
+        driver.set_maximum_speed(5, 90.0).await.unwrap();
+        assert_relative_eq!(driver.query_maximum_speed(5).await.unwrap(), 90.0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn motion_is_simulated_over_time() {
+        let (mut driver, _) = driver_with_bus(vec![VirtualServo::new(5, Model::ST1)]);
+        driver.set_maximum_speed(5, 90.0).await.unwrap();
+        driver.move_to_position(5, 90.0).await.unwrap();
+        assert_eq!(
+            driver.query_status(5).await.unwrap(),
+            MotorStatus::Traveling
+        );
+        assert_relative_eq!(driver.query_target_position(5).await.unwrap(), 90.0);
+
+        tokio::time::advance(Duration::from_millis(500)).await;
+        assert_relative_eq!(driver.query_position(5).await.unwrap(), 45.0);
+
+        tokio::time::advance(Duration::from_millis(600)).await;
+        assert_relative_eq!(driver.query_position(5).await.unwrap(), 90.0);
+        assert_eq!(driver.query_status(5).await.unwrap(), MotorStatus::Holding);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn speed_limit_caps_motion() {
+        let (mut driver, _) =
+            driver_with_bus(vec![VirtualServo::new(5, Model::ST1).with_speed_limit(10.0)]);
+        driver
+            .move_to_position_with_modifier(5, 90.0, crate::CommandModifier::Timed(1000))
+            .await
+            .unwrap();
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert_relative_eq!(driver.query_position(5).await.unwrap(), 10.0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn boot_profile_applies_on_reset() {
+        let (mut driver, bus) = driver_with_bus(vec![
+            VirtualServo::new(1, Model::ST1),
+            VirtualServo::new(2, Model::ST1),
+        ]);
+        let profile = BootProfile {
+            first_position: Some(45.0),
+            color: LedColor::Magenta,
+            motion_profile: false,
+        };
+        driver
+            .configure_boot_profile(BROADCAST_ID, profile)
+            .await
+            .unwrap();
+        assert_eq!(driver.query_boot_profile(2).await.unwrap(), profile);
+        driver.reset(BROADCAST_ID).await.unwrap();
+        tokio::time::advance(Duration::from_secs(5)).await;
+        assert_relative_eq!(driver.query_position(1).await.unwrap(), 45.0);
+        assert_eq!(driver.query_color(1).await.unwrap(), LedColor::Magenta);
+        driver.clear_first_position(1).await.unwrap();
+        assert_eq!(driver.query_first_position(1).await.unwrap(), None);
+        assert_eq!(
+            bus.servo(2, |servo| servo.first_position()),
+            Some(Some(45.0))
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn telemetry_is_read_in_one_go() {
+        let (mut driver, bus) = driver_with_bus(vec![
+            VirtualServo::new(5, Model::ST1).with_position(10.0),
+            VirtualServo::new(6, Model::ST1),
+        ]);
+        bus.servo(5, |servo| servo.set_temperature(50.0));
+        driver.move_to_position(5, 90.0).await.unwrap();
+        tokio::time::advance(Duration::from_millis(100)).await;
+        let telemetry = driver.query_telemetry(5).await.unwrap();
+        assert!(telemetry.is_complete());
+        assert!(telemetry.position.unwrap() > 10.0);
+        assert_relative_eq!(telemetry.target_position.unwrap(), 90.0);
+        assert_relative_eq!(telemetry.temperature.unwrap(), 50.0);
+        assert_relative_eq!(telemetry.voltage.unwrap(), 12.0);
+        assert_eq!(driver.expected_target(5), Some(90.0));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn telemetry_survives_dropped_and_stale_replies() {
+        let (mut driver, bus) = driver_with_bus(vec![
+            VirtualServo::new(5, Model::ST1).with_position(10.0),
+            VirtualServo::new(6, Model::ST1).with_position(20.0),
+        ]);
+        // both servos answer a broadcast query. Only the first reply is read
+        // and the other one is left on the bus
+        driver.query_position(BROADCAST_ID).await.unwrap();
+        bus.set_faults(Faults {
+            drop_every: Some(8),
+            ..Faults::default()
+        });
+        // 2 replies so far. The 6th telemetry reply is dropped
+        let telemetry = driver.query_telemetry(6).await.unwrap();
+        assert!(matches!(
+            telemetry.speed.unwrap_err().root_cause(),
+            LssDriverError::TimeoutError
+        ));
+        assert!(telemetry.status.is_ok());
+        assert_relative_eq!(telemetry.position.unwrap(), 20.0);
+        assert_relative_eq!(telemetry.target_position.unwrap(), 20.0);
+        assert!(telemetry.pwm_position.is_ok());
+        assert_relative_eq!(telemetry.speed_rpm.unwrap(), 0.0);
+        assert_relative_eq!(telemetry.voltage.unwrap(), 12.0);
+        assert!(telemetry.temperature.is_ok());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn group_move_arrives_together() {
+        let (mut driver, bus) = driver_with_bus(vec![
+            VirtualServo::new(1, Model::ST1),
+            VirtualServo::new(2, Model::ST1).with_position(-30.0),
+            VirtualServo::new(3, Model::ST1).with_position(50.0),
+        ]);
+        let targets = HashMap::from([(1, 90.0), (2, 0.0), (3, 45.0)]);
+        let started = Instant::now();
+        driver
+            .move_group(&targets, GroupTiming::Speed(90.0))
+            .await
+            .unwrap();
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_secs(1));
+        assert!(elapsed < Duration::from_secs(1) + 2 * crate::GROUP_POLL_INTERVAL);
+        for (id, target) in targets {
+            assert_relative_eq!(bus.servo(id, |servo| servo.position()).unwrap(), target);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn group_move_times_out() {
+        let (mut driver, bus) = driver_with_bus(vec![
+            VirtualServo::new(1, Model::ST1),
+            VirtualServo::new(2, Model::ST1).with_speed_limit(10.0),
+        ]);
+        let targets = HashMap::from([(1, 90.0), (2, 90.0)]);
+        let err = driver
+            .move_group(&targets, GroupTiming::Timed(Duration::from_secs(1)))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            LssDriverError::WaitTimeout {
+                id: 2,
+                status: MotorStatus::Traveling
+            }
+        ));
+        assert_relative_eq!(bus.servo(1, |servo| servo.position()).unwrap(), 90.0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn waits_until_settled() {
+        let (mut driver, _bus) = driver_with_bus(vec![VirtualServo::new(5, Model::ST1)]);
+        let options = WaitOptions::new(Duration::from_millis(10), Duration::from_secs(2));
+        driver.move_to_position(5, 90.0).await.unwrap();
+        let started = Instant::now();
+        let position = driver.wait_until_settled(5, 0.5, options).await.unwrap();
+        assert_relative_eq!(position, 90.0);
+        assert!(started.elapsed() >= Duration::from_millis(500));
+        driver.limp(5).await.unwrap();
+        driver
+            .wait_for_status(5, MotorStatus::Limp, options)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn waiting_times_out() {
+        let (mut driver, _bus) =
+            driver_with_bus(vec![VirtualServo::new(5, Model::ST1).with_speed_limit(10.0)]);
+        let options = WaitOptions::new(Duration::from_millis(30), Duration::from_millis(100));
+        driver.move_to_position(5, 90.0).await.unwrap();
+        let started = Instant::now();
+        let err = driver
+            .wait_for_status(5, MotorStatus::Holding, options)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            LssDriverError::WaitTimeout {
+                id: 5,
+                status: MotorStatus::Traveling
+            }
+        ));
+        assert_eq!(started.elapsed(), Duration::from_millis(100));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn waiting_stops_on_fault() {
+        let (mut driver, bus) = driver_with_bus(vec![
+            VirtualServo::new(1, Model::ST1),
+            VirtualServo::new(2, Model::ST1),
+        ]);
+        let options = WaitOptions::new(Duration::from_millis(10), Duration::from_secs(1));
+        assert_eq!(
+            driver.wait_until_any_fault(&[1, 2], options).await.unwrap(),
+            None
+        );
+        driver.move_to_position(2, 90.0).await.unwrap();
+        tokio::time::advance(Duration::from_millis(100)).await;
+        bus.servo(2, |servo| {
+            servo.trip_safe_mode(SafeModeStatus::CurrentLimit)
+        });
+        let err = driver
+            .wait_until_settled(2, 1.0, options)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            LssDriverError::ServoFault {
+                id: 2,
+                status: MotorStatus::SafeMode
+            }
+        ));
+        assert_eq!(
+            driver.wait_until_any_fault(&[1, 2], options).await.unwrap(),
+            Some((2, MotorStatus::SafeMode))
+        );
+        // waiting for the fault itself isn't an error
+        driver
+            .wait_for_status(2, MotorStatus::SafeMode, options)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn watching_survives_missed_replies() {
+        let (mut driver, bus) = driver_with_bus(vec![
+            VirtualServo::new(1, Model::ST1),
+            VirtualServo::new(2, Model::ST1),
+        ]);
+        driver.query_status(1).await.unwrap();
+        bus.set_faults(Faults {
+            drop_every: Some(2),
+            ..Faults::default()
+        });
+        bus.servo(2, |servo| {
+            servo.trip_safe_mode(SafeModeStatus::CurrentLimit)
+        });
+        // reply from servo 1 is dropped and servo 3 doesn't exist. Servo 2 is still watched
+        let options = WaitOptions::new(Duration::from_millis(10), Duration::from_secs(1));
+        assert_eq!(
+            driver
+                .wait_until_any_fault(&[1, 3, 2], options)
+                .await
+                .unwrap(),
+            Some((2, MotorStatus::SafeMode))
+        );
+        bus.set_faults(Faults::default());
+        let err = driver
+            .wait_until_any_fault(&[1, 3], options)
+            .await
+            .unwrap_err();
+        assert!(matches!(err.root_cause(), LssDriverError::TimeoutError));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rc_mode_makes_servo_unreachable() {
+        let (mut driver, bus) = driver_with_bus(vec![VirtualServo::new(5, Model::ST1)]);
+        driver
+            .configure_rc_mode(5, RcMode::Position, true)
+            .await
+            .unwrap();
+        // takes effect after reset
+        assert_eq!(driver.query_id(5).await.unwrap(), 5);
+        driver.reset(5).await.unwrap();
+        assert_eq!(
+            bus.servo(5, |servo| servo.rc_mode()),
+            Some(RcMode::Position)
+        );
+        assert!(matches!(
+            driver.query_id(5).await.unwrap_err().root_cause(),
+            LssDriverError::TimeoutError
+        ));
+        driver
+            .configure_rc_mode(5, RcMode::Serial, false)
+            .await
+            .unwrap();
+        driver.reset(5).await.unwrap();
+        assert_eq!(
+            bus.servo(5, |servo| servo.rc_mode()),
+            Some(RcMode::Position)
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn factory_defaults_are_restored() {
+        let (mut driver, bus) = driver_with_bus(vec![VirtualServo::new(5, Model::ST1)]);
+        driver.configure_color(5, LedColor::Red).await.unwrap();
+        driver.configure_baud_rate(5, 9600).await.unwrap();
+        // DEFAULT has no effect without CONFIRM right after it
+        bus.process("#5DEFAULT\r");
+        bus.process("#5QID\r");
+        bus.process("#5CONFIRM\r");
+        driver.reset(5).await.unwrap();
+        assert_eq!(driver.query_color(5).await.unwrap(), LedColor::Red);
+        driver.restore_factory_defaults(5, false).await.unwrap();
+        assert!(bus.servo(5, |_| ()).is_none());
+        assert_eq!(
+            driver.query_baud_rate(crate::FACTORY_ID).await.unwrap(),
+            115200
+        );
+    }
+
+    #[tokio::test]
+    async fn session_values_revert_on_reset() {
+        let (mut driver, bus) = driver_with_bus(vec![VirtualServo::new(5, Model::ST1)]);
+        driver.set_color(5, LedColor::Red).await.unwrap();
+        driver.configure_color(5, LedColor::Blue).await.unwrap();
+        driver.set_color(5, LedColor::Green).await.unwrap();
+        driver.configure_origin_offset(5, 10.0).await.unwrap();
+        driver.set_origin_offset_session(5, 20.0).await.unwrap();
+        assert_relative_eq!(driver.query_origin_offset(5).await.unwrap(), 20.0);
+        let configured = driver.query_configured_origin_offset(5).await.unwrap();
+        assert_relative_eq!(configured, 10.0);
+        assert_eq!(
+            driver.query_configured_color(5).await.unwrap(),
+            LedColor::Blue
+        );
+        driver.reset(5).await.unwrap();
+        assert_eq!(driver.query_color(5).await.unwrap(), LedColor::Blue);
+        assert_relative_eq!(driver.query_origin_offset(5).await.unwrap(), 10.0);
+        let offset = bus.servo(5, |servo| servo.configured_origin_offset());
+        assert_eq!(offset, Some(10.0));
+    }
+
+    #[tokio::test]
+    async fn origin_offset_shifts_reported_position() {
+        let (mut driver, _) = driver_with_bus(vec![VirtualServo::new(5, Model::ST1)]);
+        driver.set_origin_offset_session(5, 10.0).await.unwrap();
+        assert_relative_eq!(driver.query_position(5).await.unwrap(), -10.0);
+    }
+
+    #[tokio::test]
+    async fn safe_mode_stops_motion() {
+        let (mut driver, bus) = driver_with_bus(vec![VirtualServo::new(5, Model::ST1)]);
+        bus.servo(5, |servo| {
+            servo.trip_safe_mode(SafeModeStatus::TemperatureLimit)
+        });
+        driver.move_to_position(5, 90.0).await.unwrap();
+        assert_eq!(driver.query_status(5).await.unwrap(), MotorStatus::SafeMode);
+        assert_eq!(
+            driver.query_safety_status(5).await.unwrap(),
+            SafeModeStatus::TemperatureLimit
+        );
+        assert_relative_eq!(driver.query_target_position(5).await.unwrap(), 0.0);
+        driver.reset(5).await.unwrap();
+        assert_eq!(driver.query_status(5).await.unwrap(), MotorStatus::Limp);
+    }
+
+    #[tokio::test]
+    async fn dropped_and_garbled_responses() {
+        let faults = Faults {
+            drop_every: Some(3),
+            garble_every: Some(2),
+            ..Faults::default()
+        };
+        let bus = VirtualBus::new(vec![VirtualServo::new(5, Model::ST1)]).with_faults(faults);
+        let mut driver = LSSDriver::with_driver(Box::new(bus));
+        assert!(driver.query_position(5).await.is_ok());
+        assert!(matches!(
+            driver.query_position(5).await.unwrap_err().root_cause(),
+            LssDriverError::PacketParsingError(_)
+        ));
+        assert!(matches!(
+            driver.query_position(5).await.unwrap_err().root_cause(),
+            LssDriverError::TimeoutError
+        ));
+    }
+
+    #[tokio::test]
+    async fn safe_mode_trips_after_commands() {
+        let faults = Faults {
+            safe_mode_after: Some(2),
+            safe_mode_reason: SafeModeStatus::InputVoltageOutOfRange,
+            ..Faults::default()
+        };
+        let bus = VirtualBus::new(vec![VirtualServo::new(5, Model::ST1)]).with_faults(faults);
+        let mut driver = LSSDriver::with_driver(Box::new(bus));
+        assert_eq!(driver.query_status(5).await.unwrap(), MotorStatus::Limp);
+        assert_eq!(driver.query_status(5).await.unwrap(), MotorStatus::SafeMode);
+        assert_eq!(
+            driver.query_safety_status(5).await.unwrap(),
+            SafeModeStatus::InputVoltageOutOfRange
+        );
+    }
+
+    #[tokio::test]
+    async fn broadcast_commands_reach_every_servo() {
+        let (mut driver, bus) = driver_with_bus(vec![
+            VirtualServo::new(1, Model::ST1),
+            VirtualServo::new(2, Model::ST1),
+        ]);
+        driver
+            .set_color(BROADCAST_ID, LedColor::Cyan)
+            .await
+            .unwrap();
+        assert_eq!(bus.servo(1, |servo| servo.color()), Some(LedColor::Cyan));
+        assert_eq!(bus.servo(2, |servo| servo.color()), Some(LedColor::Cyan));
+    }
+
+    #[tokio::test]
+    async fn new_id_takes_effect_after_reset() {
+        let (mut driver, _) = driver_with_bus(vec![VirtualServo::new(5, Model::ST1)]);
+        driver.set_id(5, 6).await.unwrap();
+        assert_eq!(driver.query_id(BROADCAST_ID).await.unwrap(), 5);
+        driver.reset(5).await.unwrap();
+        assert_eq!(driver.query_id(BROADCAST_ID).await.unwrap(), 6);
+    }
+}
@@ -0,0 +1,201 @@
+use crate::message_types::LssDriverError;
+use crate::transport::{FramedDriver, LssCodec, LssCommand, LssResponse};
+use async_trait::async_trait;
+use futures::{SinkExt, StreamExt};
+use std::io;
+use tokio::net::TcpStream;
+use tokio::time::{timeout, Duration};
+use tokio_util::codec::{Decoder, Framed};
+
+type DriverResult<T> = Result<T, LssDriverError>;
+
+/// Network adds latency on top of the serial bus so this is more lenient than the serial timeout
+const DEFAULT_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// [FramedDriver] implementation over TCP
+///
+/// Meant for servos connected through a ser2net style serial-to-Ethernet bridge.
+/// The bridge is expected to forward raw bytes between the socket and the bus.
+///
+/// If the connection drops the current call fails with [LssDriverError::ConnectionLost]
+/// and the driver reconnects on the next `send`.
+/// A frame that isn't valid text only fails that receive with [LssDriverError::ReceivingError].
+pub struct FramedTcpDriver {
+    address: String,
+    framed_stream: Option<Framed<TcpStream, LssCodec>>,
+}
+
+impl FramedTcpDriver {
+    /// Connect to a serial bridge
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - Address of the bridge. e.g. 192.168.1.10:4000
+    pub async fn new(address: &str) -> DriverResult<FramedTcpDriver> {
+        let framed_stream = connect(address)
+            .await
+            .map_err(LssDriverError::FailedConnecting)?;
+        Ok(FramedTcpDriver {
+            address: address.to_owned(),
+            framed_stream: Some(framed_stream),
+        })
+    }
+}
+
+async fn connect(address: &str) -> io::Result<Framed<TcpStream, LssCodec>> {
+    let stream = TcpStream::connect(address).await?;
+    stream.set_nodelay(true)?;
+    Ok(LssCodec.framed(stream))
+}
+
+fn not_connected() -> LssDriverError {
+    LssDriverError::ConnectionLost(io::Error::new(
+        io::ErrorKind::NotConnected,
+        "Not connected to bridge",
+    ))
+}
+
+#[async_trait]
+impl FramedDriver for FramedTcpDriver {
+    async fn send(&mut self, command: LssCommand) -> DriverResult<()> {
+        if self.framed_stream.is_none() {
+            let framed_stream = connect(&self.address)
+                .await
+                .map_err(LssDriverError::ConnectionLost)?;
+            self.framed_stream = Some(framed_stream);
+        }
+        let stream = self.framed_stream.as_mut().ok_or_else(not_connected)?;
+        if let Err(err) = stream.send(command).await {
+            self.framed_stream = None;
+            return Err(LssDriverError::ConnectionLost(err));
+        }
+        Ok(())
+    }
+
+    async fn receive(&mut self) -> DriverResult<LssResponse> {
+        self.receive_timeout(DEFAULT_TIMEOUT).await
+    }
+
+    async fn receive_timeout(&mut self, timeout_duration: Duration) -> DriverResult<LssResponse> {
+        let stream = self.framed_stream.as_mut().ok_or_else(not_connected)?;
+        let response = timeout(timeout_duration, stream.next())
+            .await
+            .map_err(|_| LssDriverError::TimeoutError)?;
+        match response {
+            Some(Ok(response)) => Ok(response),
+            Some(Err(err)) if err.kind() == io::ErrorKind::InvalidData => {
+                // Framed ends the stream after a decode error. The invalid frame was already
+                // consumed so rebuild it on the same socket and keep what is buffered
+                if let Some(stream) = self.framed_stream.take() {
+                    self.framed_stream = Some(Framed::from_parts(stream.into_parts()));
+                }
+                Err(LssDriverError::ReceivingError(err))
+            }
+            Some(Err(err)) => {
+                self.framed_stream = None;
+                Err(LssDriverError::ConnectionLost(err))
+            }
+            None => {
+                self.framed_stream = None;
+                Err(LssDriverError::ConnectionLost(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Bridge closed the connection",
+                )))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    async fn reply_once(listener: &TcpListener, expected: &[u8], reply: &[u8]) -> TcpStream {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut buffer = vec![0; expected.len()];
+        socket.read_exact(&mut buffer).await.unwrap();
+        assert_eq!(buffer, expected);
+        socket.write_all(reply).await.unwrap();
+        socket
+    }
+
+    #[tokio::test]
+    async fn query_over_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = tokio::spawn(async move {
+            reply_once(&listener, b"#5QV\r", b"*5QV11200\r").await;
+        });
+        let mut driver = FramedTcpDriver::new(&address).await.unwrap();
+        driver.send(LssCommand::simple(5, "QV")).await.unwrap();
+        let response = driver.receive().await.unwrap();
+        assert_eq!(response.as_str(), "*5QV11200\r");
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn connection_loss_is_reported_and_recovered() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let mut driver = FramedTcpDriver::new(&address).await.unwrap();
+        let (socket, _) = listener.accept().await.unwrap();
+        drop(socket);
+
+        let err = driver.receive().await.unwrap_err();
+        assert!(matches!(err, LssDriverError::ConnectionLost(_)));
+
+        let server = tokio::spawn(async move {
+            reply_once(&listener, b"#5QV\r", b"*5QV11200\r").await;
+        });
+        driver.send(LssCommand::simple(5, "QV")).await.unwrap();
+        let response = driver.receive().await.unwrap();
+        assert_eq!(response.as_str(), "*5QV11200\r");
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn invalid_frame_keeps_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server =
+            tokio::spawn(
+                async move { reply_once(&listener, b"#5QV\r", b"\xff\r*5QV11200\r").await },
+            );
+        let mut driver = FramedTcpDriver::new(&address).await.unwrap();
+        driver.send(LssCommand::simple(5, "QV")).await.unwrap();
+        let err = driver.receive().await.unwrap_err();
+        assert!(matches!(err, LssDriverError::ReceivingError(_)));
+        let response = driver.receive().await.unwrap();
+        assert_eq!(response.as_str(), "*5QV11200\r");
+        // still the same socket
+        let _socket = server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn failed_reconnect_keeps_source() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let mut driver = FramedTcpDriver::new(&address).await.unwrap();
+        let (socket, _) = listener.accept().await.unwrap();
+        drop(socket);
+        drop(listener);
+        driver.receive().await.unwrap_err();
+
+        let err = driver.send(LssCommand::simple(5, "QV")).await.unwrap_err();
+        let source = std::error::Error::source(&err)
+            .and_then(|source| source.downcast_ref::<io::Error>())
+            .unwrap();
+        assert_eq!(source.kind(), io::ErrorKind::ConnectionRefused);
+    }
+
+    #[tokio::test]
+    async fn failed_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        drop(listener);
+        let res = FramedTcpDriver::new(&address).await;
+        assert!(matches!(res, Err(LssDriverError::FailedConnecting(_))));
+    }
+}
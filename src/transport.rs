@@ -0,0 +1,581 @@
+//! Transport layer used by [LSSDriver](crate::LSSDriver)
+//!
+//! The driver talks to the bus through the [FramedDriver] trait.
+//! Implement it if you want to talk to servos over something other than a local serial port
+//! or if you need a test double.
+//!
+//! All messages on the bus are ASCII frames terminated by a carriage return (`\r`).
+//! Commands start with `#` and responses start with `*`.
+//! [LssCodec] implements this framing for anything that is `AsyncRead + AsyncWrite`.
+
+use crate::message_types::{CommandModifier, LssDriverError};
+use crate::protocol::{CommandFrame, Reply, ReplyFrame};
+use crate::BROADCAST_ID;
+use async_trait::async_trait;
+use bytes::{BufMut, BytesMut};
+use std::{io, str};
+use tokio::time::{timeout, Duration};
+use tokio_util::codec::{Decoder, Encoder};
+
+type DriverResult<T> = Result<T, LssDriverError>;
+
+/// Single framed command sent to the bus
+///
+/// Serialized form includes the `#` prefix and the `\r` terminator. e.g. `#5QD\r`
+#[derive(PartialEq, Clone, Debug)]
+pub struct LssCommand {
+    message: String,
+}
+
+impl LssCommand {
+    /// Command with a single numeric parameter. e.g. `#5D1800\r`
+    pub fn with_param(id: u8, cmd: &str, val: i32) -> LssCommand {
+        LssCommand {
+            message: format!("#{}{}{}\r", id, cmd, val),
+        }
+    }
+
+    /// Command with a single numeric parameter and a modifier. e.g. `#5D1800T1000\r`
+    pub fn with_param_modifier(
+        id: u8,
+        cmd: &str,
+        val: i32,
+        modifier: CommandModifier,
+    ) -> LssCommand {
+        LssCommand {
+            message: format!("#{}{}{}{}\r", id, cmd, val, modifier.to_msg()),
+        }
+    }
+
+    /// Command with a single numeric parameter and multiple modifiers. e.g. `#5D1800T1000CH400\r`
+    pub fn with_param_modifiers(
+        id: u8,
+        cmd: &str,
+        val: i32,
+        modifiers: &[CommandModifier],
+    ) -> LssCommand {
+        LssCommand {
+            message: format!(
+                "#{}{}{}{}\r",
+                id,
+                cmd,
+                val,
+                CommandModifier::vec_to_msg(modifiers)
+            ),
+        }
+    }
+
+    /// Command without parameters. e.g. `#5QD\r`
+    pub fn simple(id: u8, cmd: &str) -> LssCommand {
+        LssCommand {
+            message: format!("#{}{}\r", id, cmd),
+        }
+    }
+
+    /// Several commands sent back-to-back in a single write. e.g. `#1D100\r#2D200\r`
+    pub fn batch(frames: &[CommandFrame]) -> LssCommand {
+        LssCommand {
+            message: frames.iter().map(|frame| frame.to_string()).collect(),
+        }
+    }
+
+    /// Serialized command including the terminator
+    pub fn as_bytes(&self) -> &[u8] {
+        self.message.as_bytes()
+    }
+
+    /// Serialized command including the terminator
+    pub fn as_str(&self) -> &str {
+        &self.message
+    }
+
+    /// ID of the servo the command is addressed to
+    ///
+    /// ID of the first command for a [batch](LssCommand::batch)
+    pub fn id(&self) -> Option<u8> {
+        let body = self.message.strip_prefix('#')?;
+        let end = body
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(body.len());
+        body[..end].parse().ok()
+    }
+}
+
+impl From<CommandFrame> for LssCommand {
+    fn from(frame: CommandFrame) -> Self {
+        LssCommand {
+            message: frame.to_string(),
+        }
+    }
+}
+
+/// Single framed response received from the bus
+///
+/// Contains the whole frame including the `*` prefix and the `\r` terminator. e.g. `*5QD1800\r`
+#[derive(PartialEq, Clone, Debug)]
+pub struct LssResponse {
+    message: String,
+}
+
+impl LssResponse {
+    /// Wrap a received frame
+    ///
+    /// The frame is expected to include the `*` prefix and `\r` terminator
+    pub fn new(message: String) -> LssResponse {
+        LssResponse { message }
+    }
+
+    /// Raw frame as received
+    pub fn as_str(&self) -> &str {
+        &self.message
+    }
+
+    /// ID of the servo that sent the response
+    ///
+    /// Returns `None` for responses that don't contain an ID such as QID
+    pub fn id(&self) -> Option<u8> {
+        let body = self.message.strip_prefix('*')?;
+        let end = body
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(body.len());
+        body[..end].parse().ok()
+    }
+
+    /// Part of the response after the echoed command if it came from servo `id`
+    fn value_after(&self, id: u8, echo: &str) -> Option<&str> {
+        let body = self.message.strip_prefix('*')?.trim_end_matches('\r');
+        let command = body.trim_start_matches(|c: char| c.is_ascii_digit());
+        if id != BROADCAST_ID && self.id().map(|response_id| response_id != id) == Some(true) {
+            return None;
+        }
+        command.strip_prefix(echo)
+    }
+
+    /// Check that this is a response from servo `id` to a query echoed as `echo`
+    ///
+    /// Responses without an ID (such as QID) and queries sent to [BROADCAST_ID] match any servo.
+    /// The value has to follow the echo directly so that e.g. `QD` doesn't match a response to `QDT`.
+    pub fn is_response_to(&self, id: u8, echo: &str) -> bool {
+        self.value_after(id, echo)
+            .map(|value| !value.starts_with(|c: char| c.is_ascii_uppercase()))
+            .unwrap_or(false)
+    }
+
+    /// Same as [is_response_to](LssResponse::is_response_to) for queries that return text
+    ///
+    /// Such as QMS
+    pub fn is_text_response_to(&self, id: u8, echo: &str) -> bool {
+        self.value_after(id, echo).is_some()
+    }
+
+    /// Parse the response and check that it echoes `separator`
+    fn reply_to(&self, separator: &str) -> DriverResult<ReplyFrame> {
+        let frame: ReplyFrame = self.message.parse()?;
+        if frame.reply.query().echo() != separator {
+            return Err(LssDriverError::PacketParsingError(format!(
+                "Expected {} response, got {:?}",
+                separator, self.message
+            )));
+        }
+        Ok(frame)
+    }
+
+    /// Split response into ID and numeric value
+    ///
+    /// `separator` is the echoed command. e.g. `QD` for `*5QD1800\r`
+    ///
+    /// Returns [LssDriverError::PacketParsingError] if the frame isn't a valid response
+    pub fn separate(&self, separator: &str) -> DriverResult<(u8, i32)> {
+        let frame = self.reply_to(separator)?;
+        let value = match &frame.reply {
+            Reply::Value(_, value) => *value,
+            Reply::Text(_, text) => text.parse().map_err(|_| {
+                LssDriverError::PacketParsingError(format!(
+                    "Failed parsing value from {:?}",
+                    self.message
+                ))
+            })?,
+            Reply::Empty(_) => {
+                return Err(LssDriverError::PacketParsingError(format!(
+                    "Failed to extract value from {:?}",
+                    self.message
+                )))
+            }
+        };
+        Ok((frame.id, value))
+    }
+
+    /// Split response into ID and string value
+    ///
+    /// Used for queries that return text such as QMS
+    pub fn separate_string(&self, separator: &str) -> DriverResult<(u8, String)> {
+        let frame = self.reply_to(separator)?;
+        let value = match frame.reply {
+            Reply::Value(_, value) => value.to_string(),
+            Reply::Text(_, text) => text,
+            Reply::Empty(_) => String::new(),
+        };
+        Ok((frame.id, value))
+    }
+
+    /// Similar to separate but doesn't parse the ID
+    /// This is useful for queries that don't return ID
+    ///
+    /// Such as QID
+    pub fn get_val(&self, separator: &str) -> DriverResult<i32> {
+        self.separate(separator).map(|(_, value)| value)
+    }
+}
+
+/// Commands and responses that [FramedDriver::transaction] exchanges without anything else in between
+pub struct Transaction {
+    /// Commands written in order
+    pub commands: Vec<LssCommand>,
+    /// Number of accepted responses to read after sending
+    pub expected_replies: usize,
+    /// How long to wait for each response. `None` reads with [FramedDriver::receive]
+    pub timeout: Option<Duration>,
+    /// Read and discard frames that are already waiting before anything is sent
+    pub drain_stale: bool,
+    /// Whether a response belongs to this transaction. Other frames are discarded and don't count
+    pub accept: Box<dyn FnMut(&LssResponse) -> bool + Send>,
+}
+
+/// Responses read by [FramedDriver::transaction]
+#[derive(Debug, Default)]
+pub struct TransactionResponses {
+    /// Accepted responses in the order they arrived
+    pub responses: Vec<LssResponse>,
+    /// Frames that weren't accepted, including stale frames drained before sending
+    pub discarded: Vec<LssResponse>,
+    /// Error that stopped reading before all responses arrived. e.g. a timeout
+    pub error: Option<LssDriverError>,
+}
+
+/// Codec implementing LSS framing
+///
+/// Decodes `\r` terminated frames into [LssResponse] and encodes [LssCommand]
+///
+/// # Example
+///
+/// ```
+/// use lss_driver::transport::LssCodec;
+/// use tokio::io::{AsyncRead, AsyncWrite};
+/// use tokio_util::codec::{Decoder, Framed};
+///
+/// fn frame<T: AsyncRead + AsyncWrite>(io: T) -> Framed<T, LssCodec> {
+///     LssCodec.framed(io)
+/// }
+/// ```
+pub struct LssCodec;
+
+impl Decoder for LssCodec {
+    type Item = LssResponse;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let command_break = src.as_ref().iter().position(|b| *b == b'\r');
+        if let Some(n) = command_break {
+            let line = src.split_to(n + 1);
+            return match str::from_utf8(line.as_ref()) {
+                Ok(s) => Ok(Some(LssResponse::new(s.to_owned()))),
+                Err(_) => Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid String")),
+            };
+        }
+        Ok(None)
+    }
+}
+
+impl Encoder<LssCommand> for LssCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, data: LssCommand, buf: &mut BytesMut) -> Result<(), io::Error> {
+        let msg = data.as_bytes();
+        buf.reserve(msg.len());
+        buf.put(msg);
+        Ok(())
+    }
+}
+
+/// Framed transport used by [LSSDriver](crate::LSSDriver)
+///
+/// Implementations have to uphold the following contract:
+///
+/// * `send` writes exactly one command to the bus. It must not wait for a response.
+/// * `receive` returns the next complete frame in the order it arrived on the bus.
+///   Frames are terminated by `\r` and the returned [LssResponse] includes the terminator.
+/// * `receive` must return [LssDriverError::TimeoutError] if no complete frame arrives in time.
+///   Servos don't answer commands addressed to IDs that aren't on the bus so
+///   the driver relies on this to not wait forever.
+///   [FramedSerialDriver](crate::FramedSerialDriver) uses a timeout of 10ms by default.
+/// * `receive_timeout` does the same as `receive` but waits at most `timeout`.
+///   [LSSDriver](crate::LSSDriver) always reads through it so that its configured timeout is used.
+///   The default implementation wraps `receive`, which means the transport's own
+///   timeout still applies if it is shorter. Transports with their own timeout should override it.
+/// * Partially received frames must be kept for the next call to `receive`.
+///   A timeout should never drop or split data.
+/// * `query` and `query_timeout` send a command and return the response to it.
+///   The default implementations call `send` followed by `receive`/`receive_timeout`.
+/// * `transaction` sends several commands and reads their responses.
+///   The default implementation calls `send` and `receive_timeout`.
+/// * Transports shared by multiple users should override `query`, `query_timeout` and `transaction`
+///   so that nothing else is sent or read before they are done.
+///
+/// # Example
+///
+/// ```
+/// use async_trait::async_trait;
+/// use lss_driver::transport::{FramedDriver, LssCommand, LssResponse};
+/// use lss_driver::{LSSDriver, LssDriverError};
+///
+/// /// Test double that reports every servo at 90 degrees
+/// struct FixedPosition {
+///     pending: Option<u8>,
+/// }
+///
+/// #[async_trait]
+/// impl FramedDriver for FixedPosition {
+///     async fn send(&mut self, command: LssCommand) -> Result<(), LssDriverError> {
+///         let id = command.as_str()[1..].split("QD").next().unwrap().parse().unwrap();
+///         self.pending = Some(id);
+///         Ok(())
+///     }
+///
+///     async fn receive(&mut self) -> Result<LssResponse, LssDriverError> {
+///         let id = self.pending.take().ok_or(LssDriverError::TimeoutError)?;
+///         Ok(LssResponse::new(format!("*{}QD900\r", id)))
+///     }
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let mut driver = LSSDriver::with_driver(Box::new(FixedPosition { pending: None }));
+///     assert_eq!(driver.query_position(5).await.unwrap(), 90.0);
+/// }
+/// ```
+#[async_trait]
+pub trait FramedDriver {
+    /// Write a single command to the bus
+    async fn send(&mut self, command: LssCommand) -> DriverResult<()>;
+    /// Read the next response frame from the bus
+    async fn receive(&mut self) -> DriverResult<LssResponse>;
+    /// Read the next response frame from the bus waiting at most `timeout`
+    async fn receive_timeout(&mut self, timeout_duration: Duration) -> DriverResult<LssResponse> {
+        timeout(timeout_duration, self.receive())
+            .await
+            .map_err(|_| LssDriverError::TimeoutError)?
+    }
+    /// Send a command and read the response to it
+    async fn query(&mut self, command: LssCommand) -> DriverResult<LssResponse> {
+        self.send(command).await?;
+        self.receive().await
+    }
+    /// Send a command and read the response to it waiting at most `timeout`
+    async fn query_timeout(
+        &mut self,
+        command: LssCommand,
+        timeout_duration: Duration,
+    ) -> DriverResult<LssResponse> {
+        self.send(command).await?;
+        self.receive_timeout(timeout_duration).await
+    }
+    /// Send commands and read responses to them
+    ///
+    /// Stops reading at the first error, which is returned in [TransactionResponses::error].
+    /// A sending error fails the whole transaction.
+    async fn transaction(
+        &mut self,
+        mut transaction: Transaction,
+    ) -> DriverResult<TransactionResponses> {
+        let mut result = TransactionResponses::default();
+        if transaction.drain_stale {
+            while let Ok(frame) = self.receive_timeout(Duration::ZERO).await {
+                result.discarded.push(frame);
+            }
+        }
+        for command in transaction.commands {
+            self.send(command).await?;
+        }
+        while result.responses.len() < transaction.expected_replies {
+            let response = match transaction.timeout {
+                Some(timeout_duration) => self.receive_timeout(timeout_duration).await,
+                None => self.receive().await,
+            };
+            match response {
+                Ok(response) if (transaction.accept)(&response) => result.responses.push(response),
+                Ok(response) => result.discarded.push(response),
+                Err(error) => {
+                    result.error = Some(error);
+                    break;
+                }
+            }
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::BytesMut;
+
+    #[test]
+    fn framing_returns_none() {
+        let mut payload = BytesMut::from("*5QV11200");
+        let mut codec = LssCodec {};
+        let res = codec.decode(&mut payload).unwrap();
+        assert_eq!(res, None);
+    }
+
+    #[test]
+    fn framing_returns_twice() {
+        let mut payload = BytesMut::from("*1QV1\r*2QV2\r");
+        let mut codec = LssCodec {};
+        let res = codec.decode(&mut payload).unwrap().unwrap();
+        let (id, val) = res.separate("QV").unwrap();
+        assert_eq!(id, 1);
+        assert_eq!(val, 1);
+        let res = codec.decode(&mut payload).unwrap().unwrap();
+        let (id, val) = res.separate("QV").unwrap();
+        assert_eq!(id, 2);
+        assert_eq!(val, 2);
+        let res = codec.decode(&mut payload).unwrap();
+        assert_eq!(res, None);
+    }
+
+    #[test]
+    fn query_voltage_gets_extracted_from_frame() {
+        let mut payload = BytesMut::from("*5QV11200\r");
+        let mut codec = LssCodec {};
+        let res = codec.decode(&mut payload).unwrap().unwrap();
+        let (id, val) = res.separate("QV").unwrap();
+        assert_eq!(id, 5);
+        assert_eq!(val, 11200);
+    }
+
+    #[test]
+    fn framing_encodes_single_command() {
+        let mut payload = BytesMut::default();
+        let mut codec = LssCodec {};
+        let command = LssCommand::simple(5, "QV");
+        codec.encode(command, &mut payload).unwrap();
+        assert_eq!(&payload[..], b"#5QV\r");
+    }
+
+    #[test]
+    fn framing_encodes_multiple_commands() {
+        let mut payload = BytesMut::default();
+        let mut codec = LssCodec {};
+        let command_1 = LssCommand::simple(5, "QV");
+        let command_2 = LssCommand::simple(5, "QT");
+        codec.encode(command_1, &mut payload).unwrap();
+        codec.encode(command_2, &mut payload).unwrap();
+        assert_eq!(&payload[..], b"#5QV\r#5QT\r");
+    }
+
+    #[test]
+    fn batch_serializes() {
+        let command = LssCommand::batch(&[
+            CommandFrame::new(1, crate::protocol::Command::Move(100)),
+            CommandFrame::new(2, crate::protocol::Command::Limp),
+        ]);
+        assert_eq!(command.as_str(), "#1D100\r#2L\r");
+        assert_eq!(command.id(), Some(1));
+    }
+
+    #[test]
+    fn simple_command_serializes() {
+        let command = LssCommand::simple(1, "QV");
+        assert_eq!(command.as_bytes(), b"#1QV\r")
+    }
+
+    #[test]
+    fn param_command_serializes() {
+        let command = LssCommand::with_param(1, "D", 10);
+        assert_eq!(command.as_bytes(), b"#1D10\r")
+    }
+
+    #[test]
+    fn response_splits() {
+        let res = LssResponse::new("*5QF42\r".to_owned());
+        let (id, val) = res.separate("QF").unwrap();
+        assert_eq!(id, 5);
+        assert_eq!(val, 42);
+    }
+
+    #[test]
+    fn response_splits_string() {
+        let res = LssResponse::new("*5QFHEH\r".to_owned());
+        let (id, val) = res.separate_string("QF").unwrap();
+        assert_eq!(id, 5);
+        assert_eq!(val, "HEH");
+    }
+
+    #[test]
+    fn response_fail_missing_val() {
+        let res = LssResponse::new("*5QF\r".to_owned());
+        let err = res.separate("QF");
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn response_fail_missing_id() {
+        let res = LssResponse::new("*QF1\r".to_owned());
+        let err = res.separate("QF");
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn response_fail_wrong_key_split() {
+        let res = LssResponse::new("*1QF2\r".to_owned());
+        let err = res.separate("ZA");
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn command_id() {
+        assert_eq!(LssCommand::simple(5, "QD").id(), Some(5));
+        assert_eq!(LssCommand::with_param(254, "D", 10).id(), Some(254));
+    }
+
+    #[test]
+    fn response_id() {
+        assert_eq!(LssResponse::new("*5QD1800\r".to_owned()).id(), Some(5));
+        assert_eq!(LssResponse::new("*QID5\r".to_owned()).id(), None);
+    }
+
+    #[test]
+    fn response_matches_query() {
+        let res = LssResponse::new("*5QD1800\r".to_owned());
+        assert!(res.is_response_to(5, "QD"));
+        assert!(res.is_response_to(BROADCAST_ID, "QD"));
+        assert!(!res.is_response_to(3, "QD"));
+        assert!(!res.is_response_to(5, "QDT"));
+        assert!(!res.is_response_to(5, "Q"));
+        let res = LssResponse::new("*5QDT1800\r".to_owned());
+        assert!(!res.is_response_to(5, "QD"));
+        let res = LssResponse::new("*QID5\r".to_owned());
+        assert!(res.is_response_to(BROADCAST_ID, "QID"));
+        let res = LssResponse::new("*5QMSLSS-ST1\r".to_owned());
+        assert!(!res.is_response_to(5, "QMS"));
+        assert!(res.is_text_response_to(5, "QMS"));
+        assert!(!res.is_text_response_to(4, "QMS"));
+    }
+
+    #[test]
+    fn response_fail_short_frame() {
+        for frame in ["\r", "*\r", ""] {
+            let res = LssResponse::new(frame.to_owned());
+            assert!(res.separate("QD").is_err());
+            assert!(res.separate_string("QD").is_err());
+            assert!(res.get_val("QD").is_err());
+        }
+    }
+
+    #[test]
+    fn response_val_only() {
+        let res = LssResponse::new("*QID5\r".to_owned());
+        let val = res.get_val("QID").unwrap();
+        assert_eq!(val, 5);
+    }
+}